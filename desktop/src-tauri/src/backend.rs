@@ -1,26 +1,318 @@
 // Roura Agent Desktop - Python Backend Integration
 // © Roura.io
 
+use crate::error::CommandError;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
-use tauri::AppHandle;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
 
 /// Backend status
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BackendStatus {
     pub running: bool,
     pub port: Option<u16>,
     pub version: Option<String>,
     pub pid: Option<u32>,
+    /// Interpreter the backend was launched with
+    #[serde(default)]
+    pub python: Option<String>,
+    #[serde(default)]
+    pub transport: Transport,
+    /// Whether the backend outlives the app
+    #[serde(default)]
+    pub detached: bool,
+}
+
+/// How the desktop exchanges requests with the backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    /// HTTP on a loopback port
+    #[default]
+    Http,
+    /// Newline-delimited JSON-RPC over the child's stdin and stdout, with no port bound
+    Stdio,
+}
+
+/// Coarse backend health, from least to most ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    /// No backend process is tracked
+    NotRunning,
+    /// The process is tracked but `/health` didn't answer
+    Unreachable,
+    /// `/health` answered but reported `ok: false`
+    Unhealthy,
+    Healthy,
+}
+
+/// Result of probing the backend's `/health` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub state: HealthState,
+    pub ok: bool,
+    pub uptime_secs: Option<f64>,
+    /// Model the backend is currently serving
+    pub model: Option<String>,
+    /// Requests waiting to be processed
+    pub queue_depth: Option<u64>,
+    /// Why the backend couldn't be reached, when unreachable
+    pub error: Option<String>,
+}
+
+/// Body of the backend's `/health` response
+#[derive(Debug, Deserialize)]
+struct HealthResponse {
+    #[serde(default)]
+    ok: bool,
+    #[serde(default, alias = "uptime")]
+    uptime_secs: Option<f64>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    queue_depth: Option<u64>,
+}
+
+/// A single line of backend output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendLogLine {
+    /// Either "stdout" or "stderr"
+    pub stream: String,
+    pub line: String,
+    /// Backend launch that wrote the line, see `BACKEND_RUN`
+    #[serde(skip)]
+    pub run: u64,
+}
+
+/// Lines coalesced into one `backend-log-batch` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendLogBatch {
+    pub lines: Vec<BackendLogLine>,
+    /// Lines discarded because the batch outgrew the buffer between flushes
+    pub dropped: usize,
+}
+
+/// How the backend was launched, so the supervisor can relaunch it identically
+#[derive(Debug, Clone)]
+struct LaunchSpec {
+    python: String,
+    /// Unused with the stdio transport
+    port: u16,
+    transport: Transport,
+    /// Runs in its own session and is left running when the app exits
+    detached: bool,
+}
+
+/// Contents of `backend.pid`, written for a detached backend so a later launch can find it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PidFile {
+    pid: u32,
+    port: u16,
+    python: String,
+}
+
+/// Restart policy for a crashed backend
+#[derive(Debug, Clone)]
+struct RestartPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RestartPolicy {
+    /// Load the policy from config, falling back to 1s doubling up to 30s, 5 attempts
+    fn load() -> Self {
+        let ms = |key: &str, default: u64| {
+            Duration::from_millis(
+                crate::commands::config_value(key)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(default),
+            )
+        };
+
+        Self {
+            // A hand-edited config can hold anything; saturate rather than wrap
+            max_attempts: crate::commands::config_value("backend.restart_max_attempts")
+                .and_then(|v| v.as_u64())
+                .map_or(5, |v| u32::try_from(v).unwrap_or(u32::MAX)),
+            initial_delay: ms("backend.restart_initial_delay_ms", 1_000),
+            max_delay: ms("backend.restart_max_delay_ms", 30_000),
+        }
+    }
+
+    /// Delay before the given (1-based) restart attempt
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+/// Retry policy for HTTP calls made while the backend may still be binding its socket
+#[derive(Debug, Clone)]
+struct HttpRetry {
+    retries: u32,
+    base_delay: Duration,
+}
+
+impl HttpRetry {
+    /// Load the policy from config, falling back to 3 retries at 250ms, 500ms, 1s
+    fn load() -> Self {
+        let value = |key: &str, default: u64| {
+            crate::commands::config_value(key)
+                .and_then(|v| v.as_u64())
+                .unwrap_or(default)
+        };
+
+        Self {
+            retries: u32::try_from(value("backend.http_retries", 3)).unwrap_or(u32::MAX),
+            base_delay: Duration::from_millis(value("backend.http_retry_base_delay_ms", 250)),
+        }
+    }
+}
+
+/// Send a request built by `build`, retrying connection failures with backoff.
+///
+/// Only errors where no connection was established are retried, so a POST is
+/// never delivered twice. Error statuses are returned to the caller untouched.
+pub(crate) async fn send_with_retry(
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    let policy = HttpRetry::load();
+    let mut attempt = 0;
+
+    loop {
+        match build().send().await {
+            Err(e) if e.is_connect() && attempt < policy.retries => {
+                let factor = 2u32.saturating_pow(attempt);
+                tokio::time::sleep(policy.base_delay.saturating_mul(factor)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
 }
 
 /// Global backend process state
 static BACKEND_PROCESS: Mutex<Option<Child>> = Mutex::new(None);
 static BACKEND_PORT: Mutex<Option<u16>> = Mutex::new(None);
+static BACKEND_LAUNCH: Mutex<Option<LaunchSpec>> = Mutex::new(None);
+static BACKEND_STARTED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// A detached backend from an earlier launch, found through its pid file.
+///
+/// It isn't our child, so it can't be waited on or restarted after a crash.
+static ADOPTED_PID: Mutex<Option<u32>> = Mutex::new(None);
+
+/// Set by `stop_backend` so an intentional shutdown is never treated as a crash
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// App handle used to emit backend events from background tasks
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// How often the supervisor checks the tracked process
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A backend that stayed up this long is considered healthy again
+const RESTART_STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Set while `start_backend` is spawning and waiting for readiness
+static BACKEND_STARTING: AtomicBool = AtomicBool::new(false);
+
+/// Time of the last request to the backend, for idle shutdown
+static LAST_ACTIVITY: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Requests currently using the backend; it is never idle while this is non-zero
+static REQUESTS_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Set when the backend was stopped for inactivity, so the next request restarts it
+static IDLE_STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// Serializes restarts after an idle stop between concurrent requests
+static IDLE_RESUME: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// How often the idle supervisor checks for inactivity
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default time to wait for a freshly spawned backend to answer
+const DEFAULT_READY_TIMEOUT_SECS: u64 = 15;
+
+/// Interval between readiness probes
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Number of stderr lines reported when the backend dies during startup
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Recent backend output, oldest first
+static BACKEND_LOGS: Mutex<VecDeque<BackendLogLine>> = Mutex::new(VecDeque::new());
+
+/// Number of output lines kept in memory
+const BACKEND_LOG_CAPACITY: usize = 1000;
+
+/// Bumped at each launch so output from earlier runs can be told apart
+static BACKEND_RUN: AtomicU64 = AtomicU64::new(0);
+
+/// Number of pipe-draining threads still reading backend output
+static ACTIVE_LOG_DRAINS: AtomicUsize = AtomicUsize::new(0);
+
+/// Output waiting for the next `backend-log-batch`, while a log panel is subscribed
+struct LogSubscription {
+    /// Tells this subscription's flusher apart from a later one's
+    generation: u64,
+    pending: VecDeque<BackendLogLine>,
+    dropped: usize,
+}
+
+/// `None` while nobody watches the logs, so no events are emitted
+static LOG_SUBSCRIPTION: Mutex<Option<LogSubscription>> = Mutex::new(None);
+static NEXT_LOG_SUBSCRIPTION: AtomicU64 = AtomicU64::new(1);
+
+/// Batches per second when `subscribe_backend_logs` isn't given a rate
+const DEFAULT_LOG_BATCH_RATE: u32 = 10;
+/// Upper bound on batches per second
+const MAX_LOG_BATCH_RATE: u32 = 60;
+
+/// Emit an event to the frontend if the app handle is available
+pub(crate) fn emit_event<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(event, payload);
+    }
+}
+
+/// Tell listeners whether a backend is currently tracked
+fn emit_status(port: Option<u16>) {
+    emit_event(
+        "backend-status",
+        serde_json::json!({ "running": port.is_some(), "port": port }),
+    );
+}
+
+/// Port of the tracked backend, if one is running
+pub(crate) fn backend_port() -> Option<u16> {
+    *BACKEND_PORT.lock().ok()?
+}
 
 /// Initialize backend on app startup
-pub async fn initialize(_app: &AppHandle) -> Result<(), String> {
+pub async fn initialize(app: &AppHandle) -> Result<(), String> {
+    let _ = APP_HANDLE.set(app.clone());
+
+    // Watch for crashes and relaunch the backend
+    tauri::async_runtime::spawn(supervise());
+    tauri::async_runtime::spawn(supervise_idle());
+
+    adopt_detached_backend().await;
+
     // Check if backend is already running
     if let Ok(status) = backend_status().await {
         if status.running {
@@ -28,155 +320,1747 @@ pub async fn initialize(_app: &AppHandle) -> Result<(), String> {
         }
     }
 
-    // Try to start backend
-    // In production, we'd have the Python backend bundled or start via uvicorn
-    Ok(())
+    // Try to start backend
+    // In production, we'd have the Python backend bundled or start via uvicorn
+    Ok(())
+}
+
+/// Start the Python backend server.
+///
+/// If the port is taken, fails unless `auto_port` is set, in which case the
+/// next free port above it is used. The returned status has the actual port.
+/// `project_path` lets a project virtualenv supply the interpreter. With the
+/// stdio transport no port is bound and `port` is ignored.
+///
+/// A `detached` backend keeps running after the app exits and is picked up
+/// again from `backend.pid` on the next launch. It needs the HTTP transport.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(port = ?port, transport = ?transport))]
+pub async fn start_backend(
+    port: Option<u16>,
+    ready_timeout_secs: Option<u64>,
+    auto_port: Option<bool>,
+    project_path: Option<String>,
+    transport: Option<Transport>,
+    detached: Option<bool>,
+) -> Result<BackendStatus, CommandError> {
+    let port = port.unwrap_or(8765);
+    let ready_timeout =
+        Duration::from_secs(ready_timeout_secs.unwrap_or(DEFAULT_READY_TIMEOUT_SECS));
+
+    // Check if already running
+    if backend_tracked() {
+        return Err(CommandError::invalid_input("Backend is already running"));
+    }
+
+    let transport = transport.unwrap_or_default();
+    let detached = detached.unwrap_or(false);
+    if detached && transport == Transport::Stdio {
+        return Err(CommandError::invalid_input(
+            "A detached backend can't use the stdio transport",
+        ));
+    }
+    if BACKEND_STARTING.swap(true, Ordering::SeqCst) {
+        return Err(CommandError::invalid_input("Backend is already starting"));
+    }
+
+    let project = project_path.as_deref().map(Path::new);
+    let port = match transport {
+        Transport::Http => resolve_port(port, auto_port.unwrap_or(false)),
+        Transport::Stdio => Ok(port),
+    };
+    let result = match port {
+        Ok(port) => launch_backend(port, ready_timeout, project, transport, detached).await,
+        Err(e) => Err(e),
+    };
+    BACKEND_STARTING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Ports tried above the requested one when `auto_port` is set
+const MAX_PORT_PROBES: u16 = 100;
+
+/// Pick the port to launch on, probing upward from `port` if allowed
+fn resolve_port(port: u16, auto_port: bool) -> Result<u16, CommandError> {
+    if port_is_free(port) {
+        return Ok(port);
+    }
+
+    if !auto_port {
+        return Err(CommandError::invalid_input(format!(
+            "Port {} is already in use; stop the process holding it or enable auto_port",
+            port
+        )));
+    }
+
+    (1..=MAX_PORT_PROBES)
+        .filter_map(|offset| port.checked_add(offset))
+        .find(|&candidate| port_is_free(candidate))
+        .ok_or_else(|| {
+            CommandError::io(format!(
+                "No free port found between {} and {}",
+                port,
+                port.saturating_add(MAX_PORT_PROBES)
+            ))
+        })
+}
+
+/// Whether nothing is listening on `port` on the loopback interface
+fn port_is_free(port: u16) -> bool {
+    // The listener is dropped immediately, releasing the port for the backend
+    std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// Spawn the backend and wait for it to answer before tracking it
+async fn launch_backend(
+    port: u16,
+    ready_timeout: Duration,
+    project: Option<&Path>,
+    transport: Transport,
+    detached: bool,
+) -> Result<BackendStatus, CommandError> {
+    emit_starting("resolving_python", serde_json::json!({ "port": port }));
+
+    // Find Python executable
+    let python = find_python(project).inspect_err(emit_start_failed)?;
+
+    let spec = LaunchSpec {
+        python,
+        port,
+        transport,
+        detached,
+    };
+
+    launch_spec(spec, ready_timeout).await
+}
+
+/// Spawn the backend described by `spec` and track it once it answers
+async fn launch_spec(
+    spec: LaunchSpec,
+    ready_timeout: Duration,
+) -> Result<BackendStatus, CommandError> {
+    let result = spawn_until_ready(spec, ready_timeout).await;
+
+    match &result {
+        Ok(status) => emit_starting(
+            "ready",
+            serde_json::json!({
+                "port": status.port,
+                "python": status.python,
+                "version": status.version,
+                "pid": status.pid,
+            }),
+        ),
+        Err(e) => emit_start_failed(e),
+    }
+
+    result
+}
+
+/// Report a startup phase to the frontend as `backend-starting`
+fn emit_starting(phase: &str, mut details: serde_json::Value) {
+    if let Some(obj) = details.as_object_mut() {
+        obj.insert("phase".to_string(), serde_json::json!(phase));
+    }
+    emit_event("backend-starting", details);
+}
+
+fn emit_start_failed(error: &CommandError) {
+    emit_starting("failed", serde_json::json!({ "error": error }));
+}
+
+/// Spawn, wait for readiness, and track; `launch_spec` reports the outcome
+async fn spawn_until_ready(
+    spec: LaunchSpec,
+    ready_timeout: Duration,
+) -> Result<BackendStatus, CommandError> {
+    let port = spec.port;
+    let python = spec.python.clone();
+
+    emit_starting("spawning", serde_json::json!({ "port": port, "python": python }));
+
+    // Start the backend server
+    let mut child = spawn_backend(&spec)?;
+
+    let pid = child.id();
+
+    // Wait for backend to be ready
+    let version = match wait_until_ready(&mut child, &spec, ready_timeout).await {
+        Ok(version) => version,
+        Err(e) => {
+            crate::rpc::detach();
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+    };
+
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    IDLE_STOPPED.store(false, Ordering::SeqCst);
+    touch_activity();
+
+    // Store process
+    let (transport, detached) = (spec.transport, spec.detached);
+    track_process(child, spec)?;
+
+    Ok(BackendStatus {
+        running: true,
+        port: (transport == Transport::Http).then_some(port),
+        version: Some(version),
+        pid: Some(pid),
+        python: Some(python),
+        transport,
+        detached,
+    })
+}
+
+/// Poll the backend until it answers, its process exits, or the timeout elapses
+async fn wait_until_ready(
+    child: &mut Child,
+    spec: &LaunchSpec,
+    timeout: Duration,
+) -> Result<String, CommandError> {
+    let port = spec.port;
+    let started = Instant::now();
+    let deadline = started + timeout;
+
+    loop {
+        emit_starting(
+            "waiting_ready",
+            serde_json::json!({
+                "port": port,
+                "elapsed_ms": started.elapsed().as_millis() as u64,
+                "timeout_ms": timeout.as_millis() as u64,
+            }),
+        );
+
+        // Fail fast if the process died during startup
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(CommandError::backend(format!(
+                "Backend process exited unexpectedly ({})",
+                status
+            ))
+            .with_details(stderr_tail().await));
+        }
+
+        let version = match spec.transport {
+            Transport::Http => get_backend_version(port).await,
+            Transport::Stdio => get_rpc_version().await,
+        };
+        if let Ok(version) = version {
+            return Ok(version);
+        }
+
+        if Instant::now() >= deadline {
+            return Err(CommandError::backend(format!(
+                "Backend did not become ready within {}s",
+                timeout.as_secs()
+            )));
+        }
+
+        // Never sleep past the deadline, so a short timeout is honored
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        tokio::time::sleep(READY_POLL_INTERVAL.min(remaining)).await;
+    }
+}
+
+/// Read the last few lines the current run of a dead backend wrote to stderr
+async fn stderr_tail() -> String {
+    // Give the drain threads a moment to read what's left in the pipes
+    for _ in 0..10 {
+        if ACTIVE_LOG_DRAINS.load(Ordering::SeqCst) == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let logs = match BACKEND_LOGS.lock() {
+        Ok(logs) => logs,
+        Err(_) => return String::new(),
+    };
+
+    let run = BACKEND_RUN.load(Ordering::SeqCst);
+    let lines: Vec<&str> = logs
+        .iter()
+        .filter(|l| l.run == run && l.stream == "stderr")
+        .map(|l| l.line.as_str())
+        .collect();
+    let start = lines.len().saturating_sub(STDERR_TAIL_LINES);
+    lines[start..].join("\n")
+}
+
+/// Drain the child's stdout and stderr so the pipes never fill up and block it
+fn attach_log_drains(child: &mut Child) {
+    let run = BACKEND_RUN.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Some(stdout) = child.stdout.take() {
+        spawn_log_drain("stdout", stdout, run);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_log_drain("stderr", stderr, run);
+    }
+}
+
+/// Read a pipe line by line into the log buffer until it closes
+fn spawn_log_drain<R: Read + Send + 'static>(stream: &'static str, pipe: R, run: u64) {
+    ACTIVE_LOG_DRAINS.fetch_add(1, Ordering::SeqCst);
+
+    std::thread::spawn(move || {
+        let reader = BufReader::new(pipe);
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            push_log_line(BackendLogLine {
+                stream: stream.to_string(),
+                line,
+                run,
+            });
+        }
+
+        ACTIVE_LOG_DRAINS.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+/// Log a stdout line from a stdio backend that wasn't a JSON-RPC message
+pub(crate) fn push_stdout_line(line: String) {
+    push_log_line(BackendLogLine {
+        stream: "stdout".to_string(),
+        line,
+        run: BACKEND_RUN.load(Ordering::SeqCst),
+    });
+}
+
+/// Append a line to the ring buffer and queue it for subscribers, if any
+fn push_log_line(entry: BackendLogLine) {
+    if let Ok(mut logs) = BACKEND_LOGS.lock() {
+        if logs.len() >= BACKEND_LOG_CAPACITY {
+            logs.pop_front();
+        }
+        logs.push_back(entry.clone());
+    }
+
+    if let Ok(mut subscription) = LOG_SUBSCRIPTION.lock() {
+        if let Some(subscription) = subscription.as_mut() {
+            if subscription.pending.len() >= BACKEND_LOG_CAPACITY {
+                subscription.pending.pop_front();
+                subscription.dropped += 1;
+            }
+            subscription.pending.push_back(entry);
+        }
+    }
+}
+
+/// Start emitting output as `backend-log-batch`, at most `max_rate_per_sec` times a second.
+///
+/// Calling it again only changes the rate. Lines arriving between flushes are
+/// coalesced into one batch.
+#[tauri::command]
+pub async fn subscribe_backend_logs(max_rate_per_sec: Option<u32>) -> Result<(), CommandError> {
+    let rate = max_rate_per_sec
+        .unwrap_or(DEFAULT_LOG_BATCH_RATE)
+        .clamp(1, MAX_LOG_BATCH_RATE);
+    let generation = NEXT_LOG_SUBSCRIPTION.fetch_add(1, Ordering::SeqCst);
+
+    {
+        let mut subscription = LOG_SUBSCRIPTION.lock().map_err(CommandError::poisoned)?;
+        // Keep anything queued for the previous flusher
+        let (pending, dropped) = subscription
+            .take()
+            .map(|s| (s.pending, s.dropped))
+            .unwrap_or_default();
+        *subscription = Some(LogSubscription {
+            generation,
+            pending,
+            dropped,
+        });
+    }
+
+    tauri::async_runtime::spawn(flush_log_batches(generation, Duration::from_secs(1) / rate));
+    Ok(())
+}
+
+/// Stop emitting output events; the buffer behind `get_backend_logs` keeps filling
+#[tauri::command]
+pub async fn unsubscribe_backend_logs() -> Result<(), CommandError> {
+    LOG_SUBSCRIPTION
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .take();
+    Ok(())
+}
+
+/// Emit queued output every `interval` until the subscription is replaced or dropped
+async fn flush_log_batches(generation: u64, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let batch = match LOG_SUBSCRIPTION.lock() {
+            Ok(mut subscription) => match subscription.as_mut() {
+                Some(s) if s.generation == generation => BackendLogBatch {
+                    lines: s.pending.drain(..).collect(),
+                    dropped: std::mem::take(&mut s.dropped),
+                },
+                _ => return,
+            },
+            Err(_) => return,
+        };
+
+        if !batch.lines.is_empty() {
+            emit_event("backend-log-batch", batch);
+        }
+    }
+}
+
+/// Everything in the backend output buffer, oldest first
+pub(crate) fn buffered_logs() -> Vec<BackendLogLine> {
+    BACKEND_LOGS
+        .lock()
+        .map(|logs| logs.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Get the most recent backend output lines
+#[tauri::command]
+pub async fn get_backend_logs(lines: Option<usize>) -> Result<Vec<String>, CommandError> {
+    let logs = BACKEND_LOGS.lock().map_err(CommandError::poisoned)?;
+    let count = lines.unwrap_or(logs.len()).min(logs.len());
+
+    Ok(logs
+        .iter()
+        .skip(logs.len() - count)
+        .map(|l| l.line.clone())
+        .collect())
+}
+
+/// A slice of the backend's log file, returned by `read_backend_log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogChunk {
+    pub text: String,
+    /// Byte offset to pass as `since_offset` on the next poll
+    pub offset: u64,
+    /// The file shrank since the given offset, so reading restarted from the top
+    /// after the rest of `backend.log.1`
+    pub rotated: bool,
+    /// More data is available past `offset`
+    pub truncated: bool,
+}
+
+/// Most bytes returned by one `read_backend_log` call
+const MAX_LOG_CHUNK_BYTES: u64 = 256 * 1024;
+/// Lines tailed when neither `lines` nor `since_offset` is given
+const DEFAULT_LOG_TAIL_LINES: usize = 200;
+
+/// Read the log file the backend writes to the config directory.
+///
+/// With `since_offset`, returns what was appended since that offset; otherwise
+/// tails the last `lines` lines. The returned `offset` resumes the next read.
+/// After a rotation the rest of `backend.log.1` comes first, and a short tail is
+/// filled from it, so lines written around a rotation aren't lost.
+#[tauri::command]
+pub async fn read_backend_log(
+    lines: Option<usize>,
+    since_offset: Option<u64>,
+) -> Result<LogChunk, CommandError> {
+    let root = crate::commands::config_root()?;
+    let path = root.join("backend.log");
+    let previous = root.join("backend.log.1");
+    let read_err = |e| CommandError::io("Failed to read backend log").with_details(e);
+
+    let open = open_log(&path)
+        .map_err(|e| CommandError::io("Failed to open backend log").with_details(e))?;
+    let Some((mut file, len)) = open else {
+        return Ok(LogChunk {
+            text: String::new(),
+            offset: 0,
+            rotated: since_offset.is_some_and(|o| o > 0),
+            truncated: false,
+        });
+    };
+
+    // An offset past the end means the file was rotated or truncated
+    let rotated = since_offset.is_some_and(|o| o > len);
+    let start = match since_offset {
+        Some(offset) if !rotated => offset,
+        Some(_) => 0,
+        None => len.saturating_sub(MAX_LOG_CHUNK_BYTES),
+    };
+
+    // Whatever reached the old file after the last poll, if it is the one we were reading
+    let mut buf = Vec::new();
+    if let Some(offset) = since_offset.filter(|_| rotated) {
+        if let Some((mut old, old_len)) = open_log(&previous).map_err(read_err)? {
+            if old_len >= offset {
+                buf = read_log_bytes(&mut old, offset, MAX_LOG_CHUNK_BYTES).map_err(read_err)?;
+            }
+        }
+    }
+
+    let budget = MAX_LOG_CHUNK_BYTES - buf.len() as u64;
+    let mut current = read_log_bytes(&mut file, start, budget).map_err(read_err)?;
+    let mut end = start + current.len() as u64;
+    let truncated = end < len;
+    if truncated {
+        // Stop at a line boundary so the next poll doesn't split a line or character
+        if let Some(newline) = current.iter().rposition(|&b| b == b'\n') {
+            current.truncate(newline + 1);
+            end = start + current.len() as u64;
+        }
+    }
+    buf.extend_from_slice(&current);
+
+    let mut text = String::from_utf8_lossy(&buf).into_owned();
+    if since_offset.is_none() {
+        let count = lines.unwrap_or(DEFAULT_LOG_TAIL_LINES);
+        // Reading began mid-file, so the first line is partial
+        let mut tail: Vec<&str> = text.lines().skip(usize::from(start > 0)).collect();
+
+        let earlier;
+        if start == 0 && tail.len() < count {
+            if let Some((mut old, old_len)) = open_log(&previous).map_err(read_err)? {
+                let budget = MAX_LOG_CHUNK_BYTES - buf.len() as u64;
+                let from = old_len.saturating_sub(budget);
+                let bytes = read_log_bytes(&mut old, from, budget).map_err(read_err)?;
+                earlier = String::from_utf8_lossy(&bytes).into_owned();
+                let mut lines: Vec<&str> = earlier.lines().skip(usize::from(from > 0)).collect();
+                lines.append(&mut tail);
+                tail = lines;
+            }
+        }
+
+        text = tail[tail.len().saturating_sub(count)..].join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+    }
+
+    Ok(LogChunk {
+        text,
+        offset: end,
+        rotated,
+        truncated,
+    })
+}
+
+/// Up to `cap` bytes of `file` from `start`
+fn read_log_bytes(file: &mut std::fs::File, start: u64, cap: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.take(cap).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Open the log at `path` with its length; `None` if it doesn't exist
+fn open_log(path: &Path) -> std::io::Result<Option<(std::fs::File, u64)>> {
+    match std::fs::File::open(path) {
+        Ok(file) => {
+            let len = file.metadata()?.len();
+            Ok(Some((file, len)))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Backend argv used when `backend.command` isn't set
+const DEFAULT_BACKEND_COMMAND: &[&str] = &["{python}", "-m", "roura_agent.server"];
+
+/// Interface the backend is told to listen on
+const BACKEND_HOST: &str = "127.0.0.1";
+
+/// A config value that is a list of strings; other items are ignored
+fn config_strings(key: &str) -> Option<Vec<String>> {
+    crate::commands::config_value(key).and_then(|v| {
+        v.as_array().map(|items| {
+            items
+                .iter()
+                .filter_map(|i| i.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+    })
+}
+
+/// `backend.command`, or the default when it's unset or empty
+fn backend_command() -> Vec<String> {
+    config_strings("backend.command")
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| {
+            DEFAULT_BACKEND_COMMAND
+                .iter()
+                .map(|a| a.to_string())
+                .collect()
+        })
+}
+
+/// The backend's argv from a `backend.command` template and `backend.extra_args`.
+///
+/// `{python}`, `{port}` and `{host}` are substituted in every element. Each
+/// element is passed as one argument, so values with spaces need no quoting.
+/// A command without `{port}` gets `--port`/`--host` appended, as the default
+/// one expects.
+fn backend_argv(spec: &LaunchSpec, template: &[String], extra_args: &[String]) -> Vec<String> {
+    let port = spec.port.to_string();
+    let substitute = |arg: &str| {
+        arg.replace("{python}", &spec.python)
+            .replace("{port}", &port)
+            .replace("{host}", BACKEND_HOST)
+    };
+
+    let mut argv: Vec<String> = template.iter().map(|a| substitute(a)).collect();
+    match spec.transport {
+        Transport::Http if !template.iter().any(|a| a.contains("{port}")) => {
+            argv.extend(["--port", &port, "--host", BACKEND_HOST].map(String::from));
+        }
+        Transport::Http => {}
+        Transport::Stdio => argv.push("--stdio".to_string()),
+    }
+    argv.extend(extra_args.iter().map(|a| substitute(a)));
+
+    argv
+}
+
+/// Extensions Windows runs a name without one as, from `PATHEXT`
+fn program_extensions() -> Vec<String> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+
+    let pathext = std::env::var("PATHEXT")
+        .ok()
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".to_string());
+    pathext
+        .split(';')
+        .map(|e| e.trim_start_matches('.').to_ascii_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// The file `program` runs from: itself if it's a path, else the first match
+/// in `dirs`. A name without an extension also matches each of `extensions`,
+/// e.g. `uv` finds `uv.cmd`.
+fn find_program_in(
+    program: &str,
+    dirs: impl IntoIterator<Item = PathBuf>,
+    extensions: &[String],
+) -> Option<PathBuf> {
+    let matching = |base: PathBuf| {
+        let with_extensions = extensions
+            .iter()
+            .filter(|_| base.extension().is_none())
+            .map(|e| base.with_extension(e));
+        std::iter::once(base.clone())
+            .chain(with_extensions)
+            .find(|p| p.is_file())
+    };
+
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return matching(path.to_path_buf());
+    }
+    dirs.into_iter().find_map(|dir| matching(dir.join(program)))
+}
+
+/// `find_program_in` over `PATH` and, on Windows, `PATHEXT`
+fn find_program(program: &str) -> Option<PathBuf> {
+    let dirs = std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).collect::<Vec<_>>())
+        .unwrap_or_default();
+    find_program_in(program, dirs, &program_extensions())
+}
+
+/// Whether `program` is a file, or a name found on `PATH`; lists Linux capture tools
+#[cfg(target_os = "linux")]
+pub(crate) fn program_exists(program: &str) -> bool {
+    find_program(program).is_some()
+}
+
+/// Spawn the backend server process described by `spec`
+fn spawn_backend(spec: &LaunchSpec) -> Result<Child, CommandError> {
+    let extra_args = config_strings("backend.extra_args").unwrap_or_default();
+    let argv = backend_argv(spec, &backend_command(), &extra_args);
+    let Some((program, args)) = argv.split_first() else {
+        return Err(CommandError::invalid_input("backend.command is empty"));
+    };
+    // Run the file found, since spawning by name only tries `.exe` on Windows
+    let Some(resolved) = find_program(program) else {
+        return Err(CommandError::not_found(format!(
+            "Backend command not found: {}",
+            program
+        )));
+    };
+
+    let mut cmd = Command::new(resolved);
+    cmd.args(args);
+
+    // Its own hidden console and process group, so stop_backend can send it
+    // CTRL_BREAK without affecting the app. A detached backend gets no console
+    // at all, so closing the app's console can't take it down; stopping it
+    // falls back to terminating the process.
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows::Win32::System::Threading::{
+            CREATE_NEW_PROCESS_GROUP, CREATE_NO_WINDOW, DETACHED_PROCESS,
+        };
+
+        let console = if spec.detached {
+            DETACHED_PROCESS
+        } else {
+            CREATE_NO_WINDOW
+        };
+        cmd.creation_flags(CREATE_NEW_PROCESS_GROUP.0 | console.0);
+    }
+
+    if spec.transport == Transport::Stdio {
+        cmd.stdin(Stdio::piped());
+    }
+
+    if spec.detached {
+        // A new session keeps it clear of the app's terminal and signals.
+        // Pipes would break when the app exits, and the backend writes its
+        // own log file anyway.
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            unsafe {
+                cmd.pre_exec(|| {
+                    libc::setsid();
+                    Ok(())
+                });
+            }
+        }
+        cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    } else {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| CommandError::io("Failed to start backend").with_details(e))?;
+
+    // stdout carries the protocol, so only stderr goes to the log
+    if spec.transport == Transport::Stdio {
+        if let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) {
+            crate::rpc::attach(stdin, stdout);
+        }
+    }
+    attach_log_drains(&mut child);
+
+    Ok(child)
+}
+
+/// Record a freshly spawned backend process as the tracked one
+fn track_process(child: Child, spec: LaunchSpec) -> Result<(), CommandError> {
+    if spec.detached {
+        let record = PidFile {
+            pid: child.id(),
+            port: spec.port,
+            python: spec.python.clone(),
+        };
+        if let Err(e) = write_pid_file(&record) {
+            tracing::warn!(error = %e, "Failed to write backend pid file");
+        }
+    }
+    {
+        let mut process = BACKEND_PROCESS.lock().map_err(CommandError::poisoned)?;
+        *process = Some(child);
+    }
+    // A stdio backend has no port, which also keeps HTTP-only features away from it
+    let port = (spec.transport == Transport::Http).then_some(spec.port);
+    {
+        let mut backend_port = BACKEND_PORT.lock().map_err(CommandError::poisoned)?;
+        *backend_port = port;
+    }
+    {
+        let mut started_at = BACKEND_STARTED_AT.lock().map_err(CommandError::poisoned)?;
+        *started_at = Some(Instant::now());
+    }
+    let transport = spec.transport;
+    {
+        let mut launch = BACKEND_LAUNCH.lock().map_err(CommandError::poisoned)?;
+        *launch = Some(spec);
+    }
+
+    match transport {
+        Transport::Http => emit_status(port),
+        Transport::Stdio => emit_event(
+            "backend-status",
+            serde_json::json!({ "running": true, "port": null, "transport": transport }),
+        ),
+    }
+
+    Ok(())
+}
+
+/// Check whether the tracked backend crashed, returning its launch spec if so.
+///
+/// A clean exit or an exit after `stop_backend` clears the tracked state instead.
+fn take_crashed_process() -> Option<LaunchSpec> {
+    let mut process = BACKEND_PROCESS.lock().ok()?;
+    let status = process.as_mut()?.try_wait().ok()??;
+    *process = None;
+    drop(process);
+
+    let spec = BACKEND_LAUNCH.lock().ok()?.clone();
+    // A relaunch writes a fresh one
+    if spec.as_ref().is_some_and(|s| s.detached) {
+        remove_pid_file();
+    }
+
+    if status.success() || STOP_REQUESTED.load(Ordering::SeqCst) {
+        if let Ok(mut backend_port) = BACKEND_PORT.lock() {
+            *backend_port = None;
+        }
+        emit_status(None);
+        return None;
+    }
+
+    spec
+}
+
+/// Supervisor loop that relaunches the backend after unexpected exits
+async fn supervise() {
+    let mut failures: u32 = 0;
+
+    loop {
+        tokio::time::sleep(SUPERVISOR_INTERVAL).await;
+
+        let Some(spec) = take_crashed_process() else {
+            continue;
+        };
+
+        // A backend that ran for a while before crashing starts a fresh backoff
+        let stable = BACKEND_STARTED_AT
+            .lock()
+            .ok()
+            .and_then(|s| *s)
+            .map(|s| s.elapsed() >= RESTART_STABLE_AFTER)
+            .unwrap_or(false);
+        if stable {
+            failures = 0;
+        }
+
+        let policy = RestartPolicy::load();
+
+        loop {
+            failures += 1;
+
+            if failures > policy.max_attempts {
+                emit_event(
+                    "backend-failed",
+                    serde_json::json!({ "attempts": failures - 1, "port": spec.port }),
+                );
+                if let Ok(mut backend_port) = BACKEND_PORT.lock() {
+                    *backend_port = None;
+                }
+                emit_status(None);
+                failures = 0;
+                break;
+            }
+
+            tokio::time::sleep(policy.delay_for(failures)).await;
+
+            // The user may have stopped or restarted the backend while we waited
+            if STOP_REQUESTED.load(Ordering::SeqCst) {
+                break;
+            }
+            if BACKEND_PROCESS.lock().map(|p| p.is_some()).unwrap_or(true) {
+                break;
+            }
+
+            match spawn_backend(&spec) {
+                Ok(child) => {
+                    let pid = child.id();
+                    if track_process(child, spec.clone()).is_ok() {
+                        emit_event(
+                            "backend-restarted",
+                            serde_json::json!({
+                                "attempt": failures,
+                                "port": spec.port,
+                                "pid": pid,
+                            }),
+                        );
+                    }
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        attempt = failures,
+                        error = %e,
+                        "Backend restart attempt failed"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Stop the Python backend server
+#[tauri::command]
+#[tracing::instrument]
+pub async fn stop_backend() -> Result<(), CommandError> {
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+    // An explicit stop is never undone by the next request
+    IDLE_STOPPED.store(false, Ordering::SeqCst);
+
+    let child = {
+        let mut process = BACKEND_PROCESS.lock().map_err(CommandError::poisoned)?;
+        process.take()
+    };
+
+    if let Some(child) = child {
+        // A stdio backend treats its stdin closing as a request to exit
+        crate::rpc::detach();
+
+        stop_child(child, STOP_TIMEOUT).await;
+        remove_pid_file();
+    } else if let Some(pid) = detached_pid() {
+        terminate_pid(pid).await;
+        remove_pid_file();
+    }
+
+    // Clear port
+    {
+        let mut backend_port = BACKEND_PORT.lock().map_err(CommandError::poisoned)?;
+        *backend_port = None;
+    }
+
+    emit_status(None);
+
+    Ok(())
+}
+
+/// Marks a request as using the backend until dropped
+pub(crate) struct BackendActivity(());
+
+impl BackendActivity {
+    pub(crate) fn begin() -> Self {
+        REQUESTS_IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        touch_activity();
+        Self(())
+    }
+}
+
+impl Drop for BackendActivity {
+    fn drop(&mut self) {
+        // Idle time counts from when the last request finished
+        touch_activity();
+        REQUESTS_IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn touch_activity() {
+    if let Ok(mut last) = LAST_ACTIVITY.lock() {
+        *last = Some(Instant::now());
+    }
+}
+
+/// Idle time after which the backend is stopped, or `None` when disabled
+fn idle_shutdown_after() -> Option<Duration> {
+    crate::commands::config_value("backend.idle_shutdown_minutes")
+        .and_then(|v| v.as_u64())
+        .filter(|&minutes| minutes > 0)
+        .map(|minutes| Duration::from_secs(minutes * 60))
+}
+
+/// Stop the backend once nothing has used it for `backend.idle_shutdown_minutes`
+async fn supervise_idle() {
+    loop {
+        tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+
+        let Some(idle_after) = idle_shutdown_after() else {
+            continue;
+        };
+        if backend_port().is_none() || REQUESTS_IN_FLIGHT.load(Ordering::SeqCst) > 0 {
+            continue;
+        }
+        let idle = LAST_ACTIVITY
+            .lock()
+            .ok()
+            .and_then(|last| *last)
+            .is_some_and(|last| last.elapsed() >= idle_after);
+        if !idle {
+            continue;
+        }
+
+        if let Err(e) = stop_backend().await {
+            tracing::error!(error = %e, "Failed to stop idle backend");
+            continue;
+        }
+        IDLE_STOPPED.store(true, Ordering::SeqCst);
+        emit_event(
+            "backend-idle-stopped",
+            serde_json::json!({ "idle_minutes": idle_after.as_secs() / 60 }),
+        );
+    }
+}
+
+/// Restart the backend if it was stopped for inactivity, with the spec it last ran with
+pub(crate) async fn resume_if_idle() -> Result<(), CommandError> {
+    if !IDLE_STOPPED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    // Concurrent requests wait here for the first one's restart
+    let _resume = IDLE_RESUME.lock().await;
+    if !IDLE_STOPPED.load(Ordering::SeqCst) || backend_port().is_some() {
+        return Ok(());
+    }
+
+    let Some(mut spec) = BACKEND_LAUNCH
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .clone()
+    else {
+        return Err(CommandError::internal(
+            "No previous backend launch to resume",
+        ));
+    };
+    if BACKEND_STARTING.swap(true, Ordering::SeqCst) {
+        return Err(CommandError::invalid_input("Backend is already starting"));
+    }
+
+    // Something else may have taken the port while the backend was down
+    let port = match spec.transport {
+        Transport::Http => resolve_port(spec.port, true),
+        Transport::Stdio => Ok(spec.port),
+    };
+    let result = match port {
+        Ok(port) => {
+            spec.port = port;
+            launch_spec(spec, Duration::from_secs(DEFAULT_READY_TIMEOUT_SECS)).await
+        }
+        Err(e) => Err(e),
+    };
+    BACKEND_STARTING.store(false, Ordering::SeqCst);
+
+    result.map(|_| ())
+}
+
+/// How long a gracefully signalled backend gets to exit before it is killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a force-killed backend gets to disappear before it's left to a reaper thread
+const FORCE_KILL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ask `child` to exit and kill it if it's still running after `grace`.
+///
+/// Returns whether it had to be killed.
+async fn stop_child(mut child: Child, grace: Duration) -> bool {
+    #[cfg(unix)]
+    let signalled = unsafe { libc::kill(child.id() as i32, libc::SIGTERM) == 0 };
+
+    #[cfg(windows)]
+    let signalled = unsafe { send_ctrl_break(child.id()) };
+
+    if signalled && wait_for_exit(&mut child, grace).await {
+        return false;
+    }
+    force_kill(child, signalled).await;
+    true
+}
+
+/// SIGKILL (or TerminateProcess) a backend that ignored the graceful stop
+async fn force_kill(mut child: Child, signalled: bool) {
+    let pid = child.id();
+    let _ = child.kill();
+
+    // Reap it so it doesn't linger as a zombie, without blocking the stop on it
+    if !wait_for_exit(&mut child, FORCE_KILL_TIMEOUT).await {
+        tracing::warn!(pid, "Backend process did not exit after being killed");
+        std::thread::spawn(move || {
+            let _ = child.wait();
+        });
+    }
+
+    emit_event(
+        "backend-force-killed",
+        serde_json::json!({ "pid": pid, "graceful_stop_sent": signalled }),
+    );
+}
+
+/// Poll until the child exits, returning false if it outlives `timeout`
+async fn wait_for_exit(child: &mut Child, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return true,
+            Ok(None) if Instant::now() < deadline => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Send CTRL_BREAK to the backend's process group so its shutdown handlers run
+#[cfg(windows)]
+unsafe fn send_ctrl_break(pid: u32) -> bool {
+    use windows::Win32::System::Console::{
+        AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT,
+    };
+
+    // Console events only reach processes on the caller's console, so
+    // borrow the backend's for the duration of the call
+    let attached = AttachConsole(pid).is_ok();
+    let sent = GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid).is_ok();
+    if attached {
+        let _ = FreeConsole();
+    }
+
+    sent
+}
+
+/// Whether a backend process is ours to manage, spawned or adopted
+fn backend_tracked() -> bool {
+    BACKEND_PROCESS.lock().is_ok_and(|p| p.is_some()) || adopted_pid().is_some()
+}
+
+/// Pid of the adopted backend while it runs.
+///
+/// Nothing is told when a process we didn't spawn exits, so this checks; a
+/// gone one is forgotten along with its pid file and port.
+fn adopted_pid() -> Option<u32> {
+    let mut adopted = ADOPTED_PID.lock().ok()?;
+    let pid = (*adopted)?;
+    if pid_alive(pid) {
+        return Some(pid);
+    }
+
+    *adopted = None;
+    drop(adopted);
+    remove_pid_file();
+    if let Ok(mut backend_port) = BACKEND_PORT.lock() {
+        *backend_port = None;
+    }
+    tracing::info!(pid, "Adopted backend exited");
+    emit_status(None);
+    None
+}
+
+fn pid_file_path() -> Result<PathBuf, CommandError> {
+    Ok(crate::commands::config_root()?.join("backend.pid"))
+}
+
+fn write_pid_file(record: &PidFile) -> Result<(), CommandError> {
+    let path = pid_file_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| CommandError::io("Failed to create config directory").with_details(e))?;
+    }
+    crate::commands::write_json_atomic(&path, record)
+}
+
+fn read_pid_file() -> Option<PidFile> {
+    let content = std::fs::read_to_string(pid_file_path().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn remove_pid_file() {
+    if let Ok(path) = pid_file_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Re-attach to a detached backend left running by an earlier launch.
+///
+/// The pid file is trusted only if that process is alive and its port answers
+/// `/health`; a stale file is removed.
+async fn adopt_detached_backend() {
+    let Some(record) = read_pid_file() else {
+        return;
+    };
+
+    let healthy =
+        pid_alive(record.pid) && probe_backend(record.port).await.is_ok_and(|s| s.running);
+    if !healthy {
+        remove_pid_file();
+        return;
+    }
+
+    if let Ok(mut adopted) = ADOPTED_PID.lock() {
+        *adopted = Some(record.pid);
+    }
+    if let Ok(mut backend_port) = BACKEND_PORT.lock() {
+        *backend_port = Some(record.port);
+    }
+    // Lets an idle stop relaunch it the same way
+    if let Ok(mut launch) = BACKEND_LAUNCH.lock() {
+        *launch = Some(LaunchSpec {
+            python: record.python,
+            port: record.port,
+            transport: Transport::Http,
+            detached: true,
+        });
+    }
+
+    tracing::info!(pid = record.pid, port = record.port, "Adopted detached backend");
+    emit_status(Some(record.port));
+}
+
+/// Pid of a detached backend that isn't our child: adopted, or still in the pid file
+fn detached_pid() -> Option<u32> {
+    let adopted = ADOPTED_PID.lock().ok().and_then(|mut p| p.take());
+    adopted.or_else(|| read_pid_file().map(|r| r.pid).filter(|&pid| pid_alive(pid)))
+}
+
+#[cfg(unix)]
+fn pid_alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn pid_alive(pid: u32) -> bool {
+    use windows::Win32::Foundation::{CloseHandle, WAIT_TIMEOUT};
+    use windows::Win32::System::Threading::{
+        OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE,
+    };
+
+    unsafe {
+        let Ok(handle) = OpenProcess(PROCESS_SYNCHRONIZE, false, pid) else {
+            return false;
+        };
+        let alive = WaitForSingleObject(handle, 0) == WAIT_TIMEOUT;
+        let _ = CloseHandle(handle);
+        alive
+    }
+}
+
+/// Stop a process we can't wait on: signal it, give it `STOP_TIMEOUT`, then kill it
+#[cfg(unix)]
+async fn terminate_pid(pid: u32) {
+    let signalled = unsafe { libc::kill(pid as i32, libc::SIGTERM) == 0 };
+
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    while signalled && pid_alive(pid) && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    if pid_alive(pid) {
+        unsafe {
+            libc::kill(pid as i32, libc::SIGKILL);
+        }
+        emit_event(
+            "backend-force-killed",
+            serde_json::json!({ "pid": pid, "graceful_stop_sent": signalled }),
+        );
+    }
+}
+
+/// Stop a process we can't wait on: signal it, give it `STOP_TIMEOUT`, then kill it
+#[cfg(windows)]
+async fn terminate_pid(pid: u32) {
+    use windows::Win32::Foundation::{CloseHandle, WAIT_OBJECT_0};
+    use windows::Win32::System::Threading::{
+        OpenProcess, TerminateProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE,
+        PROCESS_TERMINATE,
+    };
+
+    let signalled = unsafe { send_ctrl_break(pid) };
+
+    // Waiting on the handle blocks, so keep it off the async runtime
+    let killed = tokio::task::spawn_blocking(move || unsafe {
+        let Ok(handle) = OpenProcess(PROCESS_SYNCHRONIZE | PROCESS_TERMINATE, false, pid) else {
+            return false;
+        };
+        let timeout = if signalled { STOP_TIMEOUT.as_millis() as u32 } else { 0 };
+        let exited = WaitForSingleObject(handle, timeout) == WAIT_OBJECT_0;
+        if !exited {
+            let _ = TerminateProcess(handle, 1);
+        }
+        let _ = CloseHandle(handle);
+        !exited
+    })
+    .await
+    .unwrap_or(false);
+
+    if killed {
+        emit_event(
+            "backend-force-killed",
+            serde_json::json!({ "pid": pid, "graceful_stop_sent": signalled }),
+        );
+    }
+}
+
+/// Port an externally started backend listens on, from `backend.port`
+const DEFAULT_EXTERNAL_PORT: u16 = 8765;
+
+/// Whether `backend.external` says the backend is run outside the app
+fn external_backend_port() -> Option<u16> {
+    let external = crate::commands::config_value("backend.external")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !external {
+        return None;
+    }
+
+    let port = crate::commands::config_value("backend.port")
+        .and_then(|v| v.as_u64())
+        .and_then(|p| u16::try_from(p).ok())
+        .unwrap_or(DEFAULT_EXTERNAL_PORT);
+    Some(port)
+}
+
+/// Check for a backend on `port` whether or not the app started it.
+///
+/// Reachable means `/health` reports ok; the version is filled in when
+/// `/version` answers too.
+#[tauri::command]
+pub async fn probe_backend(port: u16) -> Result<BackendStatus, CommandError> {
+    let running = get_backend_health(port).await.is_ok_and(|h| h.ok);
+    let version = if running {
+        get_backend_version(port).await.ok()
+    } else {
+        None
+    };
+
+    Ok(BackendStatus {
+        running,
+        port: running.then_some(port),
+        version,
+        pid: None,
+        python: None,
+        transport: Transport::Http,
+        detached: false,
+    })
+}
+
+/// Get backend status.
+///
+/// With `backend.external` set and no tracked process, probes the configured
+/// port instead.
+#[tauri::command]
+pub async fn backend_status() -> Result<BackendStatus, CommandError> {
+    let tracked = backend_tracked();
+    if !tracked {
+        if let Some(port) = external_backend_port() {
+            return probe_backend(port).await;
+        }
+    }
+
+    let port = {
+        let backend_port = BACKEND_PORT.lock().map_err(CommandError::poisoned)?;
+        *backend_port
+    };
+
+    let running = tracked;
+
+    let pid = {
+        let process = BACKEND_PROCESS.lock().map_err(CommandError::poisoned)?;
+        process.as_ref().map(|c| c.id())
+    };
+    let pid = match pid {
+        Some(pid) => Some(pid),
+        None => adopted_pid(),
+    };
+
+    let (python, transport, detached) = if running {
+        let launch = BACKEND_LAUNCH.lock().map_err(CommandError::poisoned)?;
+        (
+            launch.as_ref().map(|l| l.python.clone()),
+            launch.as_ref().map(|l| l.transport).unwrap_or_default(),
+            launch.as_ref().is_some_and(|l| l.detached),
+        )
+    } else {
+        (None, Transport::Http, false)
+    };
+
+    // Try to get version from backend API
+    let version = match (running, transport, port) {
+        (true, Transport::Http, Some(p)) => get_backend_version(p).await.ok(),
+        (true, Transport::Stdio, _) => get_rpc_version().await.ok(),
+        _ => None,
+    };
+
+    Ok(BackendStatus {
+        running,
+        port,
+        version,
+        pid,
+        python,
+        transport,
+        detached,
+    })
+}
+
+/// Check whether the backend process is tracked and its HTTP server is healthy
+#[tauri::command]
+pub async fn backend_health() -> Result<HealthReport, CommandError> {
+    let tracked = backend_tracked();
+    let port = backend_port();
+
+    let (true, Some(port)) = (tracked, port) else {
+        return Ok(HealthReport {
+            state: HealthState::NotRunning,
+            ok: false,
+            uptime_secs: None,
+            model: None,
+            queue_depth: None,
+            error: None,
+        });
+    };
+
+    match get_backend_health(port).await {
+        Ok(health) => Ok(HealthReport {
+            state: if health.ok {
+                HealthState::Healthy
+            } else {
+                HealthState::Unhealthy
+            },
+            ok: health.ok,
+            uptime_secs: health.uptime_secs,
+            model: health.model,
+            queue_depth: health.queue_depth,
+            error: None,
+        }),
+        Err(e) => Ok(HealthReport {
+            state: HealthState::Unreachable,
+            ok: false,
+            uptime_secs: None,
+            model: None,
+            queue_depth: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+/// Get backend health from API
+async fn get_backend_health(port: u16) -> Result<HealthResponse, CommandError> {
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/health", port);
+
+    let response = client
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| CommandError::backend("Failed to connect to backend").with_details(e))?;
+
+    response
+        .json()
+        .await
+        .map_err(|e| CommandError::parse("Failed to parse response").with_details(e))
+}
+
+/// Round-trip timings of health checks against the tracked backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingResult {
+    /// Port of an HTTP backend; `None` over stdio
+    pub port: Option<u16>,
+    /// HTTP status of the last request; `None` over stdio
+    pub status: Option<u16>,
+    /// Each request's latency, in order
+    pub latency_ms: Vec<f64>,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
 }
 
-/// Start the Python backend server
+/// Requests made by `ping_backend` when no count is given
+const DEFAULT_PING_COUNT: u32 = 3;
+/// Upper bound on `ping_backend`'s count, so a typo can't stall the panel
+const MAX_PING_COUNT: u32 = 20;
+/// Longest a single ping may take
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time `count` sequential health checks against the tracked backend.
+///
+/// Over HTTP this is `GET /health`, and any status counts as an answer, so a
+/// slow unhealthy backend still reports its latency; only failing to connect
+/// is an error. Over stdio it is a `health` call.
 #[tauri::command]
-pub async fn start_backend(port: Option<u16>) -> Result<BackendStatus, String> {
-    let port = port.unwrap_or(8765);
+pub async fn ping_backend(count: Option<u32>) -> Result<PingResult, CommandError> {
+    let route = api_route()?;
+    let count = count.unwrap_or(DEFAULT_PING_COUNT).clamp(1, MAX_PING_COUNT);
 
-    // Check if already running
-    {
-        let process = BACKEND_PROCESS.lock().map_err(|e| e.to_string())?;
-        if process.is_some() {
-            return Err("Backend is already running".to_string());
+    let client = reqwest::Client::new();
+    let mut latency_ms = Vec::with_capacity(count as usize);
+    let mut status = None;
+
+    for _ in 0..count {
+        let started = Instant::now();
+        match route {
+            Route::Http(port) => {
+                let response = client
+                    .get(format!("http://127.0.0.1:{}/health", port))
+                    .timeout(PING_TIMEOUT)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        CommandError::backend("Failed to connect to backend").with_details(e)
+                    })?;
+                status = Some(response.status().as_u16());
+            }
+            Route::Stdio => {
+                crate::rpc::call("health", serde_json::json!({}), Some(PING_TIMEOUT)).await?;
+            }
         }
+        latency_ms.push(started.elapsed().as_secs_f64() * 1000.0);
     }
 
-    // Find Python executable
-    let python = find_python().ok_or("Could not find Python installation")?;
+    let min_ms = latency_ms.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_ms = latency_ms.iter().copied().fold(0.0, f64::max);
+    let avg_ms = latency_ms.iter().sum::<f64>() / latency_ms.len() as f64;
 
-    // Start the backend server
-    let child = Command::new(&python)
-        .args([
-            "-m",
-            "roura_agent.server",
-            "--port",
-            &port.to_string(),
-            "--host",
-            "127.0.0.1",
-        ])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to start backend: {}", e))?;
+    Ok(PingResult {
+        port: match route {
+            Route::Http(port) => Some(port),
+            Route::Stdio => None,
+        },
+        status,
+        latency_ms,
+        min_ms,
+        avg_ms,
+        max_ms,
+    })
+}
 
-    let pid = child.id();
+/// How long `warm_backend` waits when not given a timeout; loading a model is slow
+const DEFAULT_WARM_TIMEOUT_SECS: u64 = 120;
 
-    // Store process
-    {
-        let mut process = BACKEND_PROCESS.lock().map_err(|e| e.to_string())?;
-        *process = Some(child);
+/// Serializes `warm_backend` calls, so the model is loaded once
+static WARMING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// The launch and route last warmed; a restart or new route needs warming again
+static WARMED: Mutex<Option<(Option<Instant>, Route)>> = Mutex::new(None);
+
+/// Start the backend if needed and have it load the model ahead of the first message.
+///
+/// Returns once the backend reports the model loaded; calling it again for the
+/// same backend is a no-op. Progress is reported as `backend-starting` phases.
+#[tauri::command]
+pub async fn warm_backend(timeout_secs: Option<u64>) -> Result<(), CommandError> {
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_WARM_TIMEOUT_SECS));
+    let deadline = Instant::now() + timeout;
+    let _warming = WARMING.lock().await;
+
+    resume_if_idle().await?;
+    if api_route().is_err() {
+        if BACKEND_STARTING.load(Ordering::SeqCst) {
+            wait_for_start(deadline).await?;
+        } else {
+            let ready_secs = deadline.saturating_duration_since(Instant::now()).as_secs();
+            start_backend(None, Some(ready_secs.max(1)), Some(true), None, None, None).await?;
+        }
     }
-    {
-        let mut backend_port = BACKEND_PORT.lock().map_err(|e| e.to_string())?;
-        *backend_port = Some(port);
+
+    let route = api_route()?;
+    let started_at = BACKEND_STARTED_AT.lock().ok().and_then(|s| *s);
+    if *WARMED.lock().map_err(CommandError::poisoned)? == Some((started_at, route)) {
+        return Ok(());
     }
 
-    // Wait for backend to be ready
-    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    emit_starting("warming", serde_json::json!({}));
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let result = match route {
+        Route::Http(port) => reqwest::Client::new()
+            .post(format!("http://127.0.0.1:{}/warm", port))
+            .timeout(remaining)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map(|_| ())
+            .map_err(|e| CommandError::backend("Failed to warm backend").with_details(e)),
+        Route::Stdio => crate::rpc::call("warm", serde_json::json!({}), Some(remaining))
+            .await
+            .map(|_| ()),
+    };
 
-    // Check if process is still running
-    let status = backend_status().await?;
-    if !status.running {
-        return Err("Backend process exited unexpectedly".to_string());
+    match &result {
+        Ok(()) => {
+            *WARMED.lock().map_err(CommandError::poisoned)? = Some((started_at, route));
+            emit_starting("warm", serde_json::json!({}));
+        }
+        Err(e) => emit_starting("warm_failed", serde_json::json!({ "error": e })),
     }
+    result
+}
 
-    Ok(BackendStatus {
-        running: true,
-        port: Some(port),
-        version: None,
-        pid: pid,
-    })
+/// Wait for a start already in progress elsewhere, up to `deadline`
+async fn wait_for_start(deadline: Instant) -> Result<(), CommandError> {
+    while BACKEND_STARTING.load(Ordering::SeqCst) {
+        if Instant::now() >= deadline {
+            return Err(CommandError::backend("Timed out waiting for the backend to start"));
+        }
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+    }
+    Ok(())
 }
 
-/// Stop the Python backend server
-#[tauri::command]
-pub async fn stop_backend() -> Result<(), String> {
-    let mut process = BACKEND_PROCESS.lock().map_err(|e| e.to_string())?;
+/// Find the Python interpreter to run the backend with.
+///
+/// In order: the `backend.python_path` config key, a `.venv` or `venv` in the
+/// project, an activated `VIRTUAL_ENV`, a poetry or uv environment for the
+/// project, then system installs.
+fn find_python(project: Option<&Path>) -> Result<String, CommandError> {
+    if let Some(configured) = crate::commands::config_value("backend.python_path")
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+    {
+        // An explicit choice is never silently replaced by another interpreter
+        if !python_runs(&configured) {
+            return Err(CommandError::invalid_input(format!(
+                "Configured backend.python_path does not run: {}",
+                configured
+            )));
+        }
+        return Ok(configured);
+    }
 
-    if let Some(mut child) = process.take() {
-        // Try graceful shutdown first
-        #[cfg(unix)]
-        {
-            use std::os::unix::process::CommandExt;
-            unsafe {
-                libc::kill(child.id() as i32, libc::SIGTERM);
+    if let Some(project) = project {
+        for venv in [".venv", "venv"] {
+            let python = venv_python(&project.join(venv));
+            if python.is_file() {
+                return Ok(python.to_string_lossy().into_owned());
             }
         }
+    }
 
-        #[cfg(windows)]
-        {
-            let _ = child.kill();
+    if let Some(venv) = std::env::var_os("VIRTUAL_ENV") {
+        let python = venv_python(Path::new(&venv));
+        if python.is_file() {
+            return Ok(python.to_string_lossy().into_owned());
         }
-
-        // Wait for process to exit
-        let _ = tokio::time::timeout(
-            tokio::time::Duration::from_secs(5),
-            tokio::task::spawn_blocking(move || {
-                let _ = child.wait();
-            }),
-        )
-        .await;
     }
 
-    // Clear port
-    {
-        let mut backend_port = BACKEND_PORT.lock().map_err(|e| e.to_string())?;
-        *backend_port = None;
+    if let Some(python) = project.and_then(managed_env_python) {
+        return Ok(python);
     }
 
-    Ok(())
+    find_system_python()
+        .ok_or_else(|| CommandError::not_found("Could not find Python installation"))
+}
+
+/// Oldest Python the backend supports
+const MIN_PYTHON_VERSION: (u32, u32) = (3, 10);
+
+/// The interpreter `start_backend` would use, for diagnostics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonInfo {
+    /// Interpreter as resolved by the search order
+    pub path: String,
+    /// `sys.executable`, following any shims or symlinks
+    pub executable: Option<String>,
+    /// Version output, e.g. "Python 3.11.4"
+    pub version: Option<String>,
+    pub minimum_version: String,
+    pub meets_minimum: bool,
+    pub roura_agent_importable: bool,
+    /// Why `import roura_agent` failed, if it did
+    pub import_error: Option<String>,
 }
 
-/// Get backend status
+/// Report which Python the backend would start with and whether it can run it
 #[tauri::command]
-pub async fn backend_status() -> Result<BackendStatus, String> {
-    let port = {
-        let backend_port = BACKEND_PORT.lock().map_err(|e| e.to_string())?;
-        *backend_port
-    };
+pub async fn detect_python(project_path: Option<String>) -> Result<PythonInfo, CommandError> {
+    tokio::task::spawn_blocking(move || {
+        let path = find_python(project_path.as_deref().map(Path::new))?;
 
-    let running = {
-        let process = BACKEND_PROCESS.lock().map_err(|e| e.to_string())?;
-        if let Some(ref child) = *process {
-            // Check if process is still alive
-            // This is a bit hacky but works for now
-            true
-        } else {
-            false
-        }
-    };
+        let run = |args: &[&str]| Command::new(&path).args(args).output();
 
-    let pid = {
-        let process = BACKEND_PROCESS.lock().map_err(|e| e.to_string())?;
-        process.as_ref().map(|c| c.id())
-    };
+        // Python 2 and early 3.x print the version to stderr
+        let version = run(&["--version"]).ok().and_then(|o| {
+            let text = if o.stdout.is_empty() { o.stderr } else { o.stdout };
+            let text = String::from_utf8_lossy(&text).trim().to_string();
+            (!text.is_empty()).then_some(text)
+        });
+        let meets_minimum = version
+            .as_deref()
+            .and_then(parse_python_version)
+            .is_some_and(|v| v >= MIN_PYTHON_VERSION);
 
-    // Try to get version from backend API
-    let version = if running {
-        if let Some(p) = port {
-            get_backend_version(p).await.ok()
-        } else {
-            None
-        }
+        let executable = run(&["-c", "import sys; print(sys.executable)"])
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+
+        let import = run(&["-c", "import roura_agent"])
+            .map_err(|e| CommandError::io("Failed to run Python").with_details(e))?;
+        let import_error = (!import.status.success()).then(|| {
+            // The last traceback line names the actual error
+            String::from_utf8_lossy(&import.stderr)
+                .lines()
+                .last()
+                .unwrap_or("import failed")
+                .to_string()
+        });
+
+        Ok(PythonInfo {
+            path,
+            executable,
+            version,
+            minimum_version: format!("{}.{}", MIN_PYTHON_VERSION.0, MIN_PYTHON_VERSION.1),
+            meets_minimum,
+            roura_agent_importable: import_error.is_none(),
+            import_error,
+        })
+    })
+    .await
+    .map_err(|e| CommandError::internal("Python detection task failed").with_details(e))?
+}
+
+/// Major and minor version from `python --version` output
+fn parse_python_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().strip_prefix("Python ")?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    // Pre-releases look like "3.13.0rc1", but the minor part is always numeric
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Interpreter inside a virtualenv directory
+fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
     } else {
-        None
-    };
+        venv.join("bin").join("python")
+    }
+}
 
-    Ok(BackendStatus {
-        running,
-        port,
-        version,
-        pid,
+/// Ask poetry, then uv, for the project's managed interpreter
+fn managed_env_python(project: &Path) -> Option<String> {
+    if !project.join("pyproject.toml").is_file() {
+        return None;
+    }
+
+    // `poetry env info -e` prints the interpreter; `uv python find` honours the project
+    let tools: [(&str, &[&str]); 2] = [
+        ("poetry", &["env", "info", "-e"]),
+        ("uv", &["python", "find"]),
+    ];
+
+    tools.into_iter().find_map(|(tool, args)| {
+        let output = Command::new(tool)
+            .args(args)
+            .current_dir(project)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Path::new(&path).is_file().then_some(path)
     })
 }
 
-/// Find Python executable
-fn find_python() -> Option<String> {
+fn python_runs(python: &str) -> bool {
+    Command::new(python)
+        .arg("--version")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Find a system-wide Python executable
+fn find_system_python() -> Option<String> {
     // Try common Python paths
     let candidates = [
         "python3",
@@ -209,25 +2093,598 @@ fn find_python() -> Option<String> {
     None
 }
 
-/// Get backend version from API
-async fn get_backend_version(port: u16) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let url = format!("http://127.0.0.1:{}/version", port);
+/// A model the backend can serve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether the backend is currently using this model
+    #[serde(default)]
+    pub active: bool,
+}
 
-    let response = client
-        .get(&url)
-        .timeout(std::time::Duration::from_secs(2))
-        .send()
+/// How long a fetched model list is reused
+const MODEL_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Last model list, with the port it came from and when it was fetched
+static MODEL_CACHE: Mutex<Option<(u16, Instant, Vec<ModelInfo>)>> = Mutex::new(None);
+
+/// Port of the backend to call, tracked or external
+pub(crate) fn api_port() -> Result<u16, CommandError> {
+    backend_port()
+        .or_else(external_backend_port)
+        .ok_or_else(|| CommandError::backend("Backend is not running"))
+}
+
+/// Where chat traffic goes: a port for HTTP, or the stdio pipes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Route {
+    Http(u16),
+    Stdio,
+}
+
+/// The route to the running backend, preferring a stdio connection
+pub(crate) fn api_route() -> Result<Route, CommandError> {
+    if crate::rpc::is_attached() {
+        return Ok(Route::Stdio);
+    }
+    api_port().map(Route::Http)
+}
+
+/// List the models the backend offers, reusing a list fetched in the last 30 seconds
+#[tauri::command]
+pub async fn list_models() -> Result<Vec<ModelInfo>, CommandError> {
+    let port = api_port()?;
+    {
+        let cache = MODEL_CACHE.lock().map_err(CommandError::poisoned)?;
+        if let Some((cached_port, fetched_at, models)) = &*cache {
+            if *cached_port == port && fetched_at.elapsed() < MODEL_CACHE_TTL {
+                return Ok(models.clone());
+            }
+        }
+    }
+
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/models", port);
+    let response = send_with_retry(|| client.get(&url).timeout(Duration::from_secs(5)))
         .await
-        .map_err(|e| format!("Failed to connect to backend: {}", e))?;
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| CommandError::backend("Failed to list models").with_details(e))?;
 
-    let data: serde_json::Value = response
+    // Accept a bare list or `{ "models": [...] }`
+    let body: serde_json::Value = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| CommandError::parse("Failed to parse response").with_details(e))?;
+    let list = body.get("models").cloned().unwrap_or(body);
+    let models: Vec<ModelInfo> = serde_json::from_value(list)
+        .map_err(|e| CommandError::parse("Failed to parse model list").with_details(e))?;
+
+    if let Ok(mut cache) = MODEL_CACHE.lock() {
+        *cache = Some((port, Instant::now(), models.clone()));
+    }
+    Ok(models)
+}
+
+/// Switch the backend to `name` and save it as the `model` setting
+#[tauri::command]
+pub async fn set_active_model(name: String) -> Result<(), CommandError> {
+    if name.trim().is_empty() {
+        return Err(CommandError::invalid_input("Model name must not be empty"));
+    }
+
+    let port = api_port()?;
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/models/active", port);
+    send_with_retry(|| {
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "name": name }))
+            .timeout(Duration::from_secs(10))
+    })
+    .await
+    .and_then(|r| r.error_for_status())
+    .map_err(|e| CommandError::backend("Failed to switch model").with_details(e))?;
+
+    // The cached list's `active` flags are now stale
+    if let Ok(mut cache) = MODEL_CACHE.lock() {
+        *cache = None;
+    }
+
+    // Messages forward `model` from config, so later requests keep using it
+    crate::commands::set_config("model".to_string(), serde_json::Value::String(name)).await
+}
+
+/// Get backend version from a stdio backend
+async fn get_rpc_version() -> Result<String, CommandError> {
+    let result =
+        crate::rpc::call("version", serde_json::json!({}), Some(Duration::from_secs(2))).await?;
+
+    result
+        .get("version")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| CommandError::backend("No version in response"))
+}
+
+/// Largest `/version` body read before the server is assumed not to be a Roura backend
+const MAX_VERSION_BODY_BYTES: usize = 64 * 1024;
+/// Longest version string accepted from `/version`
+const MAX_VERSION_LEN: usize = 64;
+
+/// Get backend version from API.
+///
+/// An oversized, non-JSON or unexpected body means something other than a
+/// Roura backend holds the port.
+async fn get_backend_version(port: u16) -> Result<String, CommandError> {
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/version", port);
+
+    let mut response =
+        send_with_retry(|| client.get(&url).timeout(std::time::Duration::from_secs(2)))
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| CommandError::backend("Failed to connect to backend").with_details(e))?;
+
+    let not_roura = || CommandError::backend("Server on the backend port is not a Roura backend");
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_VERSION_BODY_BYTES as u64)
+    {
+        return Err(not_roura());
+    }
+
+    // Read incrementally so a chunked response can't grow past the cap either
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| CommandError::backend("Failed to read response").with_details(e))?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_VERSION_BODY_BYTES {
+            return Err(not_roura());
+        }
+    }
 
-    data.get("version")
+    let data: serde_json::Value = serde_json::from_slice(&body).map_err(|_| not_roura())?;
+    data.as_object()
+        .and_then(|obj| obj.get("version"))
         .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty() && v.len() <= MAX_VERSION_LEN)
         .map(|s| s.to_string())
-        .ok_or_else(|| "No version in response".to_string())
+        .ok_or_else(not_roura)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::{mock_backend, response_head, serve, temp_config_root};
+
+    fn stderr_line(line: &str, run: u64) -> BackendLogLine {
+        BackendLogLine {
+            stream: "stderr".to_string(),
+            line: line.to_string(),
+            run,
+        }
+    }
+
+    /// A project with an empty interpreter file in each listed venv directory
+    fn fake_project(venvs: &[&str]) -> tempfile::TempDir {
+        let project = tempfile::tempdir().unwrap();
+        for venv in venvs {
+            let python = venv_python(&project.path().join(venv));
+            std::fs::create_dir_all(python.parent().unwrap()).unwrap();
+            std::fs::write(&python, "").unwrap();
+        }
+        project
+    }
+
+    #[test]
+    fn project_dot_venv_is_preferred() {
+        let _root = temp_config_root();
+        let project = fake_project(&[".venv", "venv"]);
+
+        let python = find_python(Some(project.path())).unwrap();
+        assert_eq!(
+            PathBuf::from(python),
+            venv_python(&project.path().join(".venv"))
+        );
+    }
+
+    #[test]
+    fn project_venv_is_found_without_dot_venv() {
+        let _root = temp_config_root();
+        let project = fake_project(&["venv"]);
+
+        let python = find_python(Some(project.path())).unwrap();
+        assert_eq!(
+            PathBuf::from(python),
+            venv_python(&project.path().join("venv"))
+        );
+    }
+
+    #[test]
+    fn venv_directory_without_interpreter_is_skipped() {
+        let _root = temp_config_root();
+        let project = fake_project(&["venv"]);
+        std::fs::create_dir_all(project.path().join(".venv").join("bin")).unwrap();
+
+        let python = find_python(Some(project.path())).unwrap();
+        assert_eq!(
+            PathBuf::from(python),
+            venv_python(&project.path().join("venv"))
+        );
+    }
+
+    #[tokio::test]
+    async fn configured_python_wins_and_must_run() {
+        let _root = temp_config_root();
+        let project = fake_project(&[".venv"]);
+        let missing = project.path().join("no-such-python");
+        crate::commands::set_config(
+            "backend.python_path".to_string(),
+            serde_json::json!(missing.to_string_lossy()),
+        )
+        .await
+        .unwrap();
+
+        let err = find_python(Some(project.path())).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+    }
+
+    #[test]
+    fn python_version_output_is_parsed() {
+        assert_eq!(parse_python_version("Python 3.11.4\n"), Some((3, 11)));
+        assert_eq!(parse_python_version("Python 3.13.0rc1"), Some((3, 13)));
+        assert_eq!(parse_python_version("Python 2.7"), Some((2, 7)));
+        assert_eq!(parse_python_version("python3: not found"), None);
+    }
+
+    async fn set_retry_policy(retries: u64, base_delay_ms: u64) {
+        use crate::commands::set_config;
+
+        set_config(
+            "backend.http_retries".to_string(),
+            serde_json::json!(retries),
+        )
+        .await
+        .unwrap();
+        set_config(
+            "backend.http_retry_base_delay_ms".to_string(),
+            serde_json::json!(base_delay_ms),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn connection_refused_twice_then_served() {
+        let _root = temp_config_root();
+        set_retry_policy(3, 200).await;
+
+        // Free a port, then only start listening between the second and third attempt
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+                .await
+                .unwrap();
+            let ok = vec![
+                response_head("200 OK", "application/json"),
+                "{}".to_string(),
+            ];
+            serve(listener, vec![ok]).recv().await
+        });
+
+        let started = Instant::now();
+        let client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{}/version", port);
+        let response = send_with_retry(|| client.get(&url)).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        // 200ms after the first refusal plus 400ms after the second
+        assert!(started.elapsed() >= Duration::from_millis(600));
+        assert!(server.await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn error_status_is_not_retried() {
+        let _root = temp_config_root();
+        set_retry_policy(3, 10).await;
+        let unavailable = vec![response_head("503 Service Unavailable", "text/plain")];
+        let (port, mut requests) = mock_backend(vec![unavailable]).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{}/chat", port);
+        let response = send_with_retry(|| client.post(&url)).await.unwrap();
+
+        assert_eq!(response.status(), 503);
+        assert!(requests.recv().await.is_some());
+        assert!(requests.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn retries_give_up_on_a_dead_port() {
+        let _root = temp_config_root();
+        set_retry_policy(1, 10).await;
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{}/version", port);
+        let err = send_with_retry(|| client.get(&url)).await.unwrap_err();
+        assert!(err.is_connect());
+    }
+
+    #[tokio::test]
+    async fn stderr_tail_skips_earlier_runs() {
+        let run = BACKEND_RUN.fetch_add(1, Ordering::SeqCst) + 1;
+        push_log_line(stderr_line("old traceback", run - 1));
+        push_log_line(stderr_line("ImportError: no module named roura", run));
+
+        assert_eq!(stderr_tail().await, "ImportError: no module named roura");
+    }
+
+    /// A shell that has installed `trap` and is idling
+    #[cfg(unix)]
+    fn trapping_shell(trap: &str) -> Child {
+        let script = format!("{}; echo ready; while :; do sleep 1; done", trap);
+        let mut child = Command::new("sh")
+            .args(["-c", &script])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        assert_eq!(line.trim(), "ready");
+        child
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn backends_ignoring_sigterm_are_killed() {
+        let child = trapping_shell("trap '' TERM");
+        let pid = child.id();
+
+        let started = Instant::now();
+        assert!(stop_child(child, Duration::from_millis(300)).await);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(!pid_alive(pid));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn cooperative_backends_are_not_killed() {
+        let child = trapping_shell("trap 'exit 0' TERM");
+        let pid = child.id();
+
+        assert!(!stop_child(child, Duration::from_secs(5)).await);
+        assert!(!pid_alive(pid));
+    }
+
+    #[test]
+    fn exited_adopted_backends_are_forgotten() {
+        let _root = temp_config_root();
+        let mut child = Command::new(if cfg!(windows) { "cmd" } else { "true" });
+        if cfg!(windows) {
+            child.args(["/C", "exit"]);
+        }
+        let mut child = child.spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+
+        let record = PidFile {
+            pid,
+            port: 8765,
+            python: "python3".to_string(),
+        };
+        write_pid_file(&record).unwrap();
+        *ADOPTED_PID.lock().unwrap() = Some(pid);
+        *BACKEND_PORT.lock().unwrap() = Some(8765);
+
+        assert!(!backend_tracked());
+        assert_eq!(*ADOPTED_PID.lock().unwrap(), None);
+        assert_eq!(backend_port(), None);
+        assert!(read_pid_file().is_none());
+    }
+
+    #[test]
+    fn running_adopted_backends_stay_tracked() {
+        let _root = temp_config_root();
+        *ADOPTED_PID.lock().unwrap() = Some(std::process::id());
+
+        assert_eq!(adopted_pid(), Some(std::process::id()));
+        assert!(backend_tracked());
+
+        *ADOPTED_PID.lock().unwrap() = None;
+    }
+
+    fn launch_spec(python: &str, transport: Transport) -> LaunchSpec {
+        LaunchSpec {
+            python: python.to_string(),
+            port: 8765,
+            transport,
+            detached: false,
+        }
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn default_command_gets_port_and_host_appended() {
+        let spec = launch_spec("/usr/bin/python3", Transport::Http);
+        let argv = backend_argv(&spec, &strings(DEFAULT_BACKEND_COMMAND), &[]);
+        assert_eq!(
+            argv,
+            [
+                "/usr/bin/python3",
+                "-m",
+                "roura_agent.server",
+                "--port",
+                "8765",
+                "--host",
+                "127.0.0.1"
+            ]
+        );
+
+        let spec = launch_spec("/usr/bin/python3", Transport::Stdio);
+        let argv = backend_argv(&spec, &strings(DEFAULT_BACKEND_COMMAND), &[]);
+        assert_eq!(
+            argv,
+            ["/usr/bin/python3", "-m", "roura_agent.server", "--stdio"]
+        );
+    }
+
+    #[test]
+    fn placeholders_are_substituted_in_every_element() {
+        let spec = launch_spec("python", Transport::Http);
+        let template = strings(&[
+            "uv",
+            "run",
+            "serve",
+            "--bind={host}:{port}",
+            "--py={python}",
+        ]);
+        let extra = strings(&["--log-port", "{port}"]);
+
+        let argv = backend_argv(&spec, &template, &extra);
+        assert_eq!(
+            argv,
+            [
+                "uv",
+                "run",
+                "serve",
+                "--bind=127.0.0.1:8765",
+                "--py=python",
+                "--log-port",
+                "8765"
+            ]
+        );
+    }
+
+    #[test]
+    fn values_with_spaces_stay_one_argument() {
+        let python = r"C:\Program Files\Python 3.12\python.exe";
+        let spec = launch_spec(python, Transport::Http);
+        let template = strings(&["{python}", "-m", "roura_agent.server", "--port", "{port}"]);
+        let extra = strings(&["--model", "my model \"v2\""]);
+
+        let argv = backend_argv(&spec, &template, &extra);
+        assert_eq!(argv[0], python);
+        assert_eq!(argv.len(), 7);
+        assert_eq!(argv[6], "my model \"v2\"");
+    }
+
+    #[test]
+    fn programs_match_pathext_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("uv.cmd"), "").unwrap();
+        std::fs::write(dir.path().join("poetry.bat"), "").unwrap();
+        let dirs = || vec![PathBuf::from("/nonexistent"), dir.path().to_path_buf()];
+        let extensions = strings(&["exe", "cmd", "bat"]);
+
+        assert_eq!(
+            find_program_in("uv", dirs(), &extensions),
+            Some(dir.path().join("uv.cmd"))
+        );
+        assert!(find_program_in("poetry", dirs(), &extensions).is_some());
+        assert_eq!(find_program_in("uv", dirs(), &[]), None);
+        // A name with an extension is only looked up as given
+        assert_eq!(find_program_in("uv.exe", dirs(), &extensions), None);
+
+        let path = dir.path().join("uv");
+        assert_eq!(
+            find_program_in(&path.to_string_lossy(), dirs(), &extensions),
+            Some(dir.path().join("uv.cmd"))
+        );
+    }
+
+    #[test]
+    fn out_of_range_counts_saturate() {
+        let _root = crate::commands::test_support::temp_config_root();
+        let mut config = std::collections::HashMap::new();
+        config.insert(
+            "backend.restart_max_attempts".to_string(),
+            serde_json::json!(u64::from(u32::MAX) + 1),
+        );
+        config.insert(
+            "backend.http_retries".to_string(),
+            serde_json::json!(u64::MAX),
+        );
+        crate::commands::save_config(&config).unwrap();
+
+        assert_eq!(RestartPolicy::load().max_attempts, u32::MAX);
+        assert_eq!(HttpRetry::load().retries, u32::MAX);
+    }
+
+    fn numbered_lines(range: std::ops::Range<usize>) -> String {
+        range.map(|i| format!("line {i}\n")).collect()
+    }
+
+    #[tokio::test]
+    async fn log_polls_continue_across_a_rotation() {
+        let root = crate::commands::test_support::temp_config_root();
+        let log = root.dir.path().join("backend.log");
+        std::fs::write(&log, numbered_lines(0..3)).unwrap();
+
+        let first = read_backend_log(None, Some(0)).await.unwrap();
+        assert_eq!(first.text, numbered_lines(0..3));
+        assert!(!first.rotated);
+
+        // Two more lines reach the old file before it is renamed away
+        std::fs::write(&log, numbered_lines(0..5)).unwrap();
+        std::fs::rename(&log, root.dir.path().join("backend.log.1")).unwrap();
+        std::fs::write(&log, numbered_lines(5..6)).unwrap();
+
+        let next = read_backend_log(None, Some(first.offset)).await.unwrap();
+        assert!(next.rotated);
+        assert_eq!(next.text, numbered_lines(3..6));
+        assert_eq!(next.offset, numbered_lines(5..6).len() as u64);
+
+        // Tailing fills in from the old file too
+        let tail = read_backend_log(Some(4), None).await.unwrap();
+        assert_eq!(tail.text, numbered_lines(2..6));
+    }
+
+    #[tokio::test]
+    async fn log_tails_longer_than_the_file_return_all_of_it() {
+        let root = crate::commands::test_support::temp_config_root();
+        std::fs::write(root.dir.path().join("backend.log"), numbered_lines(0..3)).unwrap();
+
+        let chunk = read_backend_log(Some(50), None).await.unwrap();
+        assert_eq!(chunk.text, numbered_lines(0..3));
+        assert_eq!(chunk.offset, numbered_lines(0..3).len() as u64);
+        assert!(!chunk.truncated);
+    }
+
+    #[tokio::test]
+    async fn log_reads_stop_at_the_byte_cap_on_a_line_boundary() {
+        let root = crate::commands::test_support::temp_config_root();
+        let content = numbered_lines(0..40_000);
+        assert!(content.len() as u64 > MAX_LOG_CHUNK_BYTES);
+        std::fs::write(root.dir.path().join("backend.log"), &content).unwrap();
+
+        let first = read_backend_log(None, Some(0)).await.unwrap();
+        assert!(first.truncated);
+        assert!(first.offset <= MAX_LOG_CHUNK_BYTES);
+        assert!(first.text.ends_with('\n'));
+        assert_eq!(first.text, content[..first.offset as usize]);
+
+        let rest = read_backend_log(None, Some(first.offset)).await.unwrap();
+        assert!(!rest.truncated);
+        assert_eq!(first.text + &rest.text, content);
+    }
 }
@@ -1,313 +1,5835 @@
 // Roura Agent Desktop - Tauri Commands
 // © Roura.io
 
+use crate::backend::Route;
+use crate::error::CommandError;
+use crate::{config_schema, conversation, conversation::ConversationTurn};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
 
 /// Message sent to the agent
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AgentMessage {
     pub content: String,
     pub attachments: Option<Vec<String>>,
     pub context: Option<HashMap<String, String>>,
+    /// Caller-chosen id used to cancel the request; generated when omitted
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Add the project's most relevant memory notes to `context` under `memory`
+    #[serde(default)]
+    pub include_memory: bool,
 }
 
 /// Response from the agent
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AgentResponse {
     pub content: String,
     pub tool_calls: Option<Vec<ToolCall>>,
     pub finished: bool,
+    #[serde(default)]
+    pub request_id: String,
+    /// Set when the response was cut short by `cancel_message`
+    #[serde(default)]
+    pub cancelled: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Incremental update emitted as `agent-chunk` while a response streams in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentChunk {
+    /// Text added by this chunk
+    pub delta: String,
+    /// Full response text so far
+    pub content: String,
+    pub tool_call: Option<ToolCall>,
+    pub finished: bool,
+    pub request_id: String,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolCall {
+    /// Key for `tool-call-*` events; generated when the backend omits it
+    #[serde(default)]
+    pub id: String,
     pub name: String,
+    #[serde(default)]
     pub arguments: HashMap<String, serde_json::Value>,
     pub result: Option<String>,
+    #[serde(default)]
+    pub status: ToolCallStatus,
+}
+
+/// Lifecycle of a tool call, as reported by the backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    Pending,
+    Running,
+    /// Backends without progress reporting only send finished calls
+    #[default]
+    Done,
+    Error,
+}
+
+impl ToolCallStatus {
+    fn is_terminal(self) -> bool {
+        matches!(self, Self::Done | Self::Error)
+    }
+}
+
+/// Partial output from a running tool, emitted as `tool-call-progress`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolProgress {
+    pub id: String,
+    pub output: String,
 }
 
 /// Project information
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Project {
     pub name: String,
     pub path: String,
     pub last_opened: Option<String>,
+    /// Pinned projects are never dropped from the recent list
+    #[serde(default)]
+    pub pinned: bool,
+    /// Whether the path is on disk; checked on every read and never saved
+    #[serde(skip_deserializing, default)]
+    pub exists: bool,
+    /// Repository state when last opened or refreshed
+    #[serde(default)]
+    pub git: Option<GitInfo>,
+}
+
+/// Git state of a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GitInfo {
+    /// Current branch, or "HEAD" when detached
+    pub branch: String,
+    /// Short commit hash, absent in a repo with no commits
+    pub commit: Option<String>,
+    pub dirty: bool,
 }
 
 /// Memory note
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MemoryNote {
     pub id: String,
     pub content: String,
     pub category: String,
     pub tags: Vec<String>,
     pub created_at: String,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Decayed relevance in [0, 1]
+    #[serde(default = "default_relevance")]
+    pub relevance: f64,
+    /// Pinned notes are exempt from relevance decay
+    #[serde(default)]
+    pub pinned: bool,
+    /// Absolute paths of attached files, stored relative to `.roura`
+    #[serde(default)]
+    pub attachments: Vec<String>,
+}
+
+fn default_relevance() -> f64 {
+    1.0
+}
+
+/// A page of memory notes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryPage {
+    pub notes: Vec<MemoryNote>,
+    /// Number of notes matching the filters, across all pages
+    pub total: usize,
 }
 
-/// Configuration values
+/// Notes in one memory category
 #[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryCount {
+    pub category: String,
+    pub count: usize,
+}
+
+/// Memory categories with their note counts, largest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryCategories {
+    pub categories: Vec<CategoryCount>,
+    /// Number of notes across all categories
+    pub total: usize,
+}
+
+/// Configuration values, as described by `get_command_schemas`
+#[cfg(feature = "schema")]
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Config {
     pub values: HashMap<String, serde_json::Value>,
 }
 
-/// Send a message to the agent
-#[tauri::command]
-pub async fn send_message(message: AgentMessage) -> Result<AgentResponse, String> {
-    // This will communicate with the Python backend
-    // For now, return a placeholder
-    Ok(AgentResponse {
-        content: format!("Received: {}", message.content),
-        tool_calls: None,
-        finished: true,
-    })
+/// Environment variable that relocates the config directory, e.g. for portable installs
+const CONFIG_ROOT_ENV: &str = "ROURA_CONFIG_DIR";
+/// File in the default config directory naming the root chosen with `set_config_root`
+const CONFIG_ROOT_POINTER: &str = "config_root";
+
+/// Config directory in use, resolved on first use and repointed by `set_config_root`
+static CONFIG_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Path to the desktop config directory.
+///
+/// In order: `ROURA_CONFIG_DIR`, a root chosen with `set_config_root`, then
+/// the platform config directory.
+pub(crate) fn config_root() -> Result<PathBuf, CommandError> {
+    if let Some(root) = CONFIG_ROOT.read().ok().and_then(|root| root.clone()) {
+        return Ok(root);
+    }
+
+    let root = resolve_config_root()
+        .ok_or_else(|| CommandError::not_found("Could not find config directory"))?;
+    let mut slot = CONFIG_ROOT.write().map_err(CommandError::poisoned)?;
+    Ok(slot.get_or_insert(root).clone())
+}
+
+/// Point config reads and writes at `root` from now on
+fn use_config_root(root: PathBuf) -> Result<(), CommandError> {
+    *CONFIG_ROOT.write().map_err(CommandError::poisoned)? = Some(root);
+    // The new root may hold an older config.json
+    CONFIG_MIGRATED.store(false, Ordering::SeqCst);
+    Ok(())
 }
 
-/// Get configuration value
+fn default_config_root() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("roura-agent"))
+}
+
+fn resolve_config_root() -> Option<PathBuf> {
+    if let Some(root) = std::env::var_os(CONFIG_ROOT_ENV).filter(|r| !r.is_empty()) {
+        return Some(PathBuf::from(root));
+    }
+
+    let default = default_config_root()?;
+    let chosen = std::fs::read_to_string(default.join(CONFIG_ROOT_POINTER))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    Some(chosen.map(PathBuf::from).unwrap_or(default))
+}
+
+/// Keep config in `path` from now on, across launches; `None` goes back to the default.
+///
+/// Returns the directory now in use, which `ROURA_CONFIG_DIR` still
+/// overrides when set.
 #[tauri::command]
-pub async fn get_config(key: String) -> Result<Option<serde_json::Value>, String> {
-    // Load from config file
-    let config_path = dirs::config_dir()
-        .ok_or("Could not find config directory")?
-        .join("roura-agent")
-        .join("config.json");
+pub async fn set_config_root(
+    app: tauri::AppHandle,
+    path: Option<String>,
+) -> Result<String, CommandError> {
+    let default = default_config_root()
+        .ok_or_else(|| CommandError::not_found("Could not find config directory"))?;
+    let pointer = default.join(CONFIG_ROOT_POINTER);
+
+    let next = match path {
+        Some(path) => {
+            let root = PathBuf::from(&path);
+            if !root.is_absolute() {
+                return Err(CommandError::invalid_input(format!(
+                    "Config root must be an absolute path: {}",
+                    path
+                )));
+            }
+            std::fs::create_dir_all(&root)
+                .map_err(|e| CommandError::io("Failed to create config root").with_details(e))?;
+            std::fs::create_dir_all(&default).map_err(|e| {
+                CommandError::io("Failed to create config directory").with_details(e)
+            })?;
+            std::fs::write(&pointer, root.to_string_lossy().as_bytes())
+                .map_err(|e| CommandError::io("Failed to save config root").with_details(e))?;
+            root
+        }
+        None => {
+            match std::fs::remove_file(&pointer) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(CommandError::io("Failed to reset config root").with_details(e))
+                }
+            }
+            default
+        }
+    };
+
+    let next = match std::env::var_os(CONFIG_ROOT_ENV).filter(|r| !r.is_empty()) {
+        Some(root) => PathBuf::from(root),
+        None => next,
+    };
+    use_config_root(next.clone())?;
+    if let Err(e) = crate::watcher::start(app) {
+        tracing::error!(error = %e, "Failed to restart config watcher");
+    }
+    Ok(next.to_string_lossy().into_owned())
+}
+
+/// Load the global config map, or an empty map if no config file exists
+pub(crate) fn load_config() -> Result<HashMap<String, serde_json::Value>, CommandError> {
+    let config_path = config_root()?.join("config.json");
 
     if !config_path.exists() {
-        return Ok(None);
+        return Ok(HashMap::new());
     }
 
     let content = std::fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-
-    let config: HashMap<String, serde_json::Value> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
+        .map_err(|e| CommandError::io("Failed to read config").with_details(e))?;
 
-    Ok(config.get(&key).cloned())
+    serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse config").with_details(e))
 }
 
-/// Set configuration value
-#[tauri::command]
-pub async fn set_config(key: String, value: serde_json::Value) -> Result<(), String> {
-    let config_dir = dirs::config_dir()
-        .ok_or("Could not find config directory")?
-        .join("roura-agent");
+/// Atomically replace the global config file with `config`
+pub(crate) fn save_config(
+    config: &HashMap<String, serde_json::Value>,
+) -> Result<(), CommandError> {
+    let config_dir = config_root()?;
 
     std::fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        .map_err(|e| CommandError::io("Failed to create config directory").with_details(e))?;
 
-    let config_path = config_dir.join("config.json");
+    write_json_atomic(&config_dir.join("config.json"), config)
+}
 
-    // Load existing config
-    let mut config: HashMap<String, serde_json::Value> = if config_path.exists() {
-        let content = std::fs::read_to_string(&config_path)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        HashMap::new()
-    };
+/// Look up a single config value, treating unreadable config as unset
+pub(crate) fn config_value(key: &str) -> Option<serde_json::Value> {
+    lookup_config(&load_config().ok()?, key).cloned()
+}
 
-    // Update value
-    config.insert(key, value);
+// Dotted keys like `ui.theme` address nested objects (`{"ui": {"theme": ...}}`).
+// A literal top-level key that contains a dot always takes precedence over the
+// nested path, so flat keys written before nesting was supported stay reachable.
 
-    // Save
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+/// Resolve a possibly dotted key against the config map
+pub(crate) fn lookup_config<'a>(
+    config: &'a HashMap<String, serde_json::Value>,
+    key: &str,
+) -> Option<&'a serde_json::Value> {
+    if let Some(value) = config.get(key) {
+        return Some(value);
+    }
 
-    std::fs::write(&config_path, content)
-        .map_err(|e| format!("Failed to write config: {}", e))?;
+    let mut parts = key.split('.');
+    let mut current = config.get(parts.next()?)?;
+    for part in parts {
+        current = current.as_object()?.get(part)?;
+    }
 
-    Ok(())
+    Some(current)
 }
 
-/// List recent projects
-#[tauri::command]
-pub async fn list_projects() -> Result<Vec<Project>, String> {
-    let config_dir = dirs::config_dir()
-        .ok_or("Could not find config directory")?
-        .join("roura-agent");
+/// Set a possibly dotted key, creating intermediate objects as needed.
+///
+/// Fails rather than overwriting when an intermediate segment holds a non-object value.
+pub(crate) fn insert_config(
+    config: &mut HashMap<String, serde_json::Value>,
+    key: &str,
+    value: serde_json::Value,
+) -> Result<(), CommandError> {
+    let parts: Vec<&str> = key.split('.').collect();
+
+    if parts.len() == 1 || config.contains_key(key) {
+        config.insert(key.to_string(), value);
+        return Ok(());
+    }
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(CommandError::invalid_input(format!("Invalid config key: {}", key)));
+    }
 
-    let projects_path = config_dir.join("recent_projects.json");
+    let (leaf, parents) = parts
+        .split_last()
+        .ok_or_else(|| CommandError::invalid_input("Empty config key"))?;
+    let mut current = config
+        .entry(parents[0].to_string())
+        .or_insert_with(|| serde_json::json!({}));
 
-    if !projects_path.exists() {
-        return Ok(Vec::new());
+    for (depth, part) in parents.iter().enumerate().skip(1) {
+        current = current
+            .as_object_mut()
+            .ok_or_else(|| not_an_object(&parents[..depth]))?
+            .entry(part.to_string())
+            .or_insert_with(|| serde_json::json!({}));
     }
 
-    let content = std::fs::read_to_string(&projects_path)
-        .map_err(|e| format!("Failed to read projects: {}", e))?;
+    current
+        .as_object_mut()
+        .ok_or_else(|| not_an_object(parents))?
+        .insert(leaf.to_string(), value);
 
-    let projects: Vec<Project> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse projects: {}", e))?;
+    Ok(())
+}
 
-    Ok(projects)
+/// Remove a possibly dotted key, returning the removed value
+pub(crate) fn remove_config_key(
+    config: &mut HashMap<String, serde_json::Value>,
+    key: &str,
+) -> Option<serde_json::Value> {
+    if let Some(value) = config.remove(key) {
+        return Some(value);
+    }
+
+    let (leaf, parents) = key.rsplit_once('.')?;
+    let mut parts = parents.split('.');
+    let mut current = config.get_mut(parts.next()?)?;
+    for part in parts {
+        current = current.as_object_mut()?.get_mut(part)?;
+    }
+
+    current.as_object_mut()?.remove(leaf)
 }
 
-/// Open a project
-#[tauri::command]
-pub async fn open_project(path: String) -> Result<Project, String> {
-    let project_path = PathBuf::from(&path);
+/// Flatten nested config objects into dotted leaf paths
+pub(crate) fn flatten_config(
+    config: &HashMap<String, serde_json::Value>,
+) -> BTreeMap<String, serde_json::Value> {
+    fn walk(
+        path: String,
+        value: &serde_json::Value,
+        out: &mut BTreeMap<String, serde_json::Value>,
+    ) {
+        match value.as_object() {
+            Some(map) if !map.is_empty() => {
+                for (key, child) in map {
+                    walk(format!("{}.{}", path, key), child, out);
+                }
+            }
+            _ => {
+                out.insert(path, value.clone());
+            }
+        }
+    }
 
-    if !project_path.exists() {
-        return Err(format!("Project path does not exist: {}", path));
+    let mut out = BTreeMap::new();
+    for (key, value) in config {
+        walk(key.clone(), value, &mut out);
     }
+    out
+}
 
-    let name = project_path
+/// Whether unknown config keys are rejected (`config.strict`, on by default)
+fn strict_config(config: &HashMap<String, serde_json::Value>) -> bool {
+    lookup_config(config, "config.strict")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true)
+}
+
+fn not_an_object(path: &[&str]) -> CommandError {
+    CommandError::invalid_input(format!(
+        "Cannot set nested key: {} is not an object",
+        path.join(".")
+    ))
+}
+
+/// Write `value` as pretty JSON to `path` without ever leaving a partial file.
+///
+/// The data goes to a hidden sibling temp file first and is then renamed over
+/// the target, which is atomic on the same filesystem.
+pub(crate) fn write_json_atomic<T: Serialize + ?Sized>(
+    path: &Path,
+    value: &T,
+) -> Result<(), CommandError> {
+    let content = serde_json::to_string_pretty(value).map_err(|e| {
+        CommandError::internal(format!("Failed to serialize {}", path.display())).with_details(e)
+    })?;
+
+    write_atomic(path, content.as_bytes())
+}
+
+/// Write `content` to `path` the way `write_json_atomic` does
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> Result<(), CommandError> {
+    use std::io::Write;
+
+    let file_name = path
         .file_name()
         .and_then(|n| n.to_str())
-        .unwrap_or("Unknown")
-        .to_string();
+        .ok_or_else(|| {
+            CommandError::invalid_input(format!("Invalid file path: {}", path.display()))
+        })?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}{}",
+        file_name,
+        uuid::Uuid::new_v4(),
+        ATOMIC_TEMP_SUFFIX
+    ));
 
-    let project = Project {
-        name,
-        path: path.clone(),
-        last_opened: Some(chrono::Utc::now().to_rfc3339()),
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
     };
 
-    // Update recent projects
-    let config_dir = dirs::config_dir()
-        .ok_or("Could not find config directory")?
-        .join("roura-agent");
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        CommandError::io(format!("Failed to write {}", path.display())).with_details(e)
+    })
+}
 
-    std::fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+/// Suffix of the temp files created by `write_json_atomic`
+pub(crate) const ATOMIC_TEMP_SUFFIX: &str = ".tmp";
 
-    let projects_path = config_dir.join("recent_projects.json");
+/// An in-flight `send_message` call
+struct ActiveRequest {
+    cancel: tokio::sync::oneshot::Sender<()>,
+    started_at: String,
+    project: Option<String>,
+}
 
-    let mut projects: Vec<Project> = if projects_path.exists() {
-        let content = std::fs::read_to_string(&projects_path).unwrap_or_default();
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        Vec::new()
-    };
+/// Receiving end of `ActiveRequest::cancel`, safe to wait on from every `select!` of a loop
+struct CancelSignal(Option<tokio::sync::oneshot::Receiver<()>>);
 
-    // Remove existing entry for same path
-    projects.retain(|p| p.path != path);
+impl CancelSignal {
+    /// Resolve once the request is cancelled.
+    ///
+    /// A sender dropped without cancelling means nothing can cancel the request
+    /// any more, so this then never resolves instead of polling a spent receiver.
+    async fn cancelled(&mut self) {
+        if let Some(receiver) = self.0.as_mut() {
+            let result = receiver.await;
+            self.0 = None;
+            if result.is_ok() {
+                return;
+            }
+        }
+        std::future::pending().await
+    }
+}
 
-    // Add to front
-    projects.insert(0, project.clone());
+/// An in-flight request, from `list_active_requests`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestInfo {
+    pub request_id: String,
+    pub started_at: String,
+    pub project: Option<String>,
+}
 
-    // Keep only last 10
-    projects.truncate(10);
+/// In-flight requests by id
+static ACTIVE_REQUESTS: Mutex<BTreeMap<String, ActiveRequest>> = Mutex::new(BTreeMap::new());
 
-    // Save
-    let content = serde_json::to_string_pretty(&projects)
-        .map_err(|e| format!("Failed to serialize projects: {}", e))?;
+/// Removes a request from `ACTIVE_REQUESTS` however its call ends
+struct ActiveRequestGuard(String);
 
-    std::fs::write(&projects_path, content)
-        .map_err(|e| format!("Failed to write projects: {}", e))?;
+impl Drop for ActiveRequestGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_REQUESTS.lock() {
+            active.remove(&self.0);
+        }
+    }
+}
 
-    Ok(project)
+/// Concurrent requests allowed when `backend.max_concurrent_requests` isn't set.
+/// The Python backend handles one request at a time.
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 1;
+/// Waiting requests allowed when `backend.max_queued_requests` isn't set
+const DEFAULT_MAX_QUEUED_REQUESTS: usize = 8;
+/// How long a queued request waits for a slot before giving up
+const REQUEST_QUEUE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Slots for requests the backend works on at once, with the size they were built for
+static REQUEST_SLOTS: Mutex<Option<(usize, Arc<tokio::sync::Semaphore>)>> = Mutex::new(None);
+/// Requests waiting for a slot
+static QUEUED_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a request as queued until dropped
+struct QueuedRequest;
+
+impl Drop for QueuedRequest {
+    fn drop(&mut self) {
+        QUEUED_REQUESTS.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
-/// Get memory for current project
-#[tauri::command]
-pub async fn get_memory(project_path: String) -> Result<Vec<MemoryNote>, String> {
-    let memory_path = PathBuf::from(&project_path)
-        .join(".roura")
-        .join("memory.json");
+/// Wait for a request slot, failing with `Busy` if the queue is full or the wait times out.
+///
+/// The permit frees the slot when dropped, including on error or cancellation.
+async fn acquire_request_slot() -> Result<tokio::sync::OwnedSemaphorePermit, CommandError> {
+    let slots = request_slots()?;
 
-    if !memory_path.exists() {
-        return Ok(Vec::new());
+    // Free slot: no need to queue
+    if let Ok(permit) = slots.clone().try_acquire_owned() {
+        return Ok(permit);
     }
 
-    let content = std::fs::read_to_string(&memory_path)
-        .map_err(|e| format!("Failed to read memory: {}", e))?;
+    let max_queued = config_value("backend.max_queued_requests")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_MAX_QUEUED_REQUESTS, |n| n as usize);
+    if QUEUED_REQUESTS.fetch_add(1, Ordering::SeqCst) >= max_queued {
+        QUEUED_REQUESTS.fetch_sub(1, Ordering::SeqCst);
+        return Err(CommandError::busy("Too many requests are already waiting for the backend"));
+    }
+    let _queued = QueuedRequest;
 
-    let data: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse memory: {}", e))?;
+    match tokio::time::timeout(REQUEST_QUEUE_TIMEOUT, slots.acquire_owned()).await {
+        Ok(Ok(permit)) => Ok(permit),
+        Ok(Err(e)) => Err(CommandError::internal("Request queue closed").with_details(e)),
+        Err(_) => Err(CommandError::busy("Timed out waiting for the backend to free up")),
+    }
+}
 
-    let notes = data
-        .get("notes")
-        .and_then(|n| n.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|n| {
-                    Some(MemoryNote {
-                        id: n.get("entry_id").and_then(|v| v.as_str())?.to_string(),
-                        content: n.get("content").and_then(|v| v.as_str())?.to_string(),
-                        category: n
-                            .get("category")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("note")
-                            .to_string(),
-                        tags: n
-                            .get("tags")
-                            .and_then(|v| v.as_array())
-                            .map(|arr| {
-                                arr.iter()
-                                    .filter_map(|t| t.as_str().map(|s| s.to_string()))
-                                    .collect()
-                            })
-                            .unwrap_or_default(),
-                        created_at: n
-                            .get("created_at")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string(),
-                    })
-                })
-                .collect()
-        })
-        .unwrap_or_default();
+/// The request semaphore, rebuilt when `backend.max_concurrent_requests` changes.
+///
+/// Requests holding or waiting on a replaced semaphore finish on it, so the new
+/// limit applies fully once they're done.
+fn request_slots() -> Result<Arc<tokio::sync::Semaphore>, CommandError> {
+    let permits = config_value("backend.max_concurrent_requests")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_MAX_CONCURRENT_REQUESTS, |n| n.max(1) as usize);
 
-    Ok(notes)
+    let mut slots = REQUEST_SLOTS.lock().map_err(CommandError::poisoned)?;
+    match slots.as_ref() {
+        Some((size, semaphore)) if *size == permits => Ok(semaphore.clone()),
+        _ => {
+            let semaphore = Arc::new(tokio::sync::Semaphore::new(permits));
+            *slots = Some((permits, semaphore.clone()));
+            Ok(semaphore)
+        }
+    }
 }
 
-/// Add a memory note
+/// Send a message to the agent, streaming `agent-chunk` events as the reply arrives.
+///
+/// At most `backend.max_concurrent_requests` run at once; the rest queue, up
+/// to `backend.max_queued_requests`, and beyond that fail with a `busy` error.
 #[tauri::command]
-pub async fn add_memory_note(
-    project_path: String,
-    content: String,
-    category: String,
-    tags: Vec<String>,
-) -> Result<MemoryNote, String> {
-    let memory_dir = PathBuf::from(&project_path).join(".roura");
-    let memory_path = memory_dir.join("memory.json");
+#[tracing::instrument(skip_all, fields(project = ?project_path))]
+pub async fn send_message(
+    app: AppHandle,
+    mut message: AgentMessage,
+    project_path: Option<String>,
+) -> Result<AgentResponse, CommandError> {
+    let _slot = acquire_request_slot().await?;
 
-    std::fs::create_dir_all(&memory_dir)
-        .map_err(|e| format!("Failed to create memory directory: {}", e))?;
+    let mut original = message.clone();
+    apply_effective_settings(&mut message, project_path.as_deref());
+    apply_memory_context(&mut message, project_path.as_deref());
+    let response = stream_message(&app, message, project_path.as_deref()).await?;
 
-    // Load existing memory
-    let mut data: serde_json::Value = if memory_path.exists() {
-        let content = std::fs::read_to_string(&memory_path).unwrap_or_default();
-        serde_json::from_str(&content).unwrap_or(serde_json::json!({"notes": [], "version": 2}))
+    if response.finished && !response.cancelled {
+        notify_completion(&app, &response);
+    }
+
+    // History is best effort; a write failure shouldn't lose the reply
+    if let Some(ref project_path) = project_path {
+        original.request_id = Some(response.request_id.clone());
+        if let Err(e) = conversation::append_exchange(project_path, original, response.clone()) {
+            tracing::error!(
+                project = %project_path,
+                error = %e,
+                "Failed to save conversation"
+            );
+        }
+    }
+
+    Ok(response)
+}
+
+/// Settings sent to the backend with each message, resolved per project
+const FORWARDED_SETTINGS: &[&str] = &["model", "temperature"];
+
+/// Fill in `FORWARDED_SETTINGS` the caller didn't set in the message context
+fn apply_effective_settings(message: &mut AgentMessage, project_path: Option<&str>) {
+    let context = message.context.get_or_insert_with(HashMap::new);
+
+    for key in FORWARDED_SETTINGS {
+        if context.contains_key(*key) {
+            continue;
+        }
+        let value = project_path
+            .and_then(|project| effective_config_value(project, key))
+            .or_else(|| config_value(key));
+        if let Some(value) = value {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            context.insert(key.to_string(), value);
+        }
+    }
+
+    if context.is_empty() {
+        message.context = None;
+    }
+}
+
+/// Add the project's memory to the context under `memory`, if the message asks for it
+fn apply_memory_context(message: &mut AgentMessage, project_path: Option<&str>) {
+    let (true, Some(project_path)) = (message.include_memory, project_path) else {
+        return;
+    };
+
+    // Unreadable memory shouldn't block the message itself
+    match memory_context(project_path) {
+        Ok(Some(memory)) => {
+            message
+                .context
+                .get_or_insert_with(HashMap::new)
+                .insert("memory".to_string(), memory);
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!(
+            project = project_path,
+            error = %e,
+            "Failed to load memory for context"
+        ),
+    }
+}
+
+/// Longest response excerpt shown in a completion notification
+const NOTIFICATION_SUMMARY_CHARS: usize = 80;
+
+/// Alert the user that a reply finished while the window wasn't focused.
+///
+/// Clicking the notification brings the main window forward. The window
+/// also requests attention, so the taskbar/dock entry flashes as well.
+fn notify_completion(app: &AppHandle, response: &AgentResponse) {
+    use tauri::Manager;
+
+    let enabled = config_value("notify_on_complete")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    if !enabled {
+        return;
+    }
+
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    if window.is_focused().unwrap_or(true) {
+        return;
+    }
+
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .summary("Roura Agent replied")
+        .body(&notification_summary(&response.content))
+        .auto_icon();
+    // XDG servers only report clicks for notifications that offer a default action
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.action("default", "Open");
+    // Dev builds aren't bundled, so macOS only delivers under the terminal's identity
+    #[cfg(target_os = "macos")]
+    let _ = notify_rust::set_application(if tauri::is_dev() {
+        "com.apple.Terminal"
     } else {
-        serde_json::json!({"notes": [], "version": 2})
+        &app.config().identifier
+    });
+    #[cfg(windows)]
+    if let Some(app_id) = installed_app_id(app) {
+        notification.app_id(&app_id);
+    }
+
+    let focus_target = window.clone();
+    // Waiting for the click blocks, so it gets a thread of its own
+    std::thread::spawn(move || {
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to show notification");
+                return;
+            }
+        };
+        let _ = handle.wait_for_response(|response: &notify_rust::NotificationResponse| {
+            if matches!(response, notify_rust::NotificationResponse::Default) {
+                let _ = focus_target.unminimize();
+                let _ = focus_target.show();
+                let _ = focus_target.set_focus();
+            }
+        });
+    });
+    let _ = window.request_user_attention(Some(tauri::UserAttentionType::Informational));
+}
+
+/// The AppUserModelID toasts are sent under, unless running from a cargo build
+/// directory where it isn't registered and Windows would drop the toast
+#[cfg(windows)]
+fn installed_app_id(app: &AppHandle) -> Option<String> {
+    let exe = tauri::utils::platform::current_exe().ok()?;
+    let dir = exe.parent()?;
+    let in_build_dir = dir.ends_with(Path::new("target").join("debug"))
+        || dir.ends_with(Path::new("target").join("release"));
+    (!in_build_dir).then(|| app.config().identifier.clone())
+}
+
+/// A reply squashed onto one line and cut to `NOTIFICATION_SUMMARY_CHARS`, with an ellipsis if cut
+fn notification_summary(content: &str) -> String {
+    let collapsed = content.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if collapsed.chars().count() <= NOTIFICATION_SUMMARY_CHARS {
+        return collapsed;
+    }
+
+    let mut summary: String = collapsed.chars().take(NOTIFICATION_SUMMARY_CHARS - 1).collect();
+    summary.push('…');
+    summary
+}
+
+/// Forward a message to the backend and stream its reply
+async fn stream_message(
+    app: &AppHandle,
+    mut message: AgentMessage,
+    project_path: Option<&str>,
+) -> Result<AgentResponse, CommandError> {
+    // A backend stopped for inactivity comes back transparently
+    crate::backend::resume_if_idle().await?;
+    let _activity = crate::backend::BackendActivity::begin();
+
+    let route = crate::backend::api_route()?;
+
+    let request_id = message
+        .request_id
+        .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+        .clone();
+
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    {
+        let mut active = ACTIVE_REQUESTS.lock().map_err(CommandError::poisoned)?;
+        if active.contains_key(&request_id) {
+            return Err(CommandError::invalid_input(format!(
+                "Request {} is already in flight",
+                request_id
+            )));
+        }
+        active.insert(
+            request_id.clone(),
+            ActiveRequest {
+                cancel: cancel_tx,
+                started_at: chrono::Utc::now().to_rfc3339(),
+                project: project_path.map(str::to_string),
+            },
+        );
+    }
+    let _guard = ActiveRequestGuard(request_id.clone());
+    let mut cancel = CancelSignal(Some(cancel_rx));
+
+    let response = AgentResponse {
+        content: String::new(),
+        tool_calls: None,
+        finished: false,
+        request_id: request_id.clone(),
+        cancelled: false,
     };
 
-    // Create new note
-    let note_id = uuid::Uuid::new_v4().to_string();
-    let created_at = chrono::Utc::now().to_rfc3339();
+    // The backend can't read arbitrary paths, so send it the file contents instead.
+    // Large uploads take a while, so the request is cancellable from here on.
+    if let Some(paths) = message.attachments.take() {
+        tokio::select! {
+            ids = upload_attachments(route, &paths) => message.attachments = Some(ids?),
+            _ = cancel.cancelled() => return Ok(cancel_response(app, route, response).await),
+        }
+    }
 
-    let note = serde_json::json!({
-        "entry_id": note_id,
-        "content": content,
-        "category": category,
-        "tags": tags,
-        "source": "user",
-        "relevance": 1.0,
-        "created_at": created_at,
+    match route {
+        Route::Http(port) => stream_http(app, port, &message, response, cancel).await,
+        Route::Stdio => stream_stdio(app, &message, response, cancel).await,
+    }
+}
+
+/// Stream a reply from `POST /chat` as server-sent events
+async fn stream_http(
+    app: &AppHandle,
+    port: u16,
+    message: &AgentMessage,
+    mut response: AgentResponse,
+    mut cancel: CancelSignal,
+) -> Result<AgentResponse, CommandError> {
+    let route = Route::Http(port);
+    let url = format!("http://127.0.0.1:{}/chat", port);
+    let client = reqwest::Client::new();
+    let send = crate::backend::send_with_retry(|| {
+        client
+            .post(&url)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(message)
     });
 
-    // Add to notes array
-    if let Some(notes) = data.get_mut("notes").and_then(|n| n.as_array_mut()) {
-        notes.push(note);
+    let mut stream = tokio::select! {
+        result = send => result
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| {
+                CommandError::backend("Failed to send message to backend").with_details(e)
+            })?,
+        _ = cancel.cancelled() => {
+            return Ok(cancel_response(app, route, response).await);
+        }
+    };
+
+    let mut parser = ChatStreamParser::default();
+
+    while !response.finished {
+        let chunk = tokio::select! {
+            chunk = stream.chunk() => chunk
+                .map_err(|e| {
+                    CommandError::backend("Failed to read response stream").with_details(e)
+                })?,
+            _ = cancel.cancelled() => {
+                // Dropping the stream aborts the HTTP request
+                drop(stream);
+                return Ok(cancel_response(app, route, response).await);
+            }
+        };
+
+        let Some(bytes) = chunk else {
+            break;
+        };
+
+        for event in parser.push(&bytes) {
+            apply_chat_event(app, &mut response, event);
+        }
     }
 
-    // Save
-    let content_str = serde_json::to_string_pretty(&data)
-        .map_err(|e| format!("Failed to serialize memory: {}", e))?;
+    if let Some(event) = parser.finish() {
+        apply_chat_event(app, &mut response, event);
+    }
 
-    std::fs::write(&memory_path, content_str)
-        .map_err(|e| format!("Failed to write memory: {}", e))?;
+    close_response(app, &mut response);
+    Ok(response)
+}
 
-    Ok(MemoryNote {
-        id: note_id,
-        content,
-        category,
-        tags,
-        created_at,
-    })
+/// Stream a reply over stdio: the `chat` call returns once the backend is done
+/// with the request, and its events arrive as notifications carrying the request id
+async fn stream_stdio(
+    app: &AppHandle,
+    message: &AgentMessage,
+    mut response: AgentResponse,
+    mut cancel: CancelSignal,
+) -> Result<AgentResponse, CommandError> {
+    // Subscribe first so no early event is missed
+    let mut events = crate::rpc::subscribe(&response.request_id)?;
+    let call = crate::rpc::call("chat", serde_json::to_value(message)?, None);
+    tokio::pin!(call);
+
+    loop {
+        tokio::select! {
+            result = &mut call => {
+                result.map_err(|e| {
+                    CommandError::backend("Failed to send message to backend").with_details(e)
+                })?;
+                break;
+            }
+            Some(event) = events.recv() => apply_rpc_event(app, &mut response, event),
+            _ = cancel.cancelled() => {
+                return Ok(cancel_response(app, Route::Stdio, response).await);
+            }
+        }
+    }
+
+    // Notifications written just before the reply may still be queued
+    while let Some(event) = events.try_recv() {
+        apply_rpc_event(app, &mut response, event);
+    }
+
+    close_response(app, &mut response);
+    Ok(response)
 }
 
-// Add chrono and uuid to Cargo.toml
+fn apply_rpc_event(app: &AppHandle, response: &mut AgentResponse, event: serde_json::Value) {
+    match serde_json::from_value::<ChatEvent>(event) {
+        Ok(event) => apply_chat_event(app, response, event),
+        Err(e) => tracing::warn!(error = %e, "Ignoring malformed chat event"),
+    }
+}
+
+/// Finish a response whose stream closed without an explicit final event
+fn close_response(app: &AppHandle, response: &mut AgentResponse) {
+    if !response.finished {
+        response.finished = true;
+        let mut events = StreamEvents::new();
+        settle_tool_calls(response, &mut events);
+        emit_all(app, events);
+        emit_final_chunk(app, response);
+    }
+}
+
+/// Default cap on the combined size of one message's attachments
+const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Validate and upload attachment files, returning the backend's attachment ids
+async fn upload_attachments(route: Route, paths: &[String]) -> Result<Vec<String>, CommandError> {
+    let max_total = config_value("attachments.max_total_bytes")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_MAX_ATTACHMENT_BYTES);
+
+    // Check every file before uploading any of them
+    let mut total: u64 = 0;
+    for path in paths {
+        let metadata = std::fs::metadata(path)
+            .map_err(|_| CommandError::not_found(format!("Attachment does not exist: {}", path)))?;
+        if !metadata.is_file() {
+            return Err(CommandError::invalid_input(format!(
+                "Attachment is not a file: {}",
+                path
+            )));
+        }
+
+        total += metadata.len();
+        if total > max_total {
+            return Err(CommandError::invalid_input(format!(
+                "Attachment {} exceeds the {} byte attachment limit",
+                path, max_total
+            )));
+        }
+    }
+
+    let mut ids = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|e| {
+                CommandError::io(format!("Failed to read attachment {}", path)).with_details(e)
+            })?;
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let body = serde_json::json!({
+            "name": name,
+            "data": STANDARD.encode(&data),
+        });
+        let uploaded: serde_json::Value = match route {
+            Route::Http(port) => reqwest::Client::new()
+                .post(format!("http://127.0.0.1:{}/upload", port))
+                .json(&body)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| {
+                    CommandError::backend(format!("Failed to upload attachment {}", path))
+                        .with_details(e)
+                })?
+                .json()
+                .await
+                .map_err(|e| {
+                    CommandError::parse(format!("Failed to parse upload response for {}", path))
+                        .with_details(e)
+                })?,
+            Route::Stdio => crate::rpc::call("attachments.upload", body, None)
+                .await
+                .map_err(|e| {
+                    CommandError::backend(format!("Failed to upload attachment {}", path))
+                        .with_details(e)
+                })?,
+        };
+
+        let id = uploaded.get("id").and_then(|v| v.as_str()).ok_or_else(|| {
+            CommandError::backend(format!("Backend returned no id for attachment {}", path))
+        })?;
+        ids.push(id.to_string());
+    }
+
+    Ok(ids)
+}
+
+/// Cancel an in-flight `send_message` call
+#[tauri::command]
+pub async fn cancel_message(request_id: String) -> Result<(), CommandError> {
+    let request = ACTIVE_REQUESTS
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .remove(&request_id)
+        .ok_or_else(|| {
+            CommandError::not_found(format!("No in-flight request with id {}", request_id))
+        })?;
+
+    // The request may have finished in the meantime, which is fine
+    let _ = request.cancel.send(());
+
+    Ok(())
+}
+
+/// List in-flight `send_message` calls, oldest first
+#[tauri::command]
+pub async fn list_active_requests() -> Result<Vec<RequestInfo>, CommandError> {
+    let active = ACTIVE_REQUESTS.lock().map_err(CommandError::poisoned)?;
+    let mut requests: Vec<RequestInfo> = active
+        .iter()
+        .map(|(id, request)| RequestInfo {
+            request_id: id.clone(),
+            started_at: request.started_at.clone(),
+            project: request.project.clone(),
+        })
+        .collect();
+    requests.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+
+    Ok(requests)
+}
+
+/// Cancel every in-flight `send_message` call, returning how many there were
+#[tauri::command]
+pub async fn cancel_all_requests() -> Result<usize, CommandError> {
+    let requests = std::mem::take(&mut *ACTIVE_REQUESTS.lock().map_err(CommandError::poisoned)?);
+
+    let count = requests.len();
+    for request in requests.into_values() {
+        // Any that just finished ignore the signal
+        let _ = request.cancel.send(());
+    }
+
+    Ok(count)
+}
+
+/// Tell the backend to stop generating and finish the response as cancelled
+async fn cancel_response(
+    app: &AppHandle,
+    route: Route,
+    mut response: AgentResponse,
+) -> AgentResponse {
+    match route {
+        Route::Http(port) => {
+            let url = format!(
+                "http://127.0.0.1:{}/chat/{}/cancel",
+                port, response.request_id
+            );
+            let _ = reqwest::Client::new()
+                .post(&url)
+                .timeout(std::time::Duration::from_secs(2))
+                .send()
+                .await;
+        }
+        Route::Stdio => {
+            let _ = crate::rpc::call(
+                "chat.cancel",
+                serde_json::json!({ "request_id": response.request_id }),
+                Some(Duration::from_secs(2)),
+            )
+            .await;
+        }
+    }
+
+    response.finished = true;
+    response.cancelled = true;
+    let mut events = StreamEvents::new();
+    settle_tool_calls(&mut response, &mut events);
+    emit_all(app, events);
+    emit_final_chunk(app, &response);
+
+    response
+}
+
+/// Emit the closing `agent-chunk` for a response that ended without a final event
+fn emit_final_chunk(app: &AppHandle, response: &AgentResponse) {
+    let _ = app.emit(
+        "agent-chunk",
+        AgentChunk {
+            delta: String::new(),
+            content: response.content.clone(),
+            tool_call: None,
+            finished: true,
+            request_id: response.request_id.clone(),
+            cancelled: response.cancelled,
+        },
+    );
+}
+
+/// One event from the backend's chat stream
+#[derive(Debug, Default, Deserialize)]
+struct ChatEvent {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_call: Option<ToolCall>,
+    #[serde(default)]
+    tool_progress: Option<ToolProgress>,
+    #[serde(default)]
+    finished: bool,
+}
+
+/// Frontend events produced by a stream update, as (name, payload) in emit order
+type StreamEvents = Vec<(&'static str, serde_json::Value)>;
+
+fn emit_all(app: &AppHandle, events: StreamEvents) {
+    for (name, payload) in events {
+        let _ = app.emit(name, payload);
+    }
+}
+
+/// Fold a stream event into the response and forward it to the frontend
+fn apply_chat_event(app: &AppHandle, response: &mut AgentResponse, event: ChatEvent) {
+    emit_all(app, fold_chat_event(response, event));
+}
+
+/// Fold a stream event into the response, returning the events it produces
+fn fold_chat_event(response: &mut AgentResponse, mut event: ChatEvent) -> StreamEvents {
+    let mut events = StreamEvents::new();
+    if response.finished {
+        return events;
+    }
+
+    response.content.push_str(&event.content);
+    if let Some(ref mut call) = event.tool_call {
+        if call.id.is_empty() {
+            call.id = uuid::Uuid::new_v4().to_string();
+        }
+        track_tool_call(response, call.clone(), &mut events);
+    }
+    if let Some(progress) = event.tool_progress {
+        track_tool_progress(response, progress, &mut events);
+    }
+    response.finished = event.finished;
+    if response.finished {
+        settle_tool_calls(response, &mut events);
+    }
+
+    let chunk = AgentChunk {
+        delta: event.content,
+        content: response.content.clone(),
+        tool_call: event.tool_call,
+        finished: event.finished,
+        request_id: response.request_id.clone(),
+        cancelled: false,
+    };
+    events.push(("agent-chunk", serde_json::json!(chunk)));
+    events
+}
+
+/// Record a new or updated tool call, adding `tool-call-start` and `tool-call-end`
+fn track_tool_call(response: &mut AgentResponse, call: ToolCall, events: &mut StreamEvents) {
+    let calls = response.tool_calls.get_or_insert_with(Vec::new);
+    let is_new = match calls.iter_mut().find(|c| c.id == call.id) {
+        Some(existing) => {
+            // Updates may omit fields the start event already carried
+            let arguments = std::mem::take(&mut existing.arguments);
+            let result = existing.result.take();
+            *existing = call.clone();
+            if existing.arguments.is_empty() {
+                existing.arguments = arguments;
+            }
+            if existing.result.is_none() {
+                existing.result = result;
+            }
+            false
+        }
+        None => {
+            calls.push(call.clone());
+            true
+        }
+    };
+
+    let payload = |call: &ToolCall| {
+        serde_json::json!({ "request_id": response.request_id, "tool_call": call })
+    };
+    if is_new {
+        events.push(("tool-call-start", payload(&call)));
+    }
+    if call.status.is_terminal() {
+        if let Some(call) = response.tool_calls.iter().flatten().find(|c| c.id == call.id) {
+            events.push(("tool-call-end", payload(call)));
+        }
+    }
+}
+
+/// Append partial output to a running tool call, adding `tool-call-progress`
+fn track_tool_progress(
+    response: &mut AgentResponse,
+    progress: ToolProgress,
+    events: &mut StreamEvents,
+) {
+    let Some(call) = response
+        .tool_calls
+        .iter_mut()
+        .flatten()
+        .find(|c| c.id == progress.id)
+    else {
+        return;
+    };
+
+    call.status = ToolCallStatus::Running;
+    call.result.get_or_insert_with(String::new).push_str(&progress.output);
+
+    events.push((
+        "tool-call-progress",
+        serde_json::json!({
+            "request_id": response.request_id,
+            "id": progress.id,
+            "output": progress.output,
+        }),
+    ));
+}
+
+/// Mark tool calls the stream never finished as errors, so the response only has terminal states
+fn settle_tool_calls(response: &mut AgentResponse, events: &mut StreamEvents) {
+    for call in response.tool_calls.iter_mut().flatten() {
+        if call.status.is_terminal() {
+            continue;
+        }
+
+        call.status = ToolCallStatus::Error;
+        events.push((
+            "tool-call-end",
+            serde_json::json!({ "request_id": response.request_id, "tool_call": call }),
+        ));
+    }
+}
+
+/// Splits a server-sent-events (or newline-delimited JSON) body into events
+#[derive(Debug, Default)]
+struct ChatStreamParser {
+    buffer: Vec<u8>,
+}
+
+impl ChatStreamParser {
+    /// Feed raw bytes, returning every complete event they finish
+    fn push(&mut self, bytes: &[u8]) -> Vec<ChatEvent> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            if let Some(event) = parse_stream_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Parse whatever is left once the body ends without a trailing newline
+    fn finish(&mut self) -> Option<ChatEvent> {
+        let rest = std::mem::take(&mut self.buffer);
+        parse_stream_line(&String::from_utf8_lossy(&rest))
+    }
+}
+
+/// Parse a single `data: {...}` SSE line or a bare JSON line
+fn parse_stream_line(line: &str) -> Option<ChatEvent> {
+    let line = line.trim();
+    let payload = match line.strip_prefix("data:") {
+        Some(data) => data.trim(),
+        None if line.starts_with('{') => line,
+        // Blank separators, comments, and `event:`/`id:` fields
+        None => return None,
+    };
+
+    if payload == "[DONE]" {
+        return Some(ChatEvent {
+            finished: true,
+            ..Default::default()
+        });
+    }
+
+    serde_json::from_str(payload).ok()
+}
+
+/// Layout of config.json written by this build, stored under `config_version`
+const CONFIG_VERSION: u64 = 1;
+
+/// A config without `config_version` predates versioning
+type ConfigMigration = fn(&mut HashMap<String, serde_json::Value>) -> Result<(), CommandError>;
+
+/// Ordered upgrades; entry `n` takes a config from version `n` to `n + 1`.
+///
+/// Each must be idempotent, since a failed write means it runs again next launch.
+const CONFIG_MIGRATIONS: &[(&str, ConfigMigration)] =
+    &[("nest flat dotted keys", nest_flat_config_keys)];
+
+/// Set once this launch's migration check has succeeded
+static CONFIG_MIGRATED: AtomicBool = AtomicBool::new(false);
+
+/// Bring config.json up to `CONFIG_VERSION` the first time config is touched
+fn migrate_config_once() -> Result<(), CommandError> {
+    if CONFIG_MIGRATED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    migrate_config()?;
+    CONFIG_MIGRATED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Apply pending migrations and write the result back, emitting `config-migrated`
+fn migrate_config() -> Result<(), CommandError> {
+    if !config_root()?.join("config.json").exists() {
+        return Ok(());
+    }
+
+    let mut config = load_config()?;
+    let from = config
+        .get("config_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    if from > CONFIG_VERSION {
+        // Written by a newer build; leave it alone rather than guess
+        tracing::warn!(version = from, "Config is newer than this build understands");
+        return Ok(());
+    }
+    if from == CONFIG_VERSION {
+        return Ok(());
+    }
+
+    for (version, (name, migrate)) in CONFIG_MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migrate(&mut config).map_err(|e| {
+            CommandError::internal(format!("Config migration failed: {}", name)).with_details(e)
+        })?;
+        tracing::info!(from = version, to = version + 1, migration = name, "Migrated config");
+    }
+    config.insert("config_version".to_string(), serde_json::json!(CONFIG_VERSION));
+    save_config(&config)?;
+
+    crate::backend::emit_event(
+        "config-migrated",
+        serde_json::json!({ "from": from, "to": CONFIG_VERSION }),
+    );
+    Ok(())
+}
+
+/// v0 to v1: move literal top-level keys like `"ui.theme"` into nested objects.
+///
+/// The flat value wins over a nested one, matching `lookup_config`. A key whose
+/// parent holds a non-object stays flat.
+fn nest_flat_config_keys(
+    config: &mut HashMap<String, serde_json::Value>,
+) -> Result<(), CommandError> {
+    let mut flat: Vec<String> = config.keys().filter(|k| k.contains('.')).cloned().collect();
+    flat.sort();
+
+    for key in flat {
+        let Some(value) = config.remove(&key) else {
+            continue;
+        };
+        if insert_config(config, &key, value.clone()).is_err() {
+            config.insert(key, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Get configuration value; dotted keys read nested objects
+#[tauri::command]
+pub async fn get_config(key: String) -> Result<Option<serde_json::Value>, CommandError> {
+    migrate_config_once()?;
+
+    // Load from config file
+    let config = load_config()?;
+
+    Ok(lookup_config(&config, &key).cloned())
+}
+
+/// Set configuration value; dotted keys write nested objects
+#[tauri::command]
+pub async fn set_config(key: String, value: serde_json::Value) -> Result<(), CommandError> {
+    migrate_config_once()?;
+
+    // Load existing config; a corrupt file is reported, never silently replaced
+    let mut config = load_config()?;
+
+    // Reject unknown keys and mistyped values before they reach disk
+    let strict = match key.as_str() {
+        "config.strict" => value.as_bool().unwrap_or(true),
+        _ => strict_config(&config),
+    };
+    config_schema::validate(&key, &value, strict).map_err(CommandError::invalid_input)?;
+
+    // Update value
+    insert_config(&mut config, &key, value)?;
+
+    // Save
+    save_config(&config)
+}
+
+/// A named bundle of `ui.*` values, from `list_themes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemePreset {
+    pub name: String,
+    /// Shipped with the app rather than defined under `themes.<name>`
+    pub builtin: bool,
+    /// Dotted key to value
+    pub values: BTreeMap<String, serde_json::Value>,
+}
+
+/// Presets available without any config; `themes.<name>` entries shadow them
+fn builtin_themes() -> Vec<ThemePreset> {
+    let preset = |name: &str, theme: &str, high_contrast: bool, font_size: u32| ThemePreset {
+        name: name.to_string(),
+        builtin: true,
+        values: BTreeMap::from([
+            ("ui.theme".to_string(), serde_json::json!(theme)),
+            ("ui.high_contrast".to_string(), serde_json::json!(high_contrast)),
+            ("ui.font_size".to_string(), serde_json::json!(font_size)),
+        ]),
+    };
+
+    vec![
+        preset("light", "light", false, 14),
+        preset("dark", "dark", false, 14),
+        preset("high-contrast", "dark", true, 16),
+    ]
+}
+
+/// Built-in presets followed by user ones, a user preset replacing a built-in of the same name
+fn load_themes(config: &HashMap<String, serde_json::Value>) -> Vec<ThemePreset> {
+    let mut themes = builtin_themes();
+
+    let user = lookup_config(config, "themes").and_then(|t| t.as_object());
+    for (name, values) in user.into_iter().flatten() {
+        // Either nested (`{"ui": {"theme": ...}}`) or dotted (`{"ui.theme": ...}`)
+        let values = match values.as_object() {
+            Some(map) => flatten_config(&map.clone().into_iter().collect()),
+            None => BTreeMap::new(),
+        };
+        themes.retain(|t| &t.name != name);
+        themes.push(ThemePreset {
+            name: name.clone(),
+            builtin: false,
+            values,
+        });
+    }
+
+    themes
+}
+
+/// List the theme presets `apply_theme` accepts
+#[tauri::command]
+pub async fn list_themes() -> Result<Vec<ThemePreset>, CommandError> {
+    Ok(load_themes(&load_config()?))
+}
+
+/// Apply a theme preset's values in a single config write.
+///
+/// Every value is validated first, so a bad preset changes nothing. Emits one
+/// `config-changed` listing all keys the preset sets.
+#[tauri::command]
+pub async fn apply_theme(app: AppHandle, name: String) -> Result<(), CommandError> {
+    let mut config = load_config()?;
+    let theme = load_themes(&config)
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| CommandError::not_found(format!("Unknown theme: {}", name)))?;
+
+    if theme.values.is_empty() {
+        return Err(CommandError::invalid_input(format!("Theme {} sets no values", name)));
+    }
+    for (key, value) in &theme.values {
+        // A preset is for looks only; anything else belongs in set_config
+        if !key.starts_with("ui.") {
+            return Err(CommandError::invalid_input(format!(
+                "Theme {} sets {}, but themes may only set ui.* keys",
+                name, key
+            )));
+        }
+        config_schema::validate(key, value, true).map_err(CommandError::invalid_input)?;
+    }
+
+    for (key, value) in &theme.values {
+        insert_config(&mut config, key, value.clone())?;
+    }
+    save_config(&config)?;
+
+    crate::watcher::mark_announced();
+    let keys: Vec<&String> = theme.values.keys().collect();
+    let _ = app.emit("config-changed", serde_json::json!({ "keys": keys }));
+
+    Ok(())
+}
+
+/// Remove a configuration value, returning whether it was set
+#[tauri::command]
+pub async fn remove_config(key: String) -> Result<bool, CommandError> {
+    let mut config = load_config()?;
+
+    if remove_config_key(&mut config, &key).is_none() {
+        return Ok(false);
+    }
+
+    save_config(&config)?;
+
+    Ok(true)
+}
+
+/// Check the stored config against the schema, returning one warning per problem
+#[tauri::command]
+pub async fn validate_config() -> Result<Vec<String>, CommandError> {
+    let config = load_config()?;
+
+    Ok(config_schema::validate_all(&config, strict_config(&config)))
+}
+
+/// List all top-level configuration keys
+#[tauri::command]
+pub async fn list_config_keys() -> Result<Vec<String>, CommandError> {
+    let mut keys: Vec<String> = load_config()?.into_keys().collect();
+    keys.sort();
+
+    Ok(keys)
+}
+
+// Project config lives in `<project>/.roura/config.json` and shadows the global
+// config key by key: a dotted key set in the project file wins, anything it
+// doesn't set falls through to the global value. There is no merging inside a
+// value, so a project `ui` object hides every global `ui.*` key it doesn't repeat.
+
+fn project_config_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path).join(".roura").join("config.json")
+}
+
+/// Load a project's config map, or an empty map if it has none
+fn load_project_config(
+    project_path: &str,
+) -> Result<HashMap<String, serde_json::Value>, CommandError> {
+    let config_path = project_config_path(project_path);
+
+    if !config_path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let content = std::fs::read_to_string(&config_path)
+        .map_err(|e| CommandError::io("Failed to read project config").with_details(e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse project config").with_details(e))
+}
+
+/// Project value for `key`, treating unreadable project config as unset
+fn effective_config_value(project_path: &str, key: &str) -> Option<serde_json::Value> {
+    load_project_config(project_path)
+        .ok()
+        .and_then(|config| lookup_config(&config, key).cloned())
+}
+
+/// Get a config value for a project, falling back to the global config
+#[tauri::command]
+pub async fn get_effective_config(
+    project_path: String,
+    key: String,
+) -> Result<Option<serde_json::Value>, CommandError> {
+    if let Some(value) = lookup_config(&load_project_config(&project_path)?, &key) {
+        return Ok(Some(value.clone()));
+    }
+
+    Ok(lookup_config(&load_config()?, &key).cloned())
+}
+
+/// Set a config value for one project only; dotted keys write nested objects
+#[tauri::command]
+pub async fn set_project_config(
+    project_path: String,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), CommandError> {
+    if !Path::new(&project_path).is_dir() {
+        return Err(CommandError::not_found(format!(
+            "Project path does not exist: {}",
+            project_path
+        )));
+    }
+
+    // Strictness is a global setting
+    let strict = strict_config(&load_config()?);
+    config_schema::validate(&key, &value, strict).map_err(CommandError::invalid_input)?;
+
+    let mut config = load_project_config(&project_path)?;
+    insert_config(&mut config, &key, value)?;
+
+    let config_path = project_config_path(&project_path);
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| CommandError::io("Failed to create .roura directory").with_details(e))?;
+    }
+
+    write_json_atomic(&config_path, &config)
+}
+
+/// Unpinned projects kept in the recent list
+const MAX_RECENT_PROJECTS: usize = 10;
+
+fn projects_path() -> Result<PathBuf, CommandError> {
+    Ok(config_root()?.join("recent_projects.json"))
+}
+
+/// Read the recent projects list, empty if it doesn't exist yet
+pub(crate) fn load_projects() -> Result<Vec<Project>, CommandError> {
+    let projects_path = projects_path()?;
+
+    if !projects_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&projects_path)
+        .map_err(|e| CommandError::io("Failed to read projects").with_details(e))?;
+
+    let projects: Vec<Project> = serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse projects").with_details(e))?;
+
+    // Lists saved before paths were canonicalized can hold duplicates
+    let mut projects = dedupe_projects(projects);
+    for project in projects.iter_mut() {
+        project.exists = Path::new(&project.path).exists();
+    }
+
+    Ok(projects)
+}
+
+/// Canonicalize every path and collapse entries for the same project.
+///
+/// The first entry (the most recently opened) is kept, pinned if any duplicate was.
+fn dedupe_projects(projects: Vec<Project>) -> Vec<Project> {
+    let mut unique: Vec<Project> = Vec::with_capacity(projects.len());
+
+    for mut project in projects {
+        project.path = canonical_project_path(&project.path);
+        match unique
+            .iter_mut()
+            .find(|p| same_project_path(&p.path, &project.path))
+        {
+            Some(existing) => existing.pinned |= project.pinned,
+            None => unique.push(project),
+        }
+    }
+
+    unique
+}
+
+/// Canonical form of a project path, used for storing and comparing entries.
+///
+/// Resolves symlinks when the path exists; otherwise makes it absolute and
+/// drops `.`, `..` and trailing separators lexically.
+pub(crate) fn canonical_project_path(path: &str) -> String {
+    let canonical = match std::fs::canonicalize(path) {
+        Ok(canonical) => canonical,
+        Err(_) => {
+            let mut normalized = PathBuf::new();
+            let absolute = std::env::current_dir().unwrap_or_default().join(path);
+            for component in absolute.components() {
+                match component {
+                    std::path::Component::CurDir => {}
+                    std::path::Component::ParentDir => {
+                        normalized.pop();
+                    }
+                    other => normalized.push(other),
+                }
+            }
+            normalized
+        }
+    };
+
+    let canonical = canonical.to_string_lossy().into_owned();
+    // canonicalize returns verbatim `\\?\C:\...` paths on Windows
+    match canonical.strip_prefix(r"\\?\") {
+        Some(stripped) if !stripped.starts_with("UNC") => stripped.to_string(),
+        _ => canonical,
+    }
+}
+
+/// Reject a canonical path outside `projects.allowed_roots`, when that's set.
+///
+/// Roots are canonicalized too and compared by whole components, so neither
+/// `..` nor a sibling sharing a prefix (`/srv/app2` for `/srv/app`) gets through.
+/// An empty list allows nothing.
+pub(crate) fn ensure_allowed_project(path: &str) -> Result<(), CommandError> {
+    let Some(roots) = config_value("projects.allowed_roots") else {
+        return Ok(());
+    };
+    let roots: Vec<&str> = roots
+        .as_array()
+        .map(|roots| roots.iter().filter_map(|r| r.as_str()).collect())
+        .unwrap_or_default();
+
+    let fold = |p: &str| {
+        if cfg!(any(target_os = "macos", windows)) {
+            p.to_lowercase()
+        } else {
+            p.to_string()
+        }
+    };
+    let folded = fold(path);
+    let allowed = roots.iter().any(|root| {
+        if !Path::new(root).is_absolute() {
+            tracing::warn!(root = %root, "Ignoring relative entry in projects.allowed_roots");
+            return false;
+        }
+        Path::new(&folded).starts_with(fold(&canonical_project_path(root)))
+    });
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(CommandError::forbidden(format!(
+            "Path is outside the allowed roots: {}",
+            path
+        ))
+        .with_details("See projects.allowed_roots"))
+    }
+}
+
+/// Whether two canonical paths name the same project.
+///
+/// macOS and Windows filesystems are case-insensitive by default.
+fn same_project_path(a: &str, b: &str) -> bool {
+    if cfg!(any(target_os = "macos", windows)) {
+        a.to_lowercase() == b.to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// Keep every pinned project and only the most recent unpinned ones
+fn trim_recent_projects(projects: &mut Vec<Project>) {
+    let mut unpinned = 0;
+    projects.retain(|p| {
+        if p.pinned {
+            return true;
+        }
+        unpinned += 1;
+        unpinned <= MAX_RECENT_PROJECTS
+    });
+}
+
+fn save_projects(projects: &[Project]) -> Result<(), CommandError> {
+    let config_dir = config_root()?;
+
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| CommandError::io("Failed to create config directory").with_details(e))?;
+
+    // `exists` is only true of the disk right now, so it stays out of the file
+    let mut saved = serde_json::to_value(projects)?;
+    for project in saved.as_array_mut().into_iter().flatten() {
+        if let Some(project) = project.as_object_mut() {
+            project.remove("exists");
+        }
+    }
+
+    write_json_atomic(&projects_path()?, &saved)
+}
+
+/// List recent projects, pinned first.
+///
+/// Each project is marked with whether its path still exists. With `prune`,
+/// missing unpinned projects are also removed from the saved list.
+#[tauri::command]
+pub async fn list_projects(prune: Option<bool>) -> Result<Vec<Project>, CommandError> {
+    let mut projects = load_projects()?;
+
+    if prune.unwrap_or(false) {
+        let before = projects.len();
+        // Pinned projects survive so a remounted drive brings them back
+        projects.retain(|p| p.exists || p.pinned);
+        if projects.len() != before {
+            save_projects(&projects)?;
+        }
+    }
+
+    // Stable, so each group stays most recent first
+    projects.sort_by_key(|p| !p.pinned);
+
+    Ok(projects)
+}
+
+/// Project the UI is working in, set by `open_project` and `set_active_project`
+static ACTIVE_PROJECT: Mutex<Option<String>> = Mutex::new(None);
+
+/// The active project's path, if one is set
+pub(crate) fn active_project() -> Option<String> {
+    ACTIVE_PROJECT.lock().ok()?.clone()
+}
+
+/// The active project's path, or an error naming what's missing
+fn require_active_project() -> Result<String, CommandError> {
+    active_project().ok_or_else(|| CommandError::invalid_input("No project is active"))
+}
+
+/// Replace the active project, emitting `project-changed` if it differs
+fn change_active_project(app: &AppHandle, path: Option<String>) -> Result<(), CommandError> {
+    {
+        let mut active = ACTIVE_PROJECT.lock().map_err(CommandError::poisoned)?;
+        if *active == path {
+            return Ok(());
+        }
+        *active = path.clone();
+    }
+
+    let _ = app.emit("project-changed", serde_json::json!({ "path": path }));
+    Ok(())
+}
+
+/// Clear the active project if `matches` says it's no longer listed
+fn forget_active_project(
+    app: &AppHandle,
+    matches: impl Fn(&str) -> bool,
+) -> Result<(), CommandError> {
+    match active_project() {
+        Some(active) if matches(&active) => change_active_project(app, None),
+        _ => Ok(()),
+    }
+}
+
+/// Make `path` the active project, or clear it with `None`
+#[tauri::command]
+pub async fn set_active_project(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<Option<String>, CommandError> {
+    let path = match path {
+        Some(path) => {
+            let path = canonical_project_path(&path);
+            ensure_allowed_project(&path)?;
+            if !Path::new(&path).exists() {
+                return Err(CommandError::not_found(format!(
+                    "Project path does not exist: {}",
+                    path
+                )));
+            }
+            Some(path)
+        }
+        None => None,
+    };
+
+    change_active_project(&app, path.clone())?;
+    Ok(path)
+}
+
+/// The active project's path, if one is set
+#[tauri::command]
+pub async fn get_active_project() -> Result<Option<String>, CommandError> {
+    Ok(active_project())
+}
+
+/// Open a project and make it the active one
+#[tauri::command]
+pub async fn open_project(app: AppHandle, path: String) -> Result<Project, CommandError> {
+    let path = canonical_project_path(&path);
+    ensure_allowed_project(&path)?;
+    let project_path = PathBuf::from(&path);
+
+    if !project_path.exists() {
+        return Err(CommandError::not_found(format!(
+            "Project path does not exist: {}",
+            path
+        )));
+    }
+
+    let name = project_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    // A corrupt list is replaced rather than blocking the open
+    let mut projects = load_projects().unwrap_or_default();
+
+    // Remove existing entry for same path, keeping its pin
+    let pinned = projects
+        .iter()
+        .any(|p| same_project_path(&p.path, &path) && p.pinned);
+    projects.retain(|p| !same_project_path(&p.path, &path));
+
+    let project = Project {
+        name,
+        path: path.clone(),
+        last_opened: Some(chrono::Utc::now().to_rfc3339()),
+        pinned,
+        exists: true,
+        git: probe_git(&project_path).await,
+    };
+
+    // Add to front
+    projects.insert(0, project.clone());
+    trim_recent_projects(&mut projects);
+
+    save_projects(&projects)?;
+    change_active_project(&app, Some(path))?;
+
+    Ok(project)
+}
+
+/// Re-query a project's git state, updating its recent list entry if present
+#[tauri::command]
+pub async fn refresh_project_git(path: String) -> Result<Option<GitInfo>, CommandError> {
+    let path = canonical_project_path(&path);
+    let project_path = PathBuf::from(&path);
+
+    if !project_path.exists() {
+        return Err(CommandError::not_found(format!(
+            "Project path does not exist: {}",
+            path
+        )));
+    }
+    ensure_allowed_project(&path)?;
+
+    let git = probe_git(&project_path).await;
+
+    let mut projects = load_projects()?;
+    if let Some(project) = projects
+        .iter_mut()
+        .find(|p| same_project_path(&p.path, &path))
+    {
+        project.git = git.clone();
+        save_projects(&projects)?;
+    }
+
+    Ok(git)
+}
+
+/// Longest a single git invocation may take
+const GIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Best-effort git probe; `None` if the path isn't a repo or git misbehaves
+async fn probe_git(path: &Path) -> Option<GitInfo> {
+    let branch = run_git(path, &["rev-parse", "--abbrev-ref", "HEAD"]).await;
+    let commit = run_git(path, &["rev-parse", "--short", "HEAD"]).await;
+    let status = run_git(path, &["status", "--porcelain"]).await?;
+
+    // An unborn branch has no HEAD to resolve, but is still a repo
+    let branch = match branch {
+        Some(branch) => branch.trim().to_string(),
+        None => run_git(path, &["symbolic-ref", "--short", "HEAD"])
+            .await?
+            .trim()
+            .to_string(),
+    };
+
+    Some(GitInfo {
+        branch,
+        commit: commit.map(|c| c.trim().to_string()),
+        dirty: !status.trim().is_empty(),
+    })
+}
+
+/// Run git in `path`, returning stdout on success
+async fn run_git(path: &Path, args: &[&str]) -> Option<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(GIT_TIMEOUT, output).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Remove a project from the recent list, returning whether it was listed.
+///
+/// Clears the active project if it was the one removed.
+#[tauri::command]
+pub async fn remove_project(app: AppHandle, path: String) -> Result<bool, CommandError> {
+    let path = canonical_project_path(&path);
+    let mut projects = load_projects()?;
+
+    let before = projects.len();
+    projects.retain(|p| !same_project_path(&p.path, &path));
+    if projects.len() == before {
+        return Ok(false);
+    }
+
+    save_projects(&projects)?;
+    forget_active_project(&app, |active| same_project_path(active, &path))?;
+
+    Ok(true)
+}
+
+/// Empty the recent projects list, returning how many entries were removed.
+///
+/// Clears the active project unless it's a pinned project that was kept.
+#[tauri::command]
+pub async fn clear_recent_projects(
+    app: AppHandle,
+    keep_pinned: Option<bool>,
+) -> Result<usize, CommandError> {
+    let (removed, kept) = clear_saved_projects(keep_pinned.unwrap_or(false))?;
+    forget_active_project(&app, |active| {
+        !kept.iter().any(|p| same_project_path(&p.path, active))
+    })?;
+
+    Ok(removed)
+}
+
+/// Rewrite the recent list with only the kept projects, returning the removed count and those kept
+fn clear_saved_projects(keep_pinned: bool) -> Result<(usize, Vec<Project>), CommandError> {
+    // A corrupt list is cleared too
+    let mut projects = load_projects().unwrap_or_default();
+
+    let before = projects.len();
+    if keep_pinned {
+        projects.retain(|p| p.pinned);
+    } else {
+        projects.clear();
+    }
+
+    // Written as [] rather than deleted, so the file is always present afterwards
+    save_projects(&projects)?;
+
+    Ok((before - projects.len(), projects))
+}
+
+/// Pin or unpin a recent project
+#[tauri::command]
+pub async fn set_project_pinned(path: String, pinned: bool) -> Result<Project, CommandError> {
+    let path = canonical_project_path(&path);
+    let mut projects = load_projects()?;
+
+    let project = projects
+        .iter_mut()
+        .find(|p| same_project_path(&p.path, &path))
+        .ok_or_else(|| CommandError::not_found(format!("Project not in recent list: {}", path)))?;
+    project.pinned = pinned;
+    let project = project.clone();
+
+    save_projects(&projects)?;
+
+    Ok(project)
+}
+
+/// What a folder looks like as a project, from `inspect_project`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectInspection {
+    /// Canonical path, as it would be stored by `open_project`
+    pub path: String,
+    pub exists: bool,
+    pub is_dir: bool,
+    /// Has a `.roura` directory
+    pub has_roura_dir: bool,
+    /// Has `.roura/memory.json`
+    pub has_memory: bool,
+    pub is_git_repo: bool,
+    /// Recognized build or manifest files at the top level
+    pub build_files: Vec<String>,
+    /// Languages implied by `build_files`, without duplicates
+    pub languages: Vec<String>,
+    /// Files found, not counting dependency and build output directories
+    pub file_count: usize,
+    /// The count stopped at `MAX_INSPECTED_FILES`
+    pub file_count_truncated: bool,
+}
+
+/// Manifest files used to guess a project's languages
+const BUILD_FILES: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("package.json", "javascript"),
+    ("tsconfig.json", "typescript"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("requirements.txt", "python"),
+    ("go.mod", "go"),
+    ("pom.xml", "java"),
+    ("build.gradle", "java"),
+    ("Gemfile", "ruby"),
+    ("Package.swift", "swift"),
+];
+
+/// Directories skipped when counting files
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", ".venv", "venv", "__pycache__"];
+
+/// Files counted before `inspect_project` stops walking
+const MAX_INSPECTED_FILES: usize = 10_000;
+
+/// Describe a folder as a project without adding it to the recent list
+#[tauri::command]
+pub async fn inspect_project(path: String) -> Result<ProjectInspection, CommandError> {
+    let path = canonical_project_path(&path);
+    ensure_allowed_project(&path)?;
+
+    tokio::task::spawn_blocking(move || {
+        let root = PathBuf::from(&path);
+        let is_dir = root.is_dir();
+
+        let build_files: Vec<String> = BUILD_FILES
+            .iter()
+            .filter(|(file, _)| is_dir && root.join(file).is_file())
+            .map(|(file, _)| file.to_string())
+            .collect();
+        let mut languages: Vec<String> = Vec::new();
+        for (file, language) in BUILD_FILES {
+            if build_files.iter().any(|f| f == file) && !languages.iter().any(|l| l == language) {
+                languages.push(language.to_string());
+            }
+        }
+
+        let (file_count, file_count_truncated) = if is_dir {
+            count_project_files(&root)
+        } else {
+            (0, false)
+        };
+
+        ProjectInspection {
+            exists: root.exists(),
+            is_dir,
+            has_roura_dir: root.join(".roura").is_dir(),
+            has_memory: memory_path(&path).is_file(),
+            // `.git` is a file in worktrees and submodules
+            is_git_repo: root.join(".git").exists(),
+            build_files,
+            languages,
+            file_count,
+            file_count_truncated,
+            path,
+        }
+    })
+    .await
+    .map_err(|e| CommandError::internal("Project inspection failed").with_details(e))
+}
+
+/// Count files under `root` up to `MAX_INSPECTED_FILES`, returning whether it stopped early
+fn count_project_files(root: &Path) -> (usize, bool) {
+    let mut count = 0;
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            // Not following symlinks keeps the walk inside the project and out of cycles
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let skipped = entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| SKIPPED_DIRS.contains(&name));
+                if !skipped {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                count += 1;
+                if count >= MAX_INSPECTED_FILES {
+                    return (count, true);
+                }
+            }
+        }
+    }
+
+    (count, false)
+}
+
+/// Where `reveal_project` opens a project
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevealTarget {
+    /// Finder, Explorer or the desktop's file manager
+    FileManager,
+    /// The command in `editor.command`
+    Editor,
+}
+
+/// Open a project folder in the file manager or the configured editor.
+///
+/// Paths outside `projects.allowed_roots` are refused when that's set.
+/// Returns once the program has started; it isn't waited on.
+#[tauri::command]
+pub async fn reveal_project(path: String, target: RevealTarget) -> Result<(), CommandError> {
+    let path = canonical_project_path(&path);
+    if !Path::new(&path).exists() {
+        return Err(CommandError::not_found(format!("Path does not exist: {}", path)));
+    }
+    ensure_allowed_project(&path)?;
+
+    let argv = match target {
+        RevealTarget::FileManager => {
+            let program = if cfg!(target_os = "macos") {
+                "open"
+            } else if cfg!(target_os = "windows") {
+                "explorer"
+            } else {
+                "xdg-open"
+            };
+            vec![program.to_string(), path]
+        }
+        RevealTarget::Editor => editor_args(config_value("editor.command").as_ref(), &path)?,
+    };
+    let Some((program, args)) = argv.split_first() else {
+        return Err(CommandError::internal("Nothing to run"));
+    };
+
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                CommandError::not_found(format!("Program not found: {}", program))
+            }
+            _ => CommandError::io(format!("Failed to run {}", program)).with_details(e),
+        })?;
+
+    // Reap it in the background so it doesn't linger as a zombie
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
+
+/// The argv that opens `path` with `editor.command`.
+///
+/// A string names the program alone, so a path with spaces needs no quoting;
+/// a list is the program followed by its arguments, e.g. `["code", "-n"]`.
+fn editor_args(
+    command: Option<&serde_json::Value>,
+    path: &str,
+) -> Result<Vec<String>, CommandError> {
+    let mut argv: Vec<String> = match command {
+        Some(serde_json::Value::String(program)) => vec![program.clone()],
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|i| i.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    if argv.first().is_none_or(|program| program.trim().is_empty()) {
+        return Err(CommandError::invalid_input(
+            "No editor configured in editor.command",
+        ));
+    }
+
+    argv.push(path.to_string());
+    Ok(argv)
+}
+
+/// Marker identifying an exported settings bundle
+const CONFIG_BUNDLE_FORMAT: &str = "roura-agent-config";
+const CONFIG_BUNDLE_VERSION: u64 = 1;
+
+/// Portable copy of the desktop settings, as written by `export_config`
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfigBundle {
+    format: String,
+    version: u64,
+    exported_at: String,
+    config: HashMap<String, serde_json::Value>,
+    recent_projects: Vec<Project>,
+}
+
+/// Write the config and recent projects to a single bundle file at `dest`
+#[tauri::command]
+pub async fn export_config(dest: String) -> Result<(), CommandError> {
+    let bundle = ConfigBundle {
+        format: CONFIG_BUNDLE_FORMAT.to_string(),
+        version: CONFIG_BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        config: load_config()?,
+        recent_projects: load_projects()?,
+    };
+
+    write_json_atomic(Path::new(&dest), &bundle)
+}
+
+/// Restore settings from a bundle written by `export_config`.
+///
+/// Without `merge` the current config and recent projects are replaced. With
+/// `merge`, imported values override matching keys and imported projects are
+/// added to the recent list, deduplicated by path.
+#[tauri::command]
+pub async fn import_config(src: String, merge: bool) -> Result<(), CommandError> {
+    let content = std::fs::read_to_string(&src)
+        .map_err(|e| CommandError::io("Failed to read settings bundle").with_details(e))?;
+    let raw: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse settings bundle").with_details(e))?;
+
+    // Check the header before the body so a future format gets a clear error
+    if raw.get("format").and_then(|f| f.as_str()) != Some(CONFIG_BUNDLE_FORMAT) {
+        return Err(CommandError::invalid_input(format!(
+            "Not a settings bundle: {}",
+            src
+        )));
+    }
+    match raw.get("version").and_then(|v| v.as_u64()) {
+        Some(CONFIG_BUNDLE_VERSION) => {}
+        Some(version) => {
+            return Err(CommandError::invalid_input(format!(
+                "Unsupported settings bundle version: {}",
+                version
+            )))
+        }
+        None => return Err(CommandError::invalid_input("Settings bundle has no version")),
+    }
+
+    let bundle: ConfigBundle = serde_json::from_value(raw)
+        .map_err(|e| CommandError::parse("Invalid settings bundle").with_details(e))?;
+
+    let (config, mut projects) = if merge {
+        let mut config = load_config()?;
+        for (key, value) in bundle.config {
+            merge_config_value(&mut config, key, value);
+        }
+
+        // Local entries come first, so they win over imported duplicates
+        let mut projects = load_projects()?;
+        projects.extend(bundle.recent_projects);
+        (config, dedupe_projects(projects))
+    } else {
+        (bundle.config, dedupe_projects(bundle.recent_projects))
+    };
+
+    let issues = config_schema::validate_all(&config, strict_config(&config));
+    if !issues.is_empty() {
+        return Err(
+            CommandError::invalid_input("Settings bundle has invalid config")
+                .with_details(issues.join("; ")),
+        );
+    }
+
+    // Most recent first, matching how `open_project` orders the list
+    projects.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+    trim_recent_projects(&mut projects);
+
+    save_config(&config)?;
+    save_projects(&projects)
+}
+
+/// How one dotted config key differs between a snapshot and the current config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ConfigDiff {
+    /// Set now, absent from the snapshot
+    Added { key: String, value: serde_json::Value },
+    /// In the snapshot, unset now
+    Removed { key: String, value: serde_json::Value },
+    Changed {
+        key: String,
+        old: serde_json::Value,
+        new: serde_json::Value,
+    },
+}
+
+/// Compare the current config against a snapshot, sorted by key.
+///
+/// The snapshot can be a settings bundle from `export_config` or a plain
+/// config.json. Nested objects are compared key by key.
+#[tauri::command]
+pub async fn diff_config(other_path: String) -> Result<Vec<ConfigDiff>, CommandError> {
+    let content = std::fs::read_to_string(&other_path)
+        .map_err(|e| CommandError::io("Failed to read config snapshot").with_details(e))?;
+    let mut raw: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse config snapshot").with_details(e))?;
+
+    if raw.get("format").and_then(|f| f.as_str()) == Some(CONFIG_BUNDLE_FORMAT) {
+        raw = raw.get_mut("config").map(serde_json::Value::take).unwrap_or_default();
+    }
+    let other: HashMap<String, serde_json::Value> = serde_json::from_value(raw)
+        .map_err(|e| CommandError::parse("Config snapshot is not an object").with_details(e))?;
+
+    let old = flatten_config(&other);
+    let new = flatten_config(&load_config()?);
+
+    let mut diffs = Vec::new();
+    for (key, value) in &new {
+        match old.get(key) {
+            None => diffs.push(ConfigDiff::Added {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            Some(previous) if previous != value => diffs.push(ConfigDiff::Changed {
+                key: key.clone(),
+                old: previous.clone(),
+                new: value.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, value) in &old {
+        if !new.contains_key(key) {
+            diffs.push(ConfigDiff::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    diffs.sort_by(|a, b| a.key().cmp(b.key()));
+    Ok(diffs)
+}
+
+impl ConfigDiff {
+    fn key(&self) -> &str {
+        match self {
+            Self::Added { key, .. } | Self::Removed { key, .. } | Self::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Merge `value` into `config[key]`, recursing into objects present on both sides
+fn merge_config_value(
+    config: &mut HashMap<String, serde_json::Value>,
+    key: String,
+    value: serde_json::Value,
+) {
+    fn merge(target: &mut serde_json::Value, value: serde_json::Value) {
+        match (target, value) {
+            (serde_json::Value::Object(target), serde_json::Value::Object(value)) => {
+                for (key, value) in value {
+                    match target.get_mut(&key) {
+                        Some(existing) => merge(existing, value),
+                        None => {
+                            target.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (target, value) => *target = value,
+        }
+    }
+
+    match config.get_mut(&key) {
+        Some(existing) => merge(existing, value),
+        None => {
+            config.insert(key, value);
+        }
+    }
+}
+
+/// Get a page of memory for current project, newest first.
+///
+/// `query` matches content case-insensitively, `category` must match exactly,
+/// and `tags` requires every listed tag. All filters are optional and combine with AND.
+/// Pagination applies after filtering; `total` is the filtered count.
+/// With `sort_by_relevance`, pinned notes come first, then the most relevant.
+#[tauri::command]
+pub async fn get_memory(
+    project_path: String,
+    query: Option<String>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    sort_by_relevance: Option<bool>,
+) -> Result<MemoryPage, CommandError> {
+    let mut notes = filtered_memory(&project_path, query, category, tags)?;
+    if sort_by_relevance.unwrap_or(false) {
+        sort_most_relevant_first(&mut notes);
+    }
+    let total = notes.len();
+
+    // Offsets past the end yield an empty page
+    let notes = notes
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(DEFAULT_MEMORY_PAGE_SIZE))
+        .collect();
+
+    Ok(MemoryPage { notes, total })
+}
+
+/// `get_memory` for the active project, with its default filters and page
+#[tauri::command]
+pub async fn get_active_memory() -> Result<MemoryPage, CommandError> {
+    get_memory(require_active_project()?, None, None, None, None, None, None).await
+}
+
+/// `get_conversation` for the active project
+#[tauri::command]
+pub async fn get_active_conversation() -> Result<Vec<ConversationTurn>, CommandError> {
+    conversation::get_conversation(require_active_project()?).await
+}
+
+/// Count a project's memory notes per category, for filter facets
+#[tauri::command]
+pub async fn get_memory_categories(project_path: String) -> Result<MemoryCategories, CommandError> {
+    let notes = filtered_memory(&project_path, None, None, None)?;
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for note in &notes {
+        *counts.entry(note.category.clone()).or_default() += 1;
+    }
+
+    // Ties stay alphabetical since the sort is stable
+    let mut categories: Vec<CategoryCount> = counts
+        .into_iter()
+        .map(|(category, count)| CategoryCount { category, count })
+        .collect();
+    categories.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+    Ok(MemoryCategories {
+        categories,
+        total: notes.len(),
+    })
+}
+
+/// Notes returned by `get_memory` when no limit is given
+const DEFAULT_MEMORY_PAGE_SIZE: usize = 50;
+
+/// Load, filter and sort a project's memory notes
+fn filtered_memory(
+    project_path: &str,
+    query: Option<String>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<Vec<MemoryNote>, CommandError> {
+    let memory_path = memory_path(project_path);
+
+    if !memory_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = load_memory(&memory_path)?;
+
+    let query = query.map(|q| q.to_lowercase());
+
+    let mut notes: Vec<MemoryNote> = data
+        .get("notes")
+        .and_then(|n| n.as_array())
+        .map(|arr| arr.iter().filter_map(parse_note).collect())
+        .unwrap_or_default();
+    for note in &mut notes {
+        resolve_attachments(project_path, note);
+    }
+
+    notes.retain(|note| {
+        query
+            .as_ref()
+            .is_none_or(|q| note.content.to_lowercase().contains(q))
+            && category.as_ref().is_none_or(|c| &note.category == c)
+            && tags
+                .as_ref()
+                .is_none_or(|wanted| wanted.iter().all(|t| note.tags.contains(t)))
+    });
+
+    sort_newest_first(&mut notes);
+
+    Ok(notes)
+}
+
+/// Sort pinned notes first, then by relevance descending.
+///
+/// Stable, so equally relevant notes keep their existing (newest first) order.
+fn sort_most_relevant_first(notes: &mut [MemoryNote]) {
+    notes.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.relevance.total_cmp(&a.relevance))
+    });
+}
+
+/// Default number of memory notes added to a message's context
+const DEFAULT_MEMORY_CONTEXT_NOTES: usize = 10;
+/// Default cap on the characters of memory added to a message's context
+const DEFAULT_MEMORY_CONTEXT_CHARS: usize = 4_000;
+
+/// The most relevant notes of a project as `- note` lines, within the configured budget.
+///
+/// A note that doesn't fit the remaining budget is skipped whole, so a later,
+/// shorter note can still be included.
+fn memory_context(project_path: &str) -> Result<Option<String>, CommandError> {
+    let max_notes = config_value("memory.context_max_notes")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_MEMORY_CONTEXT_NOTES, |n| n as usize);
+    let max_chars = config_value("memory.context_max_chars")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_MEMORY_CONTEXT_CHARS, |n| n as usize);
+
+    let mut notes = filtered_memory(project_path, None, None, None)?;
+    sort_most_relevant_first(&mut notes);
+
+    let mut lines = Vec::new();
+    let mut used = 0;
+    for note in notes {
+        if lines.len() >= max_notes {
+            break;
+        }
+        let line = format!("- {}", note.content.trim());
+        let cost = line.chars().count() + 1;
+        if used + cost > max_chars {
+            continue;
+        }
+        used += cost;
+        lines.push(line);
+    }
+
+    Ok((!lines.is_empty()).then(|| lines.join("\n")))
+}
+
+/// Sort notes by `created_at` descending, with unparseable timestamps last
+fn sort_newest_first(notes: &mut [MemoryNote]) {
+    notes.sort_by_cached_key(|n| {
+        std::cmp::Reverse(chrono::DateTime::parse_from_rfc3339(&n.created_at).ok())
+    });
+}
+
+/// Convert a stored note into its frontend shape
+fn parse_note(n: &serde_json::Value) -> Option<MemoryNote> {
+    Some(MemoryNote {
+        id: n.get("entry_id").and_then(|v| v.as_str())?.to_string(),
+        content: n.get("content").and_then(|v| v.as_str())?.to_string(),
+        category: n
+            .get("category")
+            .and_then(|v| v.as_str())
+            .unwrap_or("note")
+            .to_string(),
+        tags: n
+            .get("tags")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|t| t.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        created_at: n
+            .get("created_at")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        updated_at: n
+            .get("updated_at")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        relevance: n
+            .get("relevance")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(1.0),
+        pinned: note_pinned(n),
+        attachments: n
+            .get("attachments")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|a| a.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+fn note_pinned(note: &serde_json::Value) -> bool {
+    note.get("pinned").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Path to a project's memory file
+fn memory_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path)
+        .join(".roura")
+        .join("memory.json")
+}
+
+/// Current layout of memory.json
+const MEMORY_VERSION: u64 = 3;
+
+/// Where copied note attachments go, under `.roura`
+const MEMORY_ATTACHMENTS_DIR: &str = "attachments";
+
+/// Read a memory file, upgrading older layouts in place.
+///
+/// A missing file reads as an empty document. Legacy files are rewritten
+/// once with the current `version` so later reads skip the migration.
+fn load_memory(memory_path: &Path) -> Result<serde_json::Value, CommandError> {
+    if !memory_path.exists() {
+        return Ok(serde_json::json!({"notes": [], "version": MEMORY_VERSION}));
+    }
+
+    let content = std::fs::read_to_string(memory_path)
+        .map_err(|e| CommandError::io("Failed to read memory").with_details(e))?;
+    let data: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse memory").with_details(e))?;
+
+    if memory_version(&data) >= MEMORY_VERSION {
+        return Ok(data);
+    }
+
+    let data = migrate_memory(data);
+    write_json_atomic(memory_path, &data)?;
+
+    Ok(data)
+}
+
+/// Layout version of a memory document; v1 had none
+fn memory_version(data: &serde_json::Value) -> u64 {
+    data.get("version").and_then(|v| v.as_u64()).unwrap_or(1)
+}
+
+/// Upgrade a memory document from any older layout to `MEMORY_VERSION`
+fn migrate_memory(mut data: serde_json::Value) -> serde_json::Value {
+    if memory_version(&data) < 2 {
+        data = migrate_memory_v1(data);
+    }
+    if memory_version(&data) < 3 {
+        data = migrate_memory_v2(data);
+    }
+    data
+}
+
+/// Upgrade a v2 memory document, giving every note an empty `attachments` list
+fn migrate_memory_v2(mut data: serde_json::Value) -> serde_json::Value {
+    if let Some(notes) = data.get_mut("notes").and_then(|n| n.as_array_mut()) {
+        for note in notes.iter_mut().filter_map(|n| n.as_object_mut()) {
+            note.entry("attachments".to_string())
+                .or_insert_with(|| serde_json::json!([]));
+        }
+    }
+    if let Some(doc) = data.as_object_mut() {
+        doc.insert("version".to_string(), serde_json::json!(3));
+    }
+    data
+}
+
+/// Upgrade a v1 memory document: either a bare array of notes or an
+/// envelope without `version`, with notes keyed by `id`
+fn migrate_memory_v1(data: serde_json::Value) -> serde_json::Value {
+    let (mut doc, notes) = match data {
+        serde_json::Value::Array(notes) => (serde_json::Map::new(), notes),
+        serde_json::Value::Object(mut doc) => {
+            let notes = match doc.remove("notes") {
+                Some(serde_json::Value::Array(notes)) => notes,
+                _ => Vec::new(),
+            };
+            (doc, notes)
+        }
+        _ => (serde_json::Map::new(), Vec::new()),
+    };
+
+    let notes: Vec<serde_json::Value> = notes
+        .into_iter()
+        .map(|note| match note {
+            serde_json::Value::Object(mut note) => {
+                if !note.contains_key("entry_id") {
+                    let id = match note.remove("id") {
+                        Some(serde_json::Value::String(id)) => id,
+                        Some(serde_json::Value::Number(id)) => id.to_string(),
+                        _ => uuid::Uuid::new_v4().to_string(),
+                    };
+                    note.insert("entry_id".to_string(), serde_json::json!(id));
+                }
+                note.entry("source".to_string())
+                    .or_insert_with(|| serde_json::json!("user"));
+                note.entry("relevance".to_string())
+                    .or_insert_with(|| serde_json::json!(1.0));
+                serde_json::Value::Object(note)
+            }
+            // v1 allowed plain strings as notes
+            serde_json::Value::String(content) => serde_json::json!({
+                "entry_id": uuid::Uuid::new_v4().to_string(),
+                "content": content,
+                "category": "note",
+                "tags": [],
+                "source": "user",
+                "relevance": 1.0,
+            }),
+            other => other,
+        })
+        .collect();
+
+    doc.insert("notes".to_string(), serde_json::Value::Array(notes));
+    doc.insert("version".to_string(), serde_json::json!(2));
+
+    serde_json::Value::Object(doc)
+}
+
+/// Mutable access to the notes array of a memory document
+fn notes_mut(data: &mut serde_json::Value) -> Result<&mut Vec<serde_json::Value>, CommandError> {
+    data.get_mut("notes")
+        .and_then(|n| n.as_array_mut())
+        .ok_or_else(|| CommandError::parse("Memory file has no notes array"))
+}
+
+fn note_id(note: &serde_json::Value) -> Option<&str> {
+    note.get("entry_id").and_then(|v| v.as_str())
+}
+
+/// Attachment copies for a change that hasn't been saved yet.
+///
+/// Dropping it removes the copies, so a failed add or update leaves none behind;
+/// `keep` once memory.json has been written.
+#[derive(Default)]
+struct AttachmentCopies(Vec<PathBuf>);
+
+impl AttachmentCopies {
+    fn keep(mut self) {
+        self.0.clear();
+    }
+}
+
+impl Drop for AttachmentCopies {
+    fn drop(&mut self) {
+        for copy in &self.0 {
+            let _ = std::fs::remove_file(copy);
+        }
+    }
+}
+
+/// Store files for a note as paths relative to `.roura`.
+///
+/// Absolute paths outside `.roura` are copied into `.roura/attachments`, with
+/// each copy recorded in `copies`; ones inside it are referenced in place.
+/// Relative paths must already name a file under `.roura`, after following symlinks.
+fn store_attachments(
+    project_path: &str,
+    paths: &[String],
+    copies: &mut AttachmentCopies,
+) -> Result<Vec<String>, CommandError> {
+    let roura = PathBuf::from(project_path).join(".roura");
+    let canonical_roura = std::fs::canonicalize(&roura).ok();
+    let join = |path: &Path| {
+        path.components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+
+    let mut stored = Vec::with_capacity(paths.len());
+    for path in paths {
+        let source = Path::new(path);
+        if !source.is_absolute() {
+            let normal = source
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)));
+            let inside = normal
+                && std::fs::canonicalize(roura.join(source)).is_ok_and(|resolved| {
+                    resolved.is_file()
+                        && canonical_roura
+                            .as_ref()
+                            .is_some_and(|roura| resolved.starts_with(roura))
+                });
+            if !inside {
+                return Err(CommandError::invalid_input(format!(
+                    "Attachment is not a file under .roura: {}",
+                    path
+                )));
+            }
+            stored.push(join(source));
+            continue;
+        }
+
+        let source = std::fs::canonicalize(source).map_err(|e| {
+            CommandError::not_found(format!("Attachment not found: {}", path)).with_details(e)
+        })?;
+        if !source.is_file() {
+            return Err(CommandError::invalid_input(format!("Attachment is not a file: {}", path)));
+        }
+        let within = canonical_roura
+            .as_ref()
+            .and_then(|roura| source.strip_prefix(roura).ok().map(join));
+        if let Some(relative) = within {
+            stored.push(relative);
+            continue;
+        }
+
+        let dir = roura.join(MEMORY_ATTACHMENTS_DIR);
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            CommandError::io("Failed to create attachments directory").with_details(e)
+        })?;
+        // Prefixed so attachments with the same name don't overwrite each other
+        let name = format!(
+            "{}-{}",
+            &uuid::Uuid::new_v4().simple().to_string()[..8],
+            source.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+        );
+        let copy = dir.join(&name);
+        std::fs::copy(&source, &copy).map_err(|e| {
+            CommandError::io(format!("Failed to copy attachment {}", path)).with_details(e)
+        })?;
+        copies.0.push(copy);
+        stored.push(format!("{}/{}", MEMORY_ATTACHMENTS_DIR, name));
+    }
+
+    Ok(stored)
+}
+
+/// Paths in a stored `attachments` array
+fn attachment_list(attachments: Option<&serde_json::Value>) -> Vec<String> {
+    attachments
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|a| a.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// Stored attachment paths of every note in `notes`
+fn note_attachments<'a>(notes: impl IntoIterator<Item = &'a serde_json::Value>) -> Vec<String> {
+    notes
+        .into_iter()
+        .flat_map(|n| attachment_list(n.get("attachments")))
+        .collect()
+}
+
+/// Delete copies in `.roura/attachments` that no note in `data` refers to any more.
+///
+/// Only the `dropped` paths are considered, so files referenced in place and
+/// copies for changes still being made are never touched.
+fn release_attachments(project_path: &str, data: &mut serde_json::Value, dropped: &[String]) {
+    let prefix = format!("{}/", MEMORY_ATTACHMENTS_DIR);
+    let still_used = notes_mut(data)
+        .map(|notes| note_attachments(notes.iter()))
+        .unwrap_or_default();
+    let roura = PathBuf::from(project_path).join(".roura");
+
+    for attachment in dropped {
+        let owned = attachment
+            .strip_prefix(&prefix)
+            .is_some_and(|name| !name.is_empty() && !name.contains(['/', '\\']));
+        if owned && !still_used.contains(attachment) {
+            let _ = std::fs::remove_file(roura.join(attachment));
+        }
+    }
+}
+
+/// Turn a note's stored attachment paths into absolute ones for the frontend
+fn resolve_attachments(project_path: &str, note: &mut MemoryNote) {
+    let roura = PathBuf::from(project_path).join(".roura");
+    for attachment in &mut note.attachments {
+        *attachment = roura.join(&*attachment).to_string_lossy().into_owned();
+    }
+}
+
+/// Delete a memory note, returning whether it was found
+#[tauri::command]
+pub async fn delete_memory_note(project_path: String, id: String) -> Result<bool, CommandError> {
+    let memory_path = memory_path(&project_path);
+
+    if !memory_path.exists() {
+        return Ok(false);
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let notes = notes_mut(&mut data)?;
+
+    let Some(index) = notes.iter().position(|n| note_id(n) == Some(id.as_str())) else {
+        return Ok(false);
+    };
+    let removed = notes.remove(index);
+
+    write_json_atomic(&memory_path, &data)?;
+    release_attachments(&project_path, &mut data, &note_attachments([&removed]));
+
+    Ok(true)
+}
+
+/// Update a memory note's content, category and tags in place.
+///
+/// `attachments` replaces the note's attachments when given and keeps them otherwise.
+#[tauri::command]
+pub async fn update_memory_note(
+    project_path: String,
+    id: String,
+    content: String,
+    category: String,
+    tags: Vec<String>,
+    attachments: Option<Vec<String>>,
+) -> Result<MemoryNote, CommandError> {
+    let memory_path = memory_path(&project_path);
+
+    if !memory_path.exists() {
+        return Err(CommandError::not_found(format!("Memory note not found: {}", id)));
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let note = notes_mut(&mut data)?
+        .iter_mut()
+        .find(|n| note_id(n) == Some(id.as_str()))
+        .and_then(|n| n.as_object_mut())
+        .ok_or_else(|| CommandError::not_found(format!("Memory note not found: {}", id)))?;
+
+    // created_at, source and relevance are left as they were
+    note.insert("content".to_string(), serde_json::json!(content));
+    note.insert("category".to_string(), serde_json::json!(category));
+    note.insert("tags".to_string(), serde_json::json!(tags));
+    let mut copies = AttachmentCopies::default();
+    let mut replaced = Vec::new();
+    if let Some(attachments) = attachments {
+        let attachments = store_attachments(&project_path, &attachments, &mut copies)?;
+        replaced = attachment_list(note.get("attachments"));
+        note.insert("attachments".to_string(), serde_json::json!(attachments));
+    }
+    note.insert(
+        "updated_at".to_string(),
+        serde_json::json!(chrono::Utc::now().to_rfc3339()),
+    );
+
+    let mut updated = parse_note(&serde_json::Value::Object(note.clone()))
+        .ok_or_else(|| CommandError::internal("Updated note is malformed"))?;
+    resolve_attachments(&project_path, &mut updated);
+
+    write_json_atomic(&memory_path, &data)?;
+    copies.keep();
+    release_attachments(&project_path, &mut data, &replaced);
+
+    Ok(updated)
+}
+
+/// Pin or unpin a memory note, returning whether it was found
+#[tauri::command]
+pub async fn set_memory_note_pinned(
+    project_path: String,
+    id: String,
+    pinned: bool,
+) -> Result<bool, CommandError> {
+    let memory_path = memory_path(&project_path);
+
+    if !memory_path.exists() {
+        return Ok(false);
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let Some(note) = notes_mut(&mut data)?
+        .iter_mut()
+        .find(|n| note_id(n) == Some(id.as_str()))
+        .and_then(|n| n.as_object_mut())
+    else {
+        return Ok(false);
+    };
+
+    note.insert("pinned".to_string(), serde_json::json!(pinned));
+    write_json_atomic(&memory_path, &data)?;
+
+    Ok(true)
+}
+
+/// Decay each unpinned note's relevance by its age.
+///
+/// Relevance is set to `0.5^(age / half_life)`, so a note loses half its
+/// relevance every `half_life_days`. Returns how many notes changed.
+#[tauri::command]
+pub async fn recompute_memory_relevance(
+    project_path: String,
+    half_life_days: f64,
+) -> Result<usize, CommandError> {
+    if !half_life_days.is_finite() || half_life_days <= 0.0 {
+        return Err(CommandError::invalid_input("half_life_days must be a positive number"));
+    }
+
+    let memory_path = memory_path(&project_path);
+
+    if !memory_path.exists() {
+        return Ok(0);
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let now = chrono::Utc::now();
+    let mut updated = 0;
+
+    for note in notes_mut(&mut data)?.iter_mut() {
+        if note_pinned(note) {
+            continue;
+        }
+
+        let Some(created_at) = note
+            .get("created_at")
+            .and_then(|v| v.as_str())
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        else {
+            continue;
+        };
+
+        // Future timestamps count as brand new
+        let age_days = (now - created_at.with_timezone(&chrono::Utc))
+            .num_seconds()
+            .max(0) as f64
+            / 86_400.0;
+        let relevance = 0.5_f64.powf(age_days / half_life_days).clamp(0.0, 1.0);
+
+        let Some(obj) = note.as_object_mut() else {
+            continue;
+        };
+        let previous = obj.get("relevance").and_then(|v| v.as_f64());
+        if previous != Some(relevance) {
+            obj.insert("relevance".to_string(), serde_json::json!(relevance));
+            updated += 1;
+        }
+    }
+
+    if updated > 0 {
+        write_json_atomic(&memory_path, &data)?;
+    }
+
+    Ok(updated)
+}
+
+/// Result of `compact_memory`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionReport {
+    pub before: usize,
+    pub after: usize,
+    /// Notes folded into another note
+    pub merged: usize,
+}
+
+/// Merge duplicate memory notes.
+///
+/// Notes with identical content always merge. With `similarity`, notes that match
+/// after lowercasing and collapsing whitespace merge too, as do notes whose word
+/// sets overlap at least that much (Jaccard, 0-1). The surviving note keeps the
+/// earliest `created_at` and the union of tags and attachments. Two pinned notes never merge.
+#[tauri::command]
+pub async fn compact_memory(
+    project_path: String,
+    similarity: Option<f64>,
+) -> Result<CompactionReport, CommandError> {
+    if similarity.is_some_and(|s| !(s > 0.0 && s <= 1.0)) {
+        return Err(CommandError::invalid_input("similarity must be above 0 and at most 1"));
+    }
+
+    let memory_path = memory_path(&project_path);
+
+    if !memory_path.exists() {
+        return Ok(CompactionReport {
+            before: 0,
+            after: 0,
+            merged: 0,
+        });
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let notes = std::mem::take(notes_mut(&mut data)?);
+    let before = notes.len();
+
+    // Each group is a list of indexes into `notes`, first member as representative
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, note) in notes.iter().enumerate() {
+        let group = groups.iter_mut().find(|group| {
+            let pinned_clash = note_pinned(note) && group.iter().any(|&g| note_pinned(&notes[g]));
+            !pinned_clash && notes_match(&notes[group[0]], note, similarity)
+        });
+        match group {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut compacted = Vec::with_capacity(groups.len());
+    for group in groups {
+        if group.len() == 1 {
+            compacted.push(notes[group[0]].clone());
+            continue;
+        }
+
+        // A pinned note survives, otherwise the oldest
+        let created_at = |i: &usize| {
+            notes[*i]
+                .get("created_at")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let survivor = group
+            .iter()
+            .copied()
+            .min_by_key(|i| (!note_pinned(&notes[*i]), created_at(i)))
+            .unwrap_or(group[0]);
+        let earliest = group
+            .iter()
+            .map(created_at)
+            .filter(|c| !c.is_empty())
+            .min();
+
+        let mut tags: Vec<serde_json::Value> = Vec::new();
+        let mut attachments: Vec<serde_json::Value> = Vec::new();
+        let mut relevance: Option<f64> = None;
+        for &i in &group {
+            for tag in notes[i].get("tags").and_then(|t| t.as_array()).into_iter().flatten() {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+            let attached = notes[i].get("attachments").and_then(|a| a.as_array());
+            for attachment in attached.into_iter().flatten() {
+                if !attachments.contains(attachment) {
+                    attachments.push(attachment.clone());
+                }
+            }
+            if let Some(r) = notes[i].get("relevance").and_then(|v| v.as_f64()) {
+                relevance = Some(relevance.map_or(r, |max| max.max(r)));
+            }
+        }
+
+        let mut merged = notes[survivor].clone();
+        if let Some(obj) = merged.as_object_mut() {
+            obj.insert("tags".to_string(), serde_json::Value::Array(tags));
+            obj.insert("attachments".to_string(), serde_json::Value::Array(attachments));
+            if let Some(earliest) = earliest {
+                obj.insert("created_at".to_string(), serde_json::json!(earliest));
+            }
+            if let Some(relevance) = relevance {
+                obj.insert("relevance".to_string(), serde_json::json!(relevance));
+            }
+            obj.insert("updated_at".to_string(), serde_json::json!(now));
+        }
+        compacted.push(merged);
+    }
+
+    let after = compacted.len();
+    *notes_mut(&mut data)? = compacted;
+
+    if after != before {
+        write_json_atomic(&memory_path, &data)?;
+    }
+
+    Ok(CompactionReport {
+        before,
+        after,
+        merged: before - after,
+    })
+}
+
+/// Whether two notes are duplicates under `compact_memory`'s rules
+fn notes_match(a: &serde_json::Value, b: &serde_json::Value, similarity: Option<f64>) -> bool {
+    let content = |n: &serde_json::Value| {
+        n.get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    let (a, b) = (content(a), content(b));
+
+    if a == b {
+        return true;
+    }
+    let Some(threshold) = similarity else {
+        return false;
+    };
+
+    let normalize = |s: &str| s.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ");
+    let (a, b) = (normalize(&a), normalize(&b));
+    if a == b {
+        return true;
+    }
+
+    let words_a: std::collections::HashSet<&str> = a.split(' ').collect();
+    let words_b: std::collections::HashSet<&str> = b.split(' ').collect();
+    let union = words_a.union(&words_b).count();
+    union > 0 && words_a.intersection(&words_b).count() as f64 / union as f64 >= threshold
+}
+
+/// Size of one memory category, from `memory_stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySize {
+    pub category: String,
+    pub notes: usize,
+    /// Bytes of the category's notes as compact JSON
+    pub bytes: u64,
+}
+
+/// How big a project's memory file is, from `memory_stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryStats {
+    pub notes: usize,
+    /// Size of memory.json on disk
+    pub file_bytes: u64,
+    /// Largest first
+    pub categories: Vec<CategorySize>,
+    /// `memory.max_bytes`, if set
+    pub max_bytes: Option<u64>,
+}
+
+/// Report a project's memory size, overall and per category
+#[tauri::command]
+pub async fn memory_stats(project_path: String) -> Result<MemoryStats, CommandError> {
+    let memory_path = memory_path(&project_path);
+    let max_bytes = memory_max_bytes();
+
+    if !memory_path.exists() {
+        return Ok(MemoryStats {
+            notes: 0,
+            file_bytes: 0,
+            categories: Vec::new(),
+            max_bytes,
+        });
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let file_bytes = std::fs::metadata(&memory_path)
+        .map_err(|e| CommandError::io("Failed to read memory").with_details(e))?
+        .len();
+    let notes = notes_mut(&mut data)?;
+
+    let mut sizes: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    for note in notes.iter() {
+        let category = note
+            .get("category")
+            .and_then(|v| v.as_str())
+            .unwrap_or("note")
+            .to_string();
+        let bytes = serde_json::to_vec(note).map_or(0, |b| b.len() as u64);
+        let entry = sizes.entry(category).or_default();
+        entry.0 += 1;
+        entry.1 += bytes;
+    }
+
+    let mut categories: Vec<CategorySize> = sizes
+        .into_iter()
+        .map(|(category, (notes, bytes))| CategorySize {
+            category,
+            notes,
+            bytes,
+        })
+        .collect();
+    categories.sort_by_key(|c| std::cmp::Reverse(c.bytes));
+
+    Ok(MemoryStats {
+        notes: notes.len(),
+        file_bytes,
+        categories,
+        max_bytes,
+    })
+}
+
+/// `memory.max_bytes`, the most memory.json may take up after an add
+fn memory_max_bytes() -> Option<u64> {
+    config_value("memory.max_bytes").and_then(|v| v.as_u64())
+}
+
+/// Bytes `data` takes up once written by `write_json_atomic`
+fn memory_size(data: &serde_json::Value) -> Result<u64, CommandError> {
+    let content = serde_json::to_string_pretty(data)
+        .map_err(|e| CommandError::internal("Failed to serialize memory").with_details(e))?;
+    Ok(content.len() as u64)
+}
+
+/// Keep `data` within `memory.max_bytes` after its last `added` notes were appended.
+///
+/// Over budget, an add fails unless `memory.evict_over_budget` is set, in which
+/// case the least relevant unpinned older notes are dropped, oldest first among
+/// equals. Returns the evicted notes.
+fn enforce_memory_budget(
+    data: &mut serde_json::Value,
+    added: usize,
+) -> Result<Vec<serde_json::Value>, CommandError> {
+    let Some(max_bytes) = memory_max_bytes() else {
+        return Ok(Vec::new());
+    };
+    let mut size = memory_size(data)?;
+    if size <= max_bytes {
+        return Ok(Vec::new());
+    }
+
+    // Eviction deletes notes, so it has to be asked for
+    let evict = config_value("memory.evict_over_budget")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !evict {
+        return Err(CommandError::invalid_input(format!(
+            "Memory would grow to {} bytes, over memory.max_bytes ({})",
+            size, max_bytes
+        ))
+        .with_details("Remove notes, raise the limit, or set memory.evict_over_budget"));
+    }
+
+    let mut evicted = Vec::new();
+    while size > max_bytes {
+        let notes = notes_mut(data)?;
+        let existing = notes.len().saturating_sub(added);
+        let victim = notes[..existing]
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| !note_pinned(n))
+            .min_by(|(_, a), (_, b)| {
+                let relevance = |n: &serde_json::Value| {
+                    n.get("relevance").and_then(|v| v.as_f64()).unwrap_or(1.0)
+                };
+                let created_at = |n: &serde_json::Value| {
+                    n.get("created_at")
+                        .and_then(|v| v.as_str())
+                        .and_then(|c| chrono::DateTime::parse_from_rfc3339(c).ok())
+                };
+                relevance(a)
+                    .total_cmp(&relevance(b))
+                    .then(created_at(a).cmp(&created_at(b)))
+            })
+            .map(|(i, _)| i);
+
+        let Some(victim) = victim else {
+            return Err(CommandError::invalid_input(format!(
+                "Memory exceeds memory.max_bytes ({}) even after evicting every unpinned note",
+                max_bytes
+            )));
+        };
+        evicted.push(notes.remove(victim));
+        size = memory_size(data)?;
+    }
+
+    Ok(evicted)
+}
+
+/// A note from `add_memory_note`, with what made room for it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddedNote {
+    #[serde(flatten)]
+    pub note: MemoryNote,
+    /// Notes evicted to stay within `memory.max_bytes`
+    pub evicted: usize,
+}
+
+/// Add a memory note, copying any `attachments` from outside the project into `.roura`
+#[tauri::command]
+pub async fn add_memory_note(
+    project_path: String,
+    content: String,
+    category: String,
+    tags: Vec<String>,
+    attachments: Option<Vec<String>>,
+) -> Result<AddedNote, CommandError> {
+    let memory_path = memory_path(&project_path);
+
+    if let Some(memory_dir) = memory_path.parent() {
+        std::fs::create_dir_all(memory_dir)
+            .map_err(|e| CommandError::io("Failed to create memory directory").with_details(e))?;
+    }
+
+    // Load existing memory
+    let mut data = load_memory(&memory_path)?;
+
+    // Create new note
+    let note_id = uuid::Uuid::new_v4().to_string();
+    let created_at = chrono::Utc::now().to_rfc3339();
+    let mut copies = AttachmentCopies::default();
+    let attachments =
+        store_attachments(&project_path, &attachments.unwrap_or_default(), &mut copies)?;
+
+    let note = serde_json::json!({
+        "entry_id": note_id,
+        "content": content,
+        "category": category,
+        "tags": tags,
+        "source": "user",
+        "relevance": 1.0,
+        "created_at": created_at,
+        "attachments": attachments,
+    });
+
+    // Add to notes array
+    notes_mut(&mut data)?.push(note);
+    let evicted = enforce_memory_budget(&mut data, 1)?;
+
+    // Save
+    write_json_atomic(&memory_path, &data)?;
+    copies.keep();
+    release_attachments(&project_path, &mut data, &note_attachments(&evicted));
+
+    let mut note = MemoryNote {
+        id: note_id,
+        content,
+        category,
+        tags,
+        created_at,
+        updated_at: None,
+        relevance: 1.0,
+        pinned: false,
+        attachments,
+    };
+    resolve_attachments(&project_path, &mut note);
+
+    Ok(AddedNote {
+        note,
+        evicted: evicted.len(),
+    })
+}
+
+/// A note for `add_memory_notes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewNote {
+    pub content: String,
+    pub category: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Handled as in `add_memory_note`
+    #[serde(default)]
+    pub attachments: Vec<String>,
+}
+
+/// A note `add_memory_notes` left out, by its position in the request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedNote {
+    pub index: usize,
+    pub reason: String,
+}
+
+/// Outcome of `add_memory_notes`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkAddResult {
+    pub added: Vec<MemoryNote>,
+    pub skipped: Vec<SkippedNote>,
+    /// Notes evicted to stay within `memory.max_bytes`
+    #[serde(default)]
+    pub evicted: usize,
+}
+
+/// Add many memory notes with a single write.
+///
+/// Notes with empty content fail the whole batch, or are skipped and listed
+/// in `skipped` when `skip_invalid` is set. On failure memory.json is left
+/// as it was and any attachments already copied are removed.
+#[tauri::command]
+pub async fn add_memory_notes(
+    project_path: String,
+    notes: Vec<NewNote>,
+    skip_invalid: bool,
+) -> Result<BulkAddResult, CommandError> {
+    let mut valid = Vec::with_capacity(notes.len());
+    let mut skipped = Vec::new();
+    for (index, note) in notes.into_iter().enumerate() {
+        if !note.content.trim().is_empty() {
+            valid.push(note);
+            continue;
+        }
+
+        let reason = "Note content must not be empty".to_string();
+        if !skip_invalid {
+            return Err(CommandError::invalid_input(format!("Note {}: {}", index, reason)));
+        }
+        skipped.push(SkippedNote { index, reason });
+    }
+
+    let memory_path = memory_path(&project_path);
+    if let Some(memory_dir) = memory_path.parent() {
+        std::fs::create_dir_all(memory_dir)
+            .map_err(|e| CommandError::io("Failed to create memory directory").with_details(e))?;
+    }
+
+    let mut data = load_memory(&memory_path)?;
+    let created_at = chrono::Utc::now().to_rfc3339();
+
+    let mut copies = AttachmentCopies::default();
+    let mut added: Vec<MemoryNote> = Vec::with_capacity(valid.len());
+    for note in valid {
+        added.push(MemoryNote {
+            id: uuid::Uuid::new_v4().to_string(),
+            attachments: store_attachments(&project_path, &note.attachments, &mut copies)?,
+            content: note.content,
+            category: note.category,
+            tags: note.tags,
+            created_at: created_at.clone(),
+            updated_at: None,
+            relevance: 1.0,
+            pinned: false,
+        });
+    }
+
+    let stored = notes_mut(&mut data)?;
+    for note in &added {
+        stored.push(serde_json::json!({
+            "entry_id": note.id,
+            "content": note.content,
+            "category": note.category,
+            "tags": note.tags,
+            "source": "user",
+            "relevance": note.relevance,
+            "created_at": note.created_at,
+            "attachments": note.attachments,
+        }));
+    }
+    for note in &mut added {
+        resolve_attachments(&project_path, note);
+    }
+
+    let mut evicted = Vec::new();
+    if !added.is_empty() {
+        evicted = enforce_memory_budget(&mut data, added.len())?;
+        write_json_atomic(&memory_path, &data)?;
+        copies.keep();
+        release_attachments(&project_path, &mut data, &note_attachments(&evicted));
+    }
+
+    Ok(BulkAddResult {
+        added,
+        skipped,
+        evicted: evicted.len(),
+    })
+}
+
+/// What's wrong with part of a memory file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryIssueKind {
+    /// Not JSON, or not a JSON object or array
+    InvalidDocument,
+    /// An older layout, upgraded on the next read
+    LegacyLayout,
+    /// No usable `notes` array
+    MissingNotes,
+    /// A note that isn't an object
+    InvalidNote,
+    DuplicateId,
+    MissingField,
+    InvalidTimestamp,
+    RelevanceOutOfRange,
+    UnknownKey,
+}
+
+/// A problem found by `validate_memory`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryIssue {
+    pub kind: MemoryIssueKind,
+    /// Position of the offending note in the notes array
+    pub index: Option<usize>,
+    /// Key the issue is about, for missing fields, timestamps and unknown keys
+    pub field: Option<String>,
+    pub message: String,
+    /// Whether `repair_memory` fixes this without losing data
+    pub fixable: bool,
+}
+
+impl MemoryIssue {
+    fn new(kind: MemoryIssueKind, index: Option<usize>, message: impl Into<String>) -> Self {
+        let fixable = matches!(
+            kind,
+            MemoryIssueKind::LegacyLayout
+                | MemoryIssueKind::DuplicateId
+                | MemoryIssueKind::RelevanceOutOfRange
+        );
+        Self {
+            kind,
+            index,
+            field: None,
+            message: message.into(),
+            fixable,
+        }
+    }
+
+    fn field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+
+    fn fixable(mut self, fixable: bool) -> Self {
+        self.fixable = fixable;
+        self
+    }
+}
+
+/// Outcome of `repair_memory`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRepair {
+    pub fixed: Vec<MemoryIssue>,
+    /// Issues that need a manual edit
+    pub remaining: Vec<MemoryIssue>,
+}
+
+/// Top-level keys a memory file may have
+const MEMORY_KEYS: &[&str] = &["notes", "version"];
+
+/// Read a memory file as-is, unlike `load_memory` which upgrades it in place
+fn read_memory_document(
+    memory_path: &Path,
+) -> Result<Option<Result<serde_json::Value, MemoryIssue>>, CommandError> {
+    if !memory_path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(memory_path)
+        .map_err(|e| CommandError::io("Failed to read memory").with_details(e))?;
+    let data = serde_json::from_str::<serde_json::Value>(&content).map_err(|e| {
+        MemoryIssue::new(
+            MemoryIssueKind::InvalidDocument,
+            None,
+            format!("Memory file is not valid JSON: {}", e),
+        )
+    });
+    let data = data.and_then(|data| match data {
+        serde_json::Value::Object(_) | serde_json::Value::Array(_) => Ok(data),
+        _ => Err(MemoryIssue::new(
+            MemoryIssueKind::InvalidDocument,
+            None,
+            "Memory file is neither an object nor an array of notes",
+        )),
+    });
+
+    Ok(Some(data))
+}
+
+fn is_legacy_memory(data: &serde_json::Value) -> bool {
+    memory_version(data) < MEMORY_VERSION
+}
+
+/// Problems in a memory document; a legacy one is checked as it reads after its upgrade
+fn memory_issues(data: &serde_json::Value) -> Vec<MemoryIssue> {
+    let mut issues = Vec::new();
+
+    let migrated;
+    let data = if is_legacy_memory(data) {
+        issues.push(MemoryIssue::new(
+            MemoryIssueKind::LegacyLayout,
+            None,
+            "Memory file uses an older layout",
+        ));
+        migrated = migrate_memory(data.clone());
+        &migrated
+    } else {
+        data
+    };
+    let Some(doc) = data.as_object() else {
+        return issues;
+    };
+
+    for key in doc.keys().filter(|k| !MEMORY_KEYS.contains(&k.as_str())) {
+        issues.push(
+            MemoryIssue::new(MemoryIssueKind::UnknownKey, None, format!("Unknown key {}", key))
+                .field(key),
+        );
+    }
+
+    let notes = match doc.get("notes") {
+        Some(serde_json::Value::Array(notes)) => notes,
+        other => {
+            // A missing array can be added; replacing another value would lose it
+            issues.push(
+                MemoryIssue::new(MemoryIssueKind::MissingNotes, None, "No notes array")
+                    .fixable(other.is_none()),
+            );
+            return issues;
+        }
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    for (index, note) in notes.iter().enumerate() {
+        let at = Some(index);
+        let Some(note) = note.as_object() else {
+            issues.push(MemoryIssue::new(
+                MemoryIssueKind::InvalidNote,
+                at,
+                format!("Note {} is not an object", index),
+            ));
+            continue;
+        };
+
+        match note.get("entry_id").and_then(|v| v.as_str()) {
+            Some(id) if !seen.insert(id) => issues.push(MemoryIssue::new(
+                MemoryIssueKind::DuplicateId,
+                at,
+                format!("Note {} reuses id {}", index, id),
+            )),
+            Some(_) => {}
+            None => issues.push(
+                MemoryIssue::new(
+                    MemoryIssueKind::MissingField,
+                    at,
+                    format!("Note {} has no entry_id", index),
+                )
+                .field("entry_id")
+                .fixable(true),
+            ),
+        }
+
+        if !note.get("content").is_some_and(|v| v.is_string()) {
+            issues.push(
+                MemoryIssue::new(
+                    MemoryIssueKind::MissingField,
+                    at,
+                    format!("Note {} has no content", index),
+                )
+                .field("content"),
+            );
+        }
+
+        for field in ["created_at", "updated_at"] {
+            let value = note.get(field).filter(|v| !v.is_null());
+            let valid = value
+                .and_then(|v| v.as_str())
+                .is_some_and(|s| chrono::DateTime::parse_from_rfc3339(s).is_ok());
+            let issue = match value {
+                None if field == "created_at" => MemoryIssue::new(
+                    MemoryIssueKind::MissingField,
+                    at,
+                    format!("Note {} has no created_at", index),
+                ),
+                Some(_) if !valid => MemoryIssue::new(
+                    MemoryIssueKind::InvalidTimestamp,
+                    at,
+                    format!("Note {} has an invalid {}", index, field),
+                ),
+                _ => continue,
+            };
+            issues.push(issue.field(field));
+        }
+
+        if let Some(relevance) = note.get("relevance") {
+            if !relevance.as_f64().is_some_and(|r| (0.0..=1.0).contains(&r)) {
+                issues.push(MemoryIssue::new(
+                    MemoryIssueKind::RelevanceOutOfRange,
+                    at,
+                    format!("Note {} has relevance {} outside [0, 1]", index, relevance),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Check a project's memory.json without changing it; a clean or missing file has no issues
+#[tauri::command]
+pub async fn validate_memory(project_path: String) -> Result<Vec<MemoryIssue>, CommandError> {
+    Ok(match read_memory_document(&memory_path(&project_path))? {
+        None => Vec::new(),
+        Some(Ok(data)) => memory_issues(&data),
+        Some(Err(issue)) => vec![issue],
+    })
+}
+
+/// Fix the issues `validate_memory` marks fixable and write the file back.
+///
+/// Missing and duplicate ids get fresh ones, relevance is clamped to [0, 1]
+/// (non-numbers reset to 1), and a legacy layout is upgraded. Nothing is
+/// written when there is nothing to fix.
+#[tauri::command]
+pub async fn repair_memory(project_path: String) -> Result<MemoryRepair, CommandError> {
+    let memory_path = memory_path(&project_path);
+    let data = match read_memory_document(&memory_path)? {
+        None => {
+            return Ok(MemoryRepair {
+                fixed: Vec::new(),
+                remaining: Vec::new(),
+            })
+        }
+        Some(Ok(data)) => data,
+        Some(Err(issue)) => {
+            return Err(
+                CommandError::parse("Memory file can't be repaired").with_details(issue.message)
+            )
+        }
+    };
+
+    let (fixed, remaining): (Vec<_>, Vec<_>) =
+        memory_issues(&data).into_iter().partition(|i| i.fixable);
+    if fixed.is_empty() {
+        return Ok(MemoryRepair { fixed, remaining });
+    }
+
+    let mut data = if is_legacy_memory(&data) {
+        migrate_memory(data)
+    } else {
+        data
+    };
+    if let Some(doc) = data.as_object_mut() {
+        doc.entry("notes".to_string())
+            .or_insert_with(|| serde_json::json!([]));
+    }
+
+    if let Some(notes) = data.get_mut("notes").and_then(|n| n.as_array_mut()) {
+        let mut seen = std::collections::HashSet::new();
+        for note in notes.iter_mut().filter_map(|n| n.as_object_mut()) {
+            let id = note.get("entry_id").and_then(|v| v.as_str()).map(String::from);
+            if !id.is_some_and(|id| seen.insert(id)) {
+                let id = uuid::Uuid::new_v4().to_string();
+                seen.insert(id.clone());
+                note.insert("entry_id".to_string(), serde_json::json!(id));
+            }
+
+            if let Some(relevance) = note.get_mut("relevance") {
+                let clamped = relevance.as_f64().map_or(1.0, |r| r.clamp(0.0, 1.0));
+                *relevance = serde_json::json!(clamped);
+            }
+        }
+    }
+
+    write_json_atomic(&memory_path, &data)?;
+
+    Ok(MemoryRepair { fixed, remaining })
+}
+
+// Add chrono and uuid to Cargo.toml
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::sync::{Mutex, MutexGuard};
+
+    /// The config root is process-wide, so tests that touch config take turns
+    static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Empty config root in a temp dir, in use until dropped
+    pub(crate) struct TempConfigRoot {
+        pub(crate) dir: tempfile::TempDir,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    pub(crate) fn temp_config_root() -> TempConfigRoot {
+        let lock = CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        super::use_config_root(dir.path().to_path_buf()).unwrap();
+        TempConfigRoot { dir, _lock: lock }
+    }
+
+    /// HTTP server on localhost answering one connection per response, each written
+    /// in the given parts; yields every raw request it received
+    pub(crate) async fn mock_backend(
+        responses: Vec<Vec<String>>,
+    ) -> (u16, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (port, serve(listener, responses))
+    }
+
+    /// `mock_backend` on an already bound listener
+    pub(crate) fn serve(
+        listener: tokio::net::TcpListener,
+        responses: Vec<Vec<String>>,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
+        use tokio::io::AsyncWriteExt;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for parts in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let _ = tx.send(read_request(&mut socket).await);
+                for part in parts {
+                    socket.write_all(part.as_bytes()).await.unwrap();
+                    socket.flush().await.unwrap();
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// Read one request, headers and `Content-Length` body
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+
+            let text = String::from_utf8_lossy(&data);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|l| {
+                        let l = l.to_ascii_lowercase();
+                        l.strip_prefix("content-length:")
+                            .map(|v| v.trim().parse().unwrap())
+                    })
+                    .unwrap_or(0);
+                if data.len() >= end + 4 + length {
+                    break;
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&data).into_owned()
+    }
+
+    /// Response head for a body that runs until the connection closes
+    pub(crate) fn response_head(status: &str, content_type: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nConnection: close\r\n\r\n",
+            status, content_type
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{mock_backend, response_head, temp_config_root};
+    use super::*;
+    use serde_json::json;
+
+    fn saved_config(root: &Path) -> serde_json::Value {
+        let content = std::fs::read_to_string(root.join("config.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn config_is_written_under_the_config_root() {
+        let root = temp_config_root();
+
+        set_config("ui.theme".to_string(), json!("dark"))
+            .await
+            .unwrap();
+
+        assert_eq!(saved_config(root.dir.path())["ui"]["theme"], json!("dark"));
+        assert_eq!(
+            get_config("ui.theme".to_string()).await.unwrap(),
+            Some(json!("dark"))
+        );
+    }
+
+    #[tokio::test]
+    async fn repointed_config_root_is_used_immediately() {
+        let root = temp_config_root();
+        set_config("ui.theme".to_string(), json!("dark"))
+            .await
+            .unwrap();
+
+        let other = tempfile::tempdir().unwrap();
+        use_config_root(other.path().to_path_buf()).unwrap();
+        assert_eq!(get_config("ui.theme".to_string()).await.unwrap(), None);
+        set_config("ui.theme".to_string(), json!("light"))
+            .await
+            .unwrap();
+        assert_eq!(saved_config(other.path())["ui"]["theme"], json!("light"));
+
+        use_config_root(root.dir.path().to_path_buf()).unwrap();
+        assert_eq!(
+            get_config("ui.theme".to_string()).await.unwrap(),
+            Some(json!("dark"))
+        );
+    }
+
+    #[tokio::test]
+    async fn half_written_temp_file_leaves_config_intact() {
+        let root = temp_config_root();
+        set_config("ui.theme".to_string(), json!("dark"))
+            .await
+            .unwrap();
+
+        // A crash mid-write leaves only the sibling temp file behind
+        let partial = root.dir.path().join(".config.json.crashed.tmp");
+        std::fs::write(&partial, r#"{"ui": {"theme": "li"#).unwrap();
+
+        assert_eq!(
+            get_config("ui.theme".to_string()).await.unwrap(),
+            Some(json!("dark"))
+        );
+        set_config("ui.font_size".to_string(), json!(14))
+            .await
+            .unwrap();
+        assert_eq!(saved_config(root.dir.path())["ui"]["theme"], json!("dark"));
+    }
+
+    #[test]
+    fn atomic_write_leaves_no_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recent_projects.json");
+
+        write_json_atomic(&path, &json!({ "a": 1 })).unwrap();
+        write_json_atomic(&path, &json!({ "a": 2 })).unwrap();
+
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(
+            names,
+            vec![std::ffi::OsString::from("recent_projects.json")]
+        );
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&content).unwrap()["a"],
+            2
+        );
+    }
+
+    #[test]
+    fn dotted_key_creates_intermediate_objects() {
+        let mut config = HashMap::new();
+        insert_config(&mut config, "ui.layout.sidebar", json!("left")).unwrap();
+
+        assert_eq!(config["ui"], json!({ "layout": { "sidebar": "left" } }));
+        assert_eq!(
+            lookup_config(&config, "ui.layout.sidebar"),
+            Some(&json!("left"))
+        );
+    }
+
+    #[test]
+    fn dotted_key_through_a_scalar_is_an_error() {
+        let mut config = HashMap::from([("ui".to_string(), json!("compact"))]);
+
+        let err = insert_config(&mut config, "ui.theme", json!("dark")).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+        assert_eq!(config["ui"], json!("compact"));
+    }
+
+    #[test]
+    fn missing_leaf_reads_as_none() {
+        let config = HashMap::from([("ui".to_string(), json!({ "theme": "dark" }))]);
+
+        assert_eq!(lookup_config(&config, "ui.font_size"), None);
+        assert_eq!(lookup_config(&config, "ui.theme.name"), None);
+        assert_eq!(lookup_config(&config, "editor.command"), None);
+    }
+
+    #[test]
+    fn flat_dotted_key_wins_over_nested_path() {
+        let mut config = HashMap::from([
+            ("ui.theme".to_string(), json!("flat")),
+            ("ui".to_string(), json!({ "theme": "nested" })),
+        ]);
+
+        assert_eq!(lookup_config(&config, "ui.theme"), Some(&json!("flat")));
+        insert_config(&mut config, "ui.theme", json!("dark")).unwrap();
+        assert_eq!(config["ui.theme"], json!("dark"));
+        assert_eq!(config["ui"]["theme"], json!("nested"));
+    }
+
+    #[tokio::test]
+    async fn set_config_refuses_to_replace_a_corrupt_file() {
+        let root = temp_config_root();
+        let path = root.dir.path().join("config.json");
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let err = set_config("ui.theme".to_string(), json!("dark"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "parse");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not json");
+    }
+
+    #[tokio::test]
+    async fn chat_stream_yields_each_chunk_as_it_arrives() {
+        let (port, _requests) = mock_backend(vec![vec![
+            response_head("200 OK", "text/event-stream"),
+            "data: {\"content\": \"Hel\"}\n\n".to_string(),
+            "data: {\"content\": \"lo, \"}\n\ndata: {\"con".to_string(),
+            "tent\": \"world\"}\n\ndata: [DONE]\n\n".to_string(),
+        ]])
+        .await;
+
+        let mut stream = reqwest::Client::new()
+            .post(format!("http://127.0.0.1:{}/chat", port))
+            .send()
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut parser = ChatStreamParser::default();
+        while let Some(bytes) = stream.chunk().await.unwrap() {
+            for event in parser.push(&bytes) {
+                tx.send(event).unwrap();
+            }
+        }
+        drop(tx);
+
+        let mut deltas = Vec::new();
+        let mut finished = false;
+        while let Some(event) = rx.recv().await {
+            if event.finished {
+                finished = true;
+            } else {
+                deltas.push(event.content);
+            }
+        }
+        assert_eq!(deltas, ["Hel", "lo, ", "world"]);
+        assert!(finished);
+        assert!(parser.finish().is_none());
+    }
+
+    #[tokio::test]
+    async fn cancel_signal_resolves_when_cancelled() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut cancel = CancelSignal(Some(rx));
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), cancel.cancelled())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancel_signal_stays_pending_after_sender_drops() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let mut cancel = CancelSignal(Some(rx));
+        drop(tx);
+
+        // Waiting again, as each `select!` of a loop does, must not poll the spent receiver
+        for _ in 0..3 {
+            let waited = tokio::time::timeout(Duration::from_millis(10), cancel.cancelled()).await;
+            assert!(waited.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn small_attachment_is_uploaded_as_base64() {
+        let _root = temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "hello").unwrap();
+        let (port, mut requests) = mock_backend(vec![vec![
+            response_head("200 OK", "application/json"),
+            r#"{"id": "att-1"}"#.to_string(),
+        ]])
+        .await;
+
+        let paths = vec![path.to_string_lossy().into_owned()];
+        let ids = upload_attachments(Route::Http(port), &paths).await.unwrap();
+
+        assert_eq!(ids, ["att-1"]);
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("POST /upload "));
+        assert!(request.contains(&STANDARD.encode("hello")));
+        assert!(request.contains(r#""name":"notes.txt""#));
+    }
+
+    #[tokio::test]
+    async fn oversized_attachment_is_rejected_by_name() {
+        let _root = temp_config_root();
+        set_config("attachments.max_total_bytes".to_string(), json!(8))
+            .await
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.txt");
+        let large = dir.path().join("large.bin");
+        std::fs::write(&small, "tiny").unwrap();
+        std::fs::write(&large, [0u8; 16]).unwrap();
+
+        let paths = vec![
+            small.to_string_lossy().into_owned(),
+            large.to_string_lossy().into_owned(),
+        ];
+        // Nothing listens on the port: validation must fail before any upload
+        let err = upload_attachments(Route::Http(1), &paths)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.code(), "invalid_input");
+        assert!(err.message().contains("large.bin"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn missing_attachment_is_not_found() {
+        let _root = temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("gone.png").to_string_lossy().into_owned();
+
+        let err = upload_attachments(Route::Http(1), &[missing])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "not_found");
+        assert!(err.message().contains("gone.png"));
+    }
+
+    /// A project dir plus a config root, since note adds read `memory.*` config
+    fn temp_project() -> (super::test_support::TempConfigRoot, tempfile::TempDir) {
+        (temp_config_root(), tempfile::tempdir().unwrap())
+    }
+
+    fn project_path(dir: &tempfile::TempDir) -> String {
+        dir.path().to_string_lossy().into_owned()
+    }
+
+    async fn add_note(project: &str, content: &str, category: &str, tags: &[&str]) -> MemoryNote {
+        let tags = tags.iter().map(|t| t.to_string()).collect();
+        add_memory_note(
+            project.to_string(),
+            content.to_string(),
+            category.to_string(),
+            tags,
+            None,
+        )
+        .await
+        .unwrap()
+        .note
+    }
+
+    fn stored_memory(project: &str) -> serde_json::Value {
+        let content = std::fs::read_to_string(memory_path(project)).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn deleting_an_unknown_note_reports_not_found() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        let missing = || delete_memory_note(project.clone(), "missing".to_string());
+        assert!(!missing().await.unwrap());
+
+        let kept = add_note(&project, "keep me", "note", &[]).await;
+        assert!(!missing().await.unwrap());
+        assert_eq!(
+            stored_memory(&project)["notes"][0]["entry_id"],
+            json!(kept.id)
+        );
+
+        assert!(delete_memory_note(project.clone(), kept.id).await.unwrap());
+        assert_eq!(stored_memory(&project)["notes"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn updating_one_note_leaves_the_others_alone() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        let first = add_note(&project, "first", "note", &["a"]).await;
+        let second = add_note(&project, "second", "note", &["b"]).await;
+        let before = stored_memory(&project);
+
+        let updated = update_memory_note(
+            project.clone(),
+            second.id.clone(),
+            "second, edited".to_string(),
+            "decision".to_string(),
+            vec!["c".to_string()],
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.content, "second, edited");
+        assert!(updated.updated_at.is_some());
+
+        let after = stored_memory(&project);
+        assert_eq!(after["version"], json!(MEMORY_VERSION));
+        assert_eq!(after["notes"][0], before["notes"][0]);
+        let note = &after["notes"][1];
+        assert_eq!(note["entry_id"], json!(second.id));
+        assert_eq!(note["category"], json!("decision"));
+        assert_eq!(note["tags"], json!(["c"]));
+        for kept in ["created_at", "source", "relevance"] {
+            assert_eq!(note[kept], before["notes"][1][kept], "{}", kept);
+        }
+        assert_eq!(first.content, "first");
+    }
+
+    fn write_memory(project: &str, notes: serde_json::Value) {
+        let path = memory_path(project);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let data = json!({ "version": MEMORY_VERSION, "notes": notes });
+        std::fs::write(path, data.to_string()).unwrap();
+    }
+
+    fn fixture_note(
+        id: &str,
+        content: &str,
+        category: &str,
+        tags: &[&str],
+        day: u32,
+    ) -> serde_json::Value {
+        json!({
+            "entry_id": id,
+            "content": content,
+            "category": category,
+            "tags": tags,
+            "source": "user",
+            "relevance": 1.0,
+            "created_at": format!("2024-01-{:02}T00:00:00Z", day),
+            "attachments": [],
+        })
+    }
+
+    /// Three notes created on Jan 1, 2 and 3
+    fn filter_fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        write_memory(
+            &project_path(&dir),
+            json!([
+                fixture_note(
+                    "a",
+                    "Use Tokio for the runtime",
+                    "decision",
+                    &["rust", "async"],
+                    1
+                ),
+                fixture_note("b", "tokio console is handy", "note", &["rust"], 2),
+                fixture_note("c", "Release every Friday", "decision", &["process"], 3),
+            ]),
+        );
+        dir
+    }
+
+    async fn memory_ids(
+        dir: &tempfile::TempDir,
+        query: Option<&str>,
+        category: Option<&str>,
+        tags: Option<&[&str]>,
+    ) -> Vec<String> {
+        let page = get_memory(
+            project_path(dir),
+            query.map(str::to_string),
+            category.map(str::to_string),
+            tags.map(|t| t.iter().map(|t| t.to_string()).collect()),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.total, page.notes.len());
+        page.notes.into_iter().map(|n| n.id).collect()
+    }
+
+    #[tokio::test]
+    async fn memory_without_filters_is_newest_first() {
+        let dir = filter_fixture();
+        assert_eq!(memory_ids(&dir, None, None, None).await, ["c", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn memory_query_ignores_case() {
+        let dir = filter_fixture();
+        assert_eq!(
+            memory_ids(&dir, Some("TOKIO"), None, None).await,
+            ["b", "a"]
+        );
+        assert!(memory_ids(&dir, Some("python"), None, None)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_category_matches_exactly() {
+        let dir = filter_fixture();
+        assert_eq!(
+            memory_ids(&dir, None, Some("decision"), None).await,
+            ["c", "a"]
+        );
+        assert!(memory_ids(&dir, None, Some("Decision"), None)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_tags_must_all_match() {
+        let dir = filter_fixture();
+        assert_eq!(
+            memory_ids(&dir, None, None, Some(&["rust"])).await,
+            ["b", "a"]
+        );
+        assert_eq!(
+            memory_ids(&dir, None, None, Some(&["rust", "async"])).await,
+            ["a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_filters_combine_with_and() {
+        let dir = filter_fixture();
+        let ids = memory_ids(&dir, Some("tokio"), Some("decision"), Some(&["rust"])).await;
+        assert_eq!(ids, ["a"]);
+        assert!(memory_ids(&dir, Some("friday"), Some("note"), None)
+            .await
+            .is_empty());
+    }
+
+    fn write_memory_fixture(dir: &tempfile::TempDir, fixture: &str) {
+        let path = memory_path(&project_path(dir));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, fixture).unwrap();
+    }
+
+    #[tokio::test]
+    async fn v1_memory_array_is_upgraded_without_losing_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        write_memory_fixture(&dir, include_str!("../tests/fixtures/memory_v1.json"));
+
+        let page = get_memory(project_path(&dir), None, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+
+        let stored = stored_memory(&project_path(&dir));
+        assert_eq!(stored["version"], json!(MEMORY_VERSION));
+        let notes = stored["notes"].as_array().unwrap();
+        assert_eq!(notes.len(), 3);
+
+        assert_eq!(notes[0]["entry_id"], json!("7"));
+        assert_eq!(notes[0]["content"], json!("Prefer rustls over openssl"));
+        assert_eq!(notes[0]["created_at"], json!("2023-05-01T09:00:00Z"));
+        assert_eq!(notes[0]["source"], json!("user"));
+        assert_eq!(notes[0]["relevance"], json!(1.0));
+        assert_eq!(notes[0]["author"], json!("sam"));
+        assert_eq!(notes[0]["attachments"], json!([]));
+        assert!(notes[0].get("id").is_none());
+
+        // Values v1 already had are kept rather than defaulted
+        assert_eq!(notes[1]["entry_id"], json!("legacy-2"));
+        assert_eq!(notes[1]["relevance"], json!(0.4));
+        assert_eq!(notes[1]["source"], json!("import"));
+
+        assert_eq!(notes[2]["content"], json!("Remember to bump the version"));
+        assert!(notes[2]["entry_id"]
+            .as_str()
+            .is_some_and(|id| !id.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn v1_memory_envelope_keeps_its_other_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        write_memory_fixture(
+            &dir,
+            include_str!("../tests/fixtures/memory_v1_envelope.json"),
+        );
+
+        get_memory(project_path(&dir), None, None, None, None, None, None)
+            .await
+            .unwrap();
+
+        let stored = stored_memory(&project_path(&dir));
+        assert_eq!(stored["version"], json!(MEMORY_VERSION));
+        assert_eq!(stored["project"], json!("roura"));
+        assert_eq!(stored["notes"][0]["entry_id"], json!("n1"));
+        assert_eq!(stored["notes"][0]["tags"], json!(["docs"]));
+
+        // Already upgraded, so a second read leaves the file as it is
+        let upgraded = std::fs::read_to_string(memory_path(&project_path(&dir))).unwrap();
+        get_memory(project_path(&dir), None, None, None, None, None, None)
+            .await
+            .unwrap();
+        let reread = std::fs::read_to_string(memory_path(&project_path(&dir))).unwrap();
+        assert_eq!(upgraded, reread);
+    }
+
+    fn recent_project(path: &Path, pinned: bool) -> serde_json::Value {
+        json!({
+            "name": path.file_name().unwrap().to_string_lossy(),
+            "path": path.to_string_lossy(),
+            "last_opened": null,
+            "pinned": pinned,
+            // Written by older builds; must not be trusted
+            "exists": true,
+        })
+    }
+
+    #[tokio::test]
+    async fn listing_marks_and_prunes_missing_projects() {
+        let root = temp_config_root();
+        let projects = tempfile::tempdir().unwrap();
+        let present = projects.path().join("present");
+        std::fs::create_dir(&present).unwrap();
+        let gone = projects.path().join("gone");
+        let gone_pinned = projects.path().join("gone-pinned");
+        let saved = json!([
+            recent_project(&present, false),
+            recent_project(&gone, false),
+            recent_project(&gone_pinned, true),
+        ]);
+        let projects_file = root.dir.path().join("recent_projects.json");
+        std::fs::write(&projects_file, saved.to_string()).unwrap();
+
+        let listed = list_projects(None).await.unwrap();
+        let state: Vec<_> = listed.iter().map(|p| (p.name.as_str(), p.exists)).collect();
+        assert_eq!(
+            state,
+            [("gone-pinned", false), ("present", true), ("gone", false)]
+        );
+
+        let pruned = list_projects(Some(true)).await.unwrap();
+        let names: Vec<_> = pruned.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["gone-pinned", "present"]);
+        assert!(!pruned[0].exists);
+
+        let rewritten = std::fs::read_to_string(&projects_file).unwrap();
+        let rewritten: serde_json::Value = serde_json::from_str(&rewritten).unwrap();
+        assert_eq!(rewritten.as_array().unwrap().len(), 2);
+        assert!(rewritten[0].get("exists").is_none());
+    }
+
+    fn write_recent_projects(root: &Path, projects: &[&Path]) {
+        let projects: Vec<_> = projects.iter().map(|p| recent_project(p, false)).collect();
+        std::fs::write(
+            root.join("recent_projects.json"),
+            serde_json::Value::Array(projects).to_string(),
+        )
+        .unwrap();
+    }
+
+    fn recent_paths() -> Vec<String> {
+        load_projects()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.path)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn settings_bundle_round_trips() {
+        let root = temp_config_root();
+        let projects = tempfile::tempdir().unwrap();
+        let (a, b) = (projects.path().join("a"), projects.path().join("b"));
+        std::fs::create_dir(&a).unwrap();
+        std::fs::create_dir(&b).unwrap();
+        set_config("ui.theme".to_string(), json!("dark"))
+            .await
+            .unwrap();
+        set_config("model".to_string(), json!("gpt")).await.unwrap();
+        write_recent_projects(root.dir.path(), &[&a, &b]);
+        let (config, paths) = (load_config().unwrap(), recent_paths());
+
+        let bundle = root.dir.path().join("settings.json");
+        export_config(bundle.to_string_lossy().into_owned())
+            .await
+            .unwrap();
+
+        let other = tempfile::tempdir().unwrap();
+        use_config_root(other.path().to_path_buf()).unwrap();
+        import_config(bundle.to_string_lossy().into_owned(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(load_config().unwrap(), config);
+        assert_eq!(recent_paths(), paths);
+    }
+
+    #[tokio::test]
+    async fn merged_bundle_keeps_local_values_it_lacks() {
+        let root = temp_config_root();
+        let projects = tempfile::tempdir().unwrap();
+        let (shared, local) = (
+            projects.path().join("shared"),
+            projects.path().join("local"),
+        );
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::create_dir(&local).unwrap();
+        set_config("ui.theme".to_string(), json!("dark"))
+            .await
+            .unwrap();
+        write_recent_projects(root.dir.path(), &[&shared]);
+        let bundle = root.dir.path().join("settings.json");
+        export_config(bundle.to_string_lossy().into_owned())
+            .await
+            .unwrap();
+
+        let other = tempfile::tempdir().unwrap();
+        use_config_root(other.path().to_path_buf()).unwrap();
+        set_config("ui.font_size".to_string(), json!(16))
+            .await
+            .unwrap();
+        write_recent_projects(other.path(), &[&local, &shared]);
+        import_config(bundle.to_string_lossy().into_owned(), true)
+            .await
+            .unwrap();
+
+        let config = load_config().unwrap();
+        assert_eq!(lookup_config(&config, "ui.theme"), Some(&json!("dark")));
+        assert_eq!(lookup_config(&config, "ui.font_size"), Some(&json!(16)));
+        let mut paths = recent_paths();
+        paths.sort();
+        let mut expected = vec![
+            local.to_string_lossy().into_owned(),
+            shared.to_string_lossy().into_owned(),
+        ];
+        expected.sort();
+        assert_eq!(paths, expected);
+    }
+
+    #[tokio::test]
+    async fn unknown_bundle_formats_are_rejected() {
+        let root = temp_config_root();
+        let bundle = root.dir.path().join("settings.json");
+        let src = bundle.to_string_lossy().into_owned();
+
+        std::fs::write(&bundle, r#"{"format": "something-else", "version": 1}"#).unwrap();
+        let err = import_config(src.clone(), false).await.unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+
+        let future =
+            json!({ "format": CONFIG_BUNDLE_FORMAT, "version": CONFIG_BUNDLE_VERSION + 1 });
+        std::fs::write(&bundle, future.to_string()).unwrap();
+        let err = import_config(src, false).await.unwrap_err();
+        assert!(err.message().contains("version"), "{}", err);
+        assert!(!root.dir.path().join("config.json").exists());
+    }
+
+    #[test]
+    fn short_replies_are_summarized_on_one_line() {
+        assert_eq!(
+            notification_summary("  Done.\n\nAll   tests pass.\n"),
+            "Done. All tests pass."
+        );
+        assert_eq!(notification_summary(""), "");
+    }
+
+    #[test]
+    fn long_replies_are_cut_with_an_ellipsis() {
+        let exact = "a".repeat(NOTIFICATION_SUMMARY_CHARS);
+        assert_eq!(notification_summary(&exact), exact);
+
+        let summary = notification_summary(&"a".repeat(NOTIFICATION_SUMMARY_CHARS + 1));
+        assert_eq!(summary.chars().count(), NOTIFICATION_SUMMARY_CHARS);
+        assert!(summary.ends_with('…'));
+    }
+
+    #[test]
+    fn summaries_cut_on_character_boundaries() {
+        let summary = notification_summary(&"é".repeat(NOTIFICATION_SUMMARY_CHARS * 2));
+        assert_eq!(summary.chars().count(), NOTIFICATION_SUMMARY_CHARS);
+        assert_eq!(
+            summary,
+            format!("{}…", "é".repeat(NOTIFICATION_SUMMARY_CHARS - 1))
+        );
+    }
+
+    async fn set_global(key: &str, value: serde_json::Value) {
+        set_config(key.to_string(), value).await.unwrap();
+    }
+
+    async fn effective(project: &str, key: &str) -> Option<serde_json::Value> {
+        get_effective_config(project.to_string(), key.to_string())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn project_config_overrides_global() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        set_global("model", json!("global-model")).await;
+        set_project_config(project.clone(), "model".to_string(), json!("project-model"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            effective(&project, "model").await,
+            Some(json!("project-model"))
+        );
+        let stored: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(project_config_path(&project)).unwrap())
+                .unwrap();
+        assert_eq!(stored, json!({ "model": "project-model" }));
+        assert_eq!(config_value("model"), Some(json!("global-model")));
+    }
+
+    #[tokio::test]
+    async fn unset_project_keys_fall_through_to_global() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        set_global("model", json!("global-model")).await;
+        set_global("temperature", json!(0.2)).await;
+        set_project_config(project.clone(), "temperature".to_string(), json!(0.9))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            effective(&project, "model").await,
+            Some(json!("global-model"))
+        );
+        assert_eq!(effective(&project, "temperature").await, Some(json!(0.9)));
+        assert_eq!(effective(&project, "ui.theme").await, None);
+
+        let mut message = AgentMessage {
+            content: "hi".to_string(),
+            attachments: None,
+            context: Some(HashMap::from([("model".to_string(), "chosen".to_string())])),
+            request_id: None,
+            include_memory: false,
+        };
+        apply_effective_settings(&mut message, Some(&project));
+        let context = message.context.unwrap();
+        assert_eq!(context["model"], "chosen");
+        assert_eq!(context["temperature"], "0.9");
+    }
+
+    #[tokio::test]
+    async fn absent_projects_use_the_global_config() {
+        let (_root, dir) = temp_project();
+        let missing = dir.path().join("gone").to_string_lossy().into_owned();
+        set_global("model", json!("global-model")).await;
+
+        assert_eq!(
+            effective(&missing, "model").await,
+            Some(json!("global-model"))
+        );
+        let err = set_project_config(missing.clone(), "model".to_string(), json!("x"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "not_found");
+        assert!(!Path::new(&missing).exists());
+    }
+
+    #[tokio::test]
+    async fn clearing_keeps_pinned_projects_only_when_asked() {
+        let root = temp_config_root();
+        let projects = tempfile::tempdir().unwrap();
+        let pinned = projects.path().join("pinned");
+        let recent = projects.path().join("recent");
+        let projects_file = root.dir.path().join("recent_projects.json");
+        let saved = json!([
+            recent_project(&pinned, true),
+            recent_project(&recent, false)
+        ]);
+
+        std::fs::write(&projects_file, saved.to_string()).unwrap();
+        let (removed, kept) = clear_saved_projects(true).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(recent_paths(), [pinned.to_string_lossy().into_owned()]);
+
+        std::fs::write(&projects_file, saved.to_string()).unwrap();
+        assert_eq!(clear_saved_projects(false).unwrap().0, 2);
+        assert_eq!(
+            std::fs::read_to_string(&projects_file).unwrap().trim(),
+            "[]"
+        );
+        assert_eq!(list_projects(None).await.unwrap().len(), 0);
+    }
+
+    #[test]
+    fn project_path_variants_canonicalize_alike() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("proj");
+        std::fs::create_dir(&project).unwrap();
+        let expected = canonical_project_path(&project.to_string_lossy());
+        let sep = std::path::MAIN_SEPARATOR;
+        let base = project.to_string_lossy().into_owned();
+
+        for variant in [
+            format!("{}{}", base, sep),
+            format!("{}{}.", base, sep),
+            format!("{}{}.{}..{}proj", base, sep, sep, sep),
+        ] {
+            assert_eq!(canonical_project_path(&variant), expected, "{}", variant);
+        }
+
+        // Unresolvable paths are still normalized
+        let missing =
+            Path::new(&canonical_project_path(&dir.path().to_string_lossy())).join("missing");
+        let dotted = format!("{}{}.{}x{}..", missing.to_string_lossy(), sep, sep, sep);
+        assert_eq!(
+            canonical_project_path(&dotted),
+            missing.to_string_lossy().into_owned()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_projects_resolve_to_their_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("proj");
+        std::fs::create_dir(&project).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&project, &link).unwrap();
+
+        assert_eq!(
+            canonical_project_path(&link.to_string_lossy()),
+            canonical_project_path(&project.to_string_lossy())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn saved_variants_collapse_on_load() {
+        let root = temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("proj");
+        std::fs::create_dir(&project).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&project, &link).unwrap();
+        let slashed = PathBuf::from(format!("{}/", project.to_string_lossy()));
+        let dotted = project.join(".");
+        let saved = json!([
+            recent_project(&slashed, false),
+            recent_project(&dotted, true),
+            recent_project(&link, false),
+        ]);
+        std::fs::write(
+            root.dir.path().join("recent_projects.json"),
+            saved.to_string(),
+        )
+        .unwrap();
+
+        let projects = load_projects().unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(
+            projects[0].path,
+            canonical_project_path(&project.to_string_lossy())
+        );
+    }
+
+    #[tokio::test]
+    async fn exact_duplicates_merge_tags_and_keep_the_earliest() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        write_memory(
+            &project,
+            json!([
+                fixture_note("a", "Use tabs", "note", &["style"], 3),
+                fixture_note("b", "Use tabs", "note", &["style", "editor"], 1),
+                fixture_note("c", "Ship on Fridays", "decision", &[], 2),
+            ]),
+        );
+
+        let report = compact_memory(project.clone(), None).await.unwrap();
+        assert_eq!((report.before, report.after, report.merged), (3, 2, 1));
+
+        let notes = stored_memory(&project)["notes"].clone();
+        assert_eq!(notes[0]["content"], json!("Use tabs"));
+        assert_eq!(notes[0]["tags"], json!(["style", "editor"]));
+        assert_eq!(notes[0]["created_at"], json!("2024-01-01T00:00:00Z"));
+        assert_eq!(notes[1]["entry_id"], json!("c"));
+    }
+
+    #[tokio::test]
+    async fn whitespace_and_case_differences_merge_only_when_similar() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        let notes = json!([
+            fixture_note("a", "Run  the tests\nbefore pushing", "note", &[], 1),
+            fixture_note("b", "run the tests before pushing ", "note", &[], 2),
+        ]);
+
+        write_memory(&project, notes.clone());
+        let report = compact_memory(project.clone(), None).await.unwrap();
+        assert_eq!(report.merged, 0);
+
+        write_memory(&project, notes);
+        let report = compact_memory(project.clone(), Some(1.0)).await.unwrap();
+        assert_eq!((report.after, report.merged), (1, 1));
+        assert_eq!(stored_memory(&project)["notes"][0]["entry_id"], json!("a"));
+    }
+
+    #[tokio::test]
+    async fn pinned_notes_never_merge_into_each_other() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        let mut first = fixture_note("a", "Keep", "note", &[], 1);
+        first["pinned"] = json!(true);
+        let mut second = fixture_note("b", "Keep", "note", &[], 2);
+        second["pinned"] = json!(true);
+        let loose = fixture_note("c", "Keep", "note", &[], 3);
+        write_memory(&project, json!([first, second, loose]));
+
+        let report = compact_memory(project.clone(), None).await.unwrap();
+        assert_eq!((report.after, report.merged), (2, 1));
+        let notes = stored_memory(&project)["notes"].clone();
+        let ids: Vec<_> = notes
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| &n["entry_id"])
+            .collect();
+        assert_eq!(ids, [&json!("a"), &json!("b")]);
+    }
+
+    #[tokio::test]
+    async fn memory_context_skips_notes_over_budget() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        let mut long = fixture_note("long", &"x".repeat(40), "note", &[], 1);
+        long["pinned"] = json!(true);
+        let mut first = fixture_note("first", "first note", "note", &[], 2);
+        first["relevance"] = json!(0.9);
+        let mut second = fixture_note("second", "second note", "note", &[], 3);
+        second["relevance"] = json!(0.5);
+        let mut third = fixture_note("third", "third note", "note", &[], 4);
+        third["relevance"] = json!(0.1);
+        write_memory(&project, json!([long, first, second, third]));
+        set_config("memory.context_max_chars".to_string(), json!(30))
+            .await
+            .unwrap();
+        set_config("memory.context_max_notes".to_string(), json!(2))
+            .await
+            .unwrap();
+
+        let context = memory_context(&project).unwrap().unwrap();
+        assert_eq!(context, "- first note\n- second note");
+        assert!(context.chars().count() <= 30);
+
+        let mut message = AgentMessage {
+            content: "hi".to_string(),
+            attachments: None,
+            context: None,
+            request_id: None,
+            include_memory: true,
+        };
+        apply_memory_context(&mut message, Some(&project));
+        assert_eq!(message.context.unwrap()["memory"], context);
+
+        set_config("memory.context_max_chars".to_string(), json!(5))
+            .await
+            .unwrap();
+        assert_eq!(memory_context(&project).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn one_request_at_a_time_by_default() {
+        let _root = temp_config_root();
+        assert_eq!(request_slots().unwrap().available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn excess_requests_queue_then_get_busy() {
+        let _root = temp_config_root();
+        set_config("backend.max_queued_requests".to_string(), json!(1))
+            .await
+            .unwrap();
+
+        let first = acquire_request_slot().await.unwrap();
+        let queued = tokio::spawn(acquire_request_slot());
+        while QUEUED_REQUESTS.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let err = acquire_request_slot().await.unwrap_err();
+        assert_eq!(err.code(), "busy");
+        assert!(!queued.is_finished());
+
+        drop(first);
+        let second = queued.await.unwrap().unwrap();
+        assert_eq!(QUEUED_REQUESTS.load(Ordering::SeqCst), 0);
+        drop(second);
+        assert_eq!(request_slots().unwrap().available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn raising_the_limit_takes_effect_immediately() {
+        let _root = temp_config_root();
+        let held = acquire_request_slot().await.unwrap();
+
+        set_config("backend.max_concurrent_requests".to_string(), json!(3))
+            .await
+            .unwrap();
+        let more = [
+            acquire_request_slot().await.unwrap(),
+            acquire_request_slot().await.unwrap(),
+        ];
+        assert_eq!(request_slots().unwrap().available_permits(), 1);
+
+        drop((held, more));
+        set_config("backend.max_concurrent_requests".to_string(), json!(1))
+            .await
+            .unwrap();
+        assert_eq!(request_slots().unwrap().available_permits(), 1);
+    }
+
+    #[test]
+    fn editor_strings_are_one_program() {
+        let command = json!("/Applications/Sublime Text.app/Contents/MacOS/sublime_text");
+        assert_eq!(
+            editor_args(Some(&command), "/work/my project").unwrap(),
+            [
+                "/Applications/Sublime Text.app/Contents/MacOS/sublime_text",
+                "/work/my project"
+            ]
+        );
+    }
+
+    #[test]
+    fn editor_lists_are_argv() {
+        let command = json!(["code", "-n", "--reuse-window"]);
+        assert_eq!(
+            editor_args(Some(&command), "/work/proj").unwrap(),
+            ["code", "-n", "--reuse-window", "/work/proj"]
+        );
+    }
+
+    #[test]
+    fn missing_editor_is_invalid_input() {
+        for command in [None, Some(json!("  ")), Some(json!([])), Some(json!(3))] {
+            let err = editor_args(command.as_ref(), "/work/proj").unwrap_err();
+            assert_eq!(err.code(), "invalid_input", "{:?}", command);
+        }
+        assert!(config_schema::validate("editor.command", &json!(["code", "-n"]), true).is_ok());
+        assert!(config_schema::validate("editor.command", &json!([1]), true).is_err());
+    }
+
+    #[test]
+    fn tool_calls_stream_start_progress_and_end() {
+        let stream = concat!(
+            "data: {\"content\": \"Checking\", \"tool_call\": {\"id\": \"t1\", ",
+            "\"name\": \"grep\", \"arguments\": {\"pattern\": \"todo\"}, ",
+            "\"status\": \"running\"}}\n\n",
+            "data: {\"tool_progress\": {\"id\": \"t1\", \"output\": \"a.rs:1\\n\"}}\n\n",
+            "data: {\"tool_progress\": {\"id\": \"t1\", \"output\": \"b.rs:2\\n\"}}\n\n",
+            "data: {\"tool_call\": {\"id\": \"t1\", \"name\": \"grep\", \"status\": \"done\"}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let mut response = AgentResponse {
+            content: String::new(),
+            tool_calls: None,
+            finished: false,
+            request_id: "r1".to_string(),
+            cancelled: false,
+        };
+
+        let mut parser = ChatStreamParser::default();
+        let mut names = Vec::new();
+        let mut progress = Vec::new();
+        // Split mid-event, as network chunks would be
+        for part in stream.as_bytes().chunks(7) {
+            for event in parser.push(part) {
+                for (name, payload) in fold_chat_event(&mut response, event) {
+                    if name == "tool-call-progress" {
+                        assert_eq!(payload["id"], json!("t1"));
+                        progress.push(payload["output"].as_str().unwrap().to_string());
+                    }
+                    if name != "agent-chunk" {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            names,
+            [
+                "tool-call-start",
+                "tool-call-progress",
+                "tool-call-progress",
+                "tool-call-end"
+            ]
+        );
+        assert_eq!(progress, ["a.rs:1\n", "b.rs:2\n"]);
+        assert!(response.finished);
+        assert_eq!(response.content, "Checking");
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].status, ToolCallStatus::Done);
+        assert_eq!(calls[0].result.as_deref(), Some("a.rs:1\nb.rs:2\n"));
+        assert_eq!(calls[0].arguments["pattern"], json!("todo"));
+    }
+
+    #[test]
+    fn unfinished_tool_calls_end_as_errors() {
+        let mut response = AgentResponse {
+            content: String::new(),
+            tool_calls: None,
+            finished: false,
+            request_id: "r1".to_string(),
+            cancelled: false,
+        };
+        let started = parse_stream_line(
+            r#"data: {"tool_call": {"id": "t1", "name": "build", "status": "running"}}"#,
+        )
+        .unwrap();
+        fold_chat_event(&mut response, started);
+
+        let events = fold_chat_event(&mut response, parse_stream_line("data: [DONE]").unwrap());
+        assert_eq!(events[0].0, "tool-call-end");
+        assert_eq!(events[0].1["tool_call"]["status"], json!("error"));
+        assert_eq!(
+            response.tool_calls.unwrap()[0].status,
+            ToolCallStatus::Error
+        );
+    }
+
+    /// A file outside the project to attach
+    fn outside_file(dir: &tempfile::TempDir, name: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, name).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn attachment_copies(project: &str) -> Vec<String> {
+        let dir = PathBuf::from(project)
+            .join(".roura")
+            .join(MEMORY_ATTACHMENTS_DIR);
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    async fn add_attached(project: &str, content: &str, attachments: Vec<String>) -> MemoryNote {
+        add_memory_note(
+            project.to_string(),
+            content.to_string(),
+            "note".to_string(),
+            Vec::new(),
+            Some(attachments),
+        )
+        .await
+        .unwrap()
+        .note
+    }
+
+    #[tokio::test]
+    async fn deleting_a_note_removes_its_copies_only() {
+        let (_root, dir) = temp_project();
+        let outside = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        let in_place = dir.path().join(".roura").join("notes.txt");
+        std::fs::create_dir_all(in_place.parent().unwrap()).unwrap();
+        std::fs::write(&in_place, "mine").unwrap();
+
+        let note = add_attached(
+            &project,
+            "with files",
+            vec![outside_file(&outside, "log.txt"), "notes.txt".to_string()],
+        )
+        .await;
+        assert_eq!(attachment_copies(&project).len(), 1);
+
+        assert!(delete_memory_note(project.clone(), note.id).await.unwrap());
+        assert!(attachment_copies(&project).is_empty());
+        assert!(in_place.exists());
+    }
+
+    #[tokio::test]
+    async fn replacing_attachments_removes_unreferenced_copies() {
+        let (_root, dir) = temp_project();
+        let outside = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        let note = add_attached(&project, "first", vec![outside_file(&outside, "a.txt")]).await;
+        let old_copy = attachment_copies(&project);
+
+        let updated = update_memory_note(
+            project.clone(),
+            note.id,
+            "first".to_string(),
+            "note".to_string(),
+            Vec::new(),
+            Some(vec![outside_file(&outside, "b.txt")]),
+        )
+        .await
+        .unwrap();
+
+        let copies = attachment_copies(&project);
+        assert_eq!(copies.len(), 1);
+        assert_ne!(copies, old_copy);
+        assert!(updated.attachments[0].ends_with("b.txt"));
+    }
+
+    #[tokio::test]
+    async fn evicted_notes_release_their_copies() {
+        let (_root, dir) = temp_project();
+        let outside = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        add_attached(&project, "old", vec![outside_file(&outside, "old.txt")]).await;
+        let size = std::fs::metadata(memory_path(&project)).unwrap().len();
+        set_config("memory.max_bytes".to_string(), json!(size + 50))
+            .await
+            .unwrap();
+        set_config("memory.evict_over_budget".to_string(), json!(true))
+            .await
+            .unwrap();
+
+        let added = add_memory_note(
+            project.clone(),
+            "new".to_string(),
+            "note".to_string(),
+            Vec::new(),
+            Some(vec![outside_file(&outside, "new.txt")]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(added.evicted, 1);
+        let copies = attachment_copies(&project);
+        assert_eq!(copies.len(), 1);
+        assert!(copies[0].ends_with("new.txt"));
+    }
+
+    #[tokio::test]
+    async fn failed_adds_leave_no_copies() {
+        let (_root, dir) = temp_project();
+        let outside = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        let missing = outside
+            .path()
+            .join("missing.txt")
+            .to_string_lossy()
+            .into_owned();
+
+        let notes = vec![
+            NewNote {
+                content: "one".to_string(),
+                category: "note".to_string(),
+                tags: Vec::new(),
+                attachments: vec![outside_file(&outside, "one.txt")],
+            },
+            NewNote {
+                content: "two".to_string(),
+                category: "note".to_string(),
+                tags: Vec::new(),
+                attachments: vec![outside_file(&outside, "two.txt"), missing],
+            },
+        ];
+        let err = add_memory_notes(project.clone(), notes, false)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.code(), "not_found");
+        assert!(attachment_copies(&project).is_empty());
+        assert!(!memory_path(&project).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn relative_attachments_cannot_escape_through_symlinks() {
+        let (_root, dir) = temp_project();
+        let outside = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        let roura = dir.path().join(".roura");
+        std::fs::create_dir_all(&roura).unwrap();
+        std::os::unix::fs::symlink(outside_file(&outside, "secret.txt"), roura.join("link"))
+            .unwrap();
+
+        for path in ["link", "../outside.txt", "missing.txt"] {
+            let err = add_memory_note(
+                project.clone(),
+                "escape".to_string(),
+                "note".to_string(),
+                Vec::new(),
+                Some(vec![path.to_string()]),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.code(), "invalid_input", "{}", path);
+        }
+    }
+
+    fn budget_fixture() -> serde_json::Value {
+        json!({
+            "version": MEMORY_VERSION,
+            "notes": [
+                fixture_note("low", "least relevant", "note", &[], 2),
+                fixture_note("old", "oldest", "note", &[], 1),
+                fixture_note("new", "just added", "note", &[], 3),
+            ],
+        })
+    }
+
+    #[tokio::test]
+    async fn memory_exactly_at_the_budget_is_allowed() {
+        let _root = temp_config_root();
+        let mut data = budget_fixture();
+        let size = memory_size(&data).unwrap();
+
+        set_config("memory.max_bytes".to_string(), json!(size))
+            .await
+            .unwrap();
+        assert!(enforce_memory_budget(&mut data, 1).unwrap().is_empty());
+
+        set_config("memory.max_bytes".to_string(), json!(size - 1))
+            .await
+            .unwrap();
+        let err = enforce_memory_budget(&mut data, 1).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+        assert_eq!(notes_mut(&mut data).unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn one_byte_over_evicts_one_note() {
+        let _root = temp_config_root();
+        let mut data = budget_fixture();
+        notes_mut(&mut data).unwrap()[0]["relevance"] = json!(0.2);
+        let size = memory_size(&data).unwrap();
+        set_config("memory.max_bytes".to_string(), json!(size - 1))
+            .await
+            .unwrap();
+        set_config("memory.evict_over_budget".to_string(), json!(true))
+            .await
+            .unwrap();
+
+        let evicted = enforce_memory_budget(&mut data, 1).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(note_id(&evicted[0]), Some("low"));
+        let left: Vec<_> = notes_mut(&mut data)
+            .unwrap()
+            .iter()
+            .map(|n| note_id(n))
+            .collect();
+        assert_eq!(left, [Some("old"), Some("new")]);
+
+        // Equally relevant notes go oldest first; the added note is never evicted
+        let mut data = budget_fixture();
+        let evicted = enforce_memory_budget(&mut data, 1).unwrap();
+        assert_eq!(note_id(&evicted[0]), Some("old"));
+    }
+
+    #[tokio::test]
+    async fn over_budget_adds_leave_memory_and_copies_untouched() {
+        let (_root, dir) = temp_project();
+        let outside = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        add_note(&project, "existing", "note", &[]).await;
+        let before = std::fs::read_to_string(memory_path(&project)).unwrap();
+        set_config("memory.max_bytes".to_string(), json!(before.len()))
+            .await
+            .unwrap();
+
+        let err = add_memory_note(
+            project.clone(),
+            "too much".to_string(),
+            "note".to_string(),
+            Vec::new(),
+            Some(vec![outside_file(&outside, "big.txt")]),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.code(), "invalid_input");
+        assert_eq!(
+            std::fs::read_to_string(memory_path(&project)).unwrap(),
+            before
+        );
+        assert!(attachment_copies(&project).is_empty());
+    }
+
+    #[tokio::test]
+    async fn categories_are_counted_largest_first() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        write_memory(
+            &project,
+            json!([
+                fixture_note("a", "one", "todo", &[], 1),
+                fixture_note("b", "two", "decision", &[], 2),
+                fixture_note("c", "three", "todo", &[], 3),
+                fixture_note("d", "four", "bug", &[], 4),
+                fixture_note("e", "five", "todo", &[], 5),
+                fixture_note("f", "six", "decision", &[], 6),
+            ]),
+        );
+
+        let stats = get_memory_categories(project).await.unwrap();
+        let counts: Vec<_> = stats
+            .categories
+            .iter()
+            .map(|c| (c.category.as_str(), c.count))
+            .collect();
+        assert_eq!(counts, [("todo", 3), ("decision", 2), ("bug", 1)]);
+        assert_eq!(stats.total, 6);
+    }
+
+    #[tokio::test]
+    async fn category_ties_stay_alphabetical() {
+        let (_root, dir) = temp_project();
+        let project = project_path(&dir);
+        write_memory(
+            &project,
+            json!([
+                fixture_note("a", "one", "todo", &[], 1),
+                fixture_note("b", "two", "bug", &[], 2),
+                fixture_note("c", "three", "decision", &[], 3),
+            ]),
+        );
+
+        let stats = get_memory_categories(project).await.unwrap();
+        let names: Vec<_> = stats
+            .categories
+            .iter()
+            .map(|c| c.category.as_str())
+            .collect();
+        assert_eq!(names, ["bug", "decision", "todo"]);
+    }
+
+    /// Issues found in a fixture, as (kind, index, field, fixable)
+    async fn fixture_issues(
+        fixture: &str,
+    ) -> Vec<(MemoryIssueKind, Option<usize>, Option<String>, bool)> {
+        let dir = tempfile::tempdir().unwrap();
+        write_memory_fixture(&dir, fixture);
+        validate_memory(project_path(&dir))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|i| (i.kind, i.index, i.field, i.fixable))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn each_fixture_reports_its_issue() {
+        use MemoryIssueKind::*;
+        let cases = [
+            (
+                include_str!("../tests/fixtures/memory_issues/invalid_document.json"),
+                (InvalidDocument, None, None, false),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/legacy_layout.json"),
+                (LegacyLayout, None, None, true),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/missing_notes.json"),
+                (MissingNotes, None, None, true),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/invalid_note.json"),
+                (InvalidNote, Some(1), None, false),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/duplicate_id.json"),
+                (DuplicateId, Some(1), None, true),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/missing_field.json"),
+                (MissingField, Some(0), Some("entry_id".to_string()), true),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/invalid_timestamp.json"),
+                (
+                    InvalidTimestamp,
+                    Some(0),
+                    Some("updated_at".to_string()),
+                    false,
+                ),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/relevance_out_of_range.json"),
+                (RelevanceOutOfRange, Some(0), None, true),
+            ),
+            (
+                include_str!("../tests/fixtures/memory_issues/unknown_key.json"),
+                (UnknownKey, None, Some("owner".to_string()), false),
+            ),
+        ];
+
+        for (fixture, expected) in cases {
+            let kind = expected.0;
+            assert_eq!(fixture_issues(fixture).await, [expected], "{:?}", kind);
+        }
+    }
+
+    #[tokio::test]
+    async fn unrecoverable_shapes_are_not_fixable() {
+        let issues = fixture_issues(r#"{ "version": 3, "notes": 5 }"#).await;
+        assert_eq!(issues, [(MemoryIssueKind::MissingNotes, None, None, false)]);
+
+        let issues = fixture_issues("42").await;
+        assert_eq!(
+            issues,
+            [(MemoryIssueKind::InvalidDocument, None, None, false)]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        write_memory_fixture(
+            &dir,
+            include_str!("../tests/fixtures/memory_issues/invalid_document.json"),
+        );
+        let err = repair_memory(project_path(&dir)).await.unwrap_err();
+        assert_eq!(err.code(), "parse");
+    }
+
+    #[tokio::test]
+    async fn repair_fixes_only_fixable_issues() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project_path(&dir);
+        write_memory(
+            &project,
+            json!([
+                { "entry_id": "a", "content": "one", "created_at": "2026-01-01T09:00:00Z" },
+                { "entry_id": "a", "content": "two", "created_at": "2026-01-01T09:00:00Z",
+                  "relevance": -2 },
+                { "content": "three", "created_at": "2026-01-01T09:00:00Z", "relevance": "high" },
+                { "entry_id": "d", "created_at": "2026-01-01T09:00:00Z" },
+            ]),
+        );
+
+        let repair = repair_memory(project.clone()).await.unwrap();
+        let fixed: Vec<_> = repair.fixed.iter().map(|i| (i.kind, i.index)).collect();
+        assert_eq!(
+            fixed,
+            [
+                (MemoryIssueKind::DuplicateId, Some(1)),
+                (MemoryIssueKind::RelevanceOutOfRange, Some(1)),
+                (MemoryIssueKind::MissingField, Some(2)),
+                (MemoryIssueKind::RelevanceOutOfRange, Some(2)),
+            ]
+        );
+        let remaining: Vec<_> = repair.remaining.iter().map(|i| (i.kind, i.index)).collect();
+        assert_eq!(remaining, [(MemoryIssueKind::MissingField, Some(3))]);
+
+        let notes = stored_memory(&project)["notes"].as_array().unwrap().clone();
+        let ids: std::collections::HashSet<_> = notes
+            .iter()
+            .map(|n| n["entry_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(notes[0]["entry_id"], json!("a"));
+        assert_eq!(notes[1]["relevance"], json!(0.0));
+        assert_eq!(notes[2]["relevance"], json!(1.0));
+
+        let left = fixture_issues(&std::fs::read_to_string(memory_path(&project)).unwrap()).await;
+        assert_eq!(
+            left,
+            [(
+                MemoryIssueKind::MissingField,
+                Some(3),
+                Some("content".to_string()),
+                false
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn clean_memory_is_not_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = include_str!("../tests/fixtures/memory_issues/invalid_timestamp.json");
+        write_memory_fixture(&dir, fixture);
+
+        let repair = repair_memory(project_path(&dir)).await.unwrap();
+        assert!(repair.fixed.is_empty());
+        assert_eq!(repair.remaining.len(), 1);
+        let stored = std::fs::read_to_string(memory_path(&project_path(&dir))).unwrap();
+        assert_eq!(stored, fixture);
+    }
+
+    fn config_map(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn flat_keys_are_nested_without_touching_others() {
+        let mut config = config_map(json!({
+            "ui.theme": "dark",
+            "ui": { "theme": "light", "font_size": 12 },
+            "backend.port": 9000,
+            "name": "work",
+            "shortcut.capture": "CmdOrCtrl+Shift+S",
+            "shortcut": "off",
+        }));
+
+        nest_flat_config_keys(&mut config).unwrap();
+
+        assert_eq!(
+            config,
+            config_map(json!({
+                "ui": { "theme": "dark", "font_size": 12 },
+                "backend": { "port": 9000 },
+                "name": "work",
+                "shortcut.capture": "CmdOrCtrl+Shift+S",
+                "shortcut": "off",
+            }))
+        );
+
+        // Running it again changes nothing
+        let before = config.clone();
+        nest_flat_config_keys(&mut config).unwrap();
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn old_configs_are_upgraded_keeping_unrelated_keys() {
+        let root = temp_config_root();
+        let path = root.dir.path().join("config.json");
+        let old = json!({ "ui.theme": "dark", "recent_limit": 5, "custom": { "a": [1, 2] } });
+        std::fs::write(&path, old.to_string()).unwrap();
+
+        migrate_config().unwrap();
+
+        let stored: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            stored,
+            json!({
+                "config_version": CONFIG_VERSION,
+                "ui": { "theme": "dark" },
+                "recent_limit": 5,
+                "custom": { "a": [1, 2] },
+            })
+        );
+    }
+
+    #[test]
+    fn current_and_newer_configs_are_left_alone() {
+        let root = temp_config_root();
+        let path = root.dir.path().join("config.json");
+
+        for version in [CONFIG_VERSION, CONFIG_VERSION + 1] {
+            let config = json!({ "config_version": version, "ui.theme": "dark" }).to_string();
+            std::fs::write(&path, &config).unwrap();
+
+            migrate_config().unwrap();
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), config);
+        }
+    }
+
+    /// Register a fake in-flight request, returning the receiver its cancel reaches
+    fn fake_request(id: &str) -> tokio::sync::oneshot::Receiver<()> {
+        let (cancel, receiver) = tokio::sync::oneshot::channel();
+        ACTIVE_REQUESTS.lock().unwrap().insert(
+            id.to_string(),
+            ActiveRequest {
+                cancel,
+                started_at: chrono::Utc::now().to_rfc3339(),
+                project: None,
+            },
+        );
+        receiver
+    }
+
+    #[tokio::test]
+    async fn cancel_all_signals_every_request() {
+        // Requests in flight share process-wide state with the chat tests
+        let _root = temp_config_root();
+        let receivers: Vec<_> = ["a", "b", "c"].into_iter().map(fake_request).collect();
+        // One that finished just before the cancel has dropped its receiver
+        drop(fake_request("d"));
+
+        assert_eq!(cancel_all_requests().await.unwrap(), 4);
+        for receiver in receivers {
+            assert_eq!(
+                tokio::time::timeout(Duration::from_secs(1), receiver)
+                    .await
+                    .unwrap(),
+                Ok(())
+            );
+        }
+        assert!(list_active_requests().await.unwrap().is_empty());
+        assert_eq!(cancel_all_requests().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn allowed_roots_admit_only_paths_inside_them() {
+        let _root = temp_config_root();
+        let base = tempfile::tempdir().unwrap();
+        let base_path = canonical_project_path(&base.path().to_string_lossy());
+        for dir in ["app/src", "app2", "other"] {
+            std::fs::create_dir_all(base.path().join(dir)).unwrap();
+        }
+        let app = format!("{}/app", base_path);
+        let check = |p: &str| ensure_allowed_project(&canonical_project_path(p));
+
+        // Unset allows everything
+        assert!(check(&format!("{}/other", base_path)).is_ok());
+
+        set_config("projects.allowed_roots".to_string(), json!([app]))
+            .await
+            .unwrap();
+        assert!(check(&app).is_ok());
+        assert!(check(&format!("{}/src", app)).is_ok());
+        assert!(check(&format!("{}/src/..", app)).is_ok());
+
+        let forbidden = |p: String| check(&p).unwrap_err().code();
+        assert_eq!(forbidden(format!("{}/src/../../other", app)), "forbidden");
+        assert_eq!(forbidden(format!("{}/other", base_path)), "forbidden");
+        // Shares a string prefix with the root but is a sibling
+        assert_eq!(forbidden(format!("{}/app2", base_path)), "forbidden");
+
+        #[cfg(unix)]
+        {
+            let link = format!("{}/src/escape", app);
+            std::os::unix::fs::symlink(base.path().join("other"), &link).unwrap();
+            assert_eq!(forbidden(link), "forbidden");
+        }
+
+        set_config("projects.allowed_roots".to_string(), json!([]))
+            .await
+            .unwrap();
+        assert_eq!(forbidden(app), "forbidden");
+    }
+
+    #[tokio::test]
+    async fn reveal_refuses_paths_outside_the_allowed_roots() {
+        let _root = temp_config_root();
+        let allowed = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        set_config(
+            "projects.allowed_roots".to_string(),
+            json!([allowed.path().to_string_lossy()]),
+        )
+        .await
+        .unwrap();
+
+        let err = reveal_project(
+            outside.path().to_string_lossy().into_owned(),
+            RevealTarget::Editor,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), "forbidden");
+    }
+
+    #[tokio::test]
+    async fn git_refresh_refuses_paths_outside_the_allowed_roots() {
+        let _root = temp_config_root();
+        let allowed = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        set_config(
+            "projects.allowed_roots".to_string(),
+            json!([allowed.path().to_string_lossy()]),
+        )
+        .await
+        .unwrap();
+
+        let err = refresh_project_git(outside.path().to_string_lossy().into_owned())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "forbidden");
+    }
+}
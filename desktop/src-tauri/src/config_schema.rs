@@ -0,0 +1,370 @@
+// Roura Agent Desktop - Config Schema
+// © Roura.io
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Expected shape of a config value
+#[derive(Debug, Clone, Copy)]
+enum ValueKind {
+    Bool,
+    Integer { min: i64, max: i64 },
+    Number { min: f64, max: f64 },
+    String,
+    StringList,
+    /// A program name, or a program and its arguments as a list of strings
+    Command,
+    OneOf(&'static [&'static str]),
+    /// Any object; keys below it aren't checked
+    Object,
+}
+
+/// Config keys known to the desktop app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigKey {
+    Model,
+    Temperature,
+    UiTheme,
+    UiFontSize,
+    BackendRestartMaxAttempts,
+    BackendRestartInitialDelayMs,
+    BackendRestartMaxDelayMs,
+    ConfigStrict,
+    AttachmentsMaxTotalBytes,
+    ConversationMaxTurns,
+    MinimizeToTray,
+    ShortcutsCaptureRegion,
+    WindowX,
+    WindowY,
+    WindowWidth,
+    WindowHeight,
+    WindowMaximized,
+    WindowFullscreen,
+    DragdropAllowedExtensions,
+    DragdropMaxFiles,
+    BackendPythonPath,
+    BackendHttpRetries,
+    BackendHttpRetryBaseDelayMs,
+    NotifyOnComplete,
+    BackendIdleShutdownMinutes,
+    MemoryContextMaxNotes,
+    MemoryContextMaxChars,
+    BackendExternal,
+    BackendPort,
+    BackendMaxConcurrentRequests,
+    BackendMaxQueuedRequests,
+    EditorCommand,
+    ScreenshotsTempMaxAgeMinutes,
+    LogLevel,
+    BackendCommand,
+    BackendExtraArgs,
+    UiHighContrast,
+    Themes,
+    MemoryMaxBytes,
+    MemoryEvictOverBudget,
+    ConfigVersion,
+    ProjectsAllowedRoots,
+}
+
+impl ConfigKey {
+    const ALL: &'static [ConfigKey] = &[
+        ConfigKey::Model,
+        ConfigKey::Temperature,
+        ConfigKey::UiTheme,
+        ConfigKey::UiFontSize,
+        ConfigKey::BackendRestartMaxAttempts,
+        ConfigKey::BackendRestartInitialDelayMs,
+        ConfigKey::BackendRestartMaxDelayMs,
+        ConfigKey::ConfigStrict,
+        ConfigKey::AttachmentsMaxTotalBytes,
+        ConfigKey::ConversationMaxTurns,
+        ConfigKey::MinimizeToTray,
+        ConfigKey::ShortcutsCaptureRegion,
+        ConfigKey::WindowX,
+        ConfigKey::WindowY,
+        ConfigKey::WindowWidth,
+        ConfigKey::WindowHeight,
+        ConfigKey::WindowMaximized,
+        ConfigKey::WindowFullscreen,
+        ConfigKey::DragdropAllowedExtensions,
+        ConfigKey::DragdropMaxFiles,
+        ConfigKey::BackendPythonPath,
+        ConfigKey::BackendHttpRetries,
+        ConfigKey::BackendHttpRetryBaseDelayMs,
+        ConfigKey::NotifyOnComplete,
+        ConfigKey::BackendIdleShutdownMinutes,
+        ConfigKey::MemoryContextMaxNotes,
+        ConfigKey::MemoryContextMaxChars,
+        ConfigKey::BackendExternal,
+        ConfigKey::BackendPort,
+        ConfigKey::BackendMaxConcurrentRequests,
+        ConfigKey::BackendMaxQueuedRequests,
+        ConfigKey::EditorCommand,
+        ConfigKey::ScreenshotsTempMaxAgeMinutes,
+        ConfigKey::LogLevel,
+        ConfigKey::BackendCommand,
+        ConfigKey::BackendExtraArgs,
+        ConfigKey::UiHighContrast,
+        ConfigKey::Themes,
+        ConfigKey::MemoryMaxBytes,
+        ConfigKey::MemoryEvictOverBudget,
+        ConfigKey::ConfigVersion,
+        ConfigKey::ProjectsAllowedRoots,
+    ];
+
+    /// Dotted config path for this key
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigKey::Model => "model",
+            ConfigKey::Temperature => "temperature",
+            ConfigKey::UiTheme => "ui.theme",
+            ConfigKey::UiFontSize => "ui.font_size",
+            ConfigKey::BackendRestartMaxAttempts => "backend.restart_max_attempts",
+            ConfigKey::BackendRestartInitialDelayMs => "backend.restart_initial_delay_ms",
+            ConfigKey::BackendRestartMaxDelayMs => "backend.restart_max_delay_ms",
+            ConfigKey::ConfigStrict => "config.strict",
+            ConfigKey::AttachmentsMaxTotalBytes => "attachments.max_total_bytes",
+            ConfigKey::ConversationMaxTurns => "conversation.max_turns",
+            ConfigKey::MinimizeToTray => "minimize_to_tray",
+            ConfigKey::ShortcutsCaptureRegion => "shortcuts.capture_region",
+            ConfigKey::WindowX => "window.x",
+            ConfigKey::WindowY => "window.y",
+            ConfigKey::WindowWidth => "window.width",
+            ConfigKey::WindowHeight => "window.height",
+            ConfigKey::WindowMaximized => "window.maximized",
+            ConfigKey::WindowFullscreen => "window.fullscreen",
+            ConfigKey::DragdropAllowedExtensions => "dragdrop.allowed_extensions",
+            ConfigKey::DragdropMaxFiles => "dragdrop.max_files",
+            ConfigKey::BackendPythonPath => "backend.python_path",
+            ConfigKey::BackendHttpRetries => "backend.http_retries",
+            ConfigKey::BackendHttpRetryBaseDelayMs => "backend.http_retry_base_delay_ms",
+            ConfigKey::NotifyOnComplete => "notify_on_complete",
+            ConfigKey::BackendIdleShutdownMinutes => "backend.idle_shutdown_minutes",
+            ConfigKey::MemoryContextMaxNotes => "memory.context_max_notes",
+            ConfigKey::MemoryContextMaxChars => "memory.context_max_chars",
+            ConfigKey::BackendExternal => "backend.external",
+            ConfigKey::BackendPort => "backend.port",
+            ConfigKey::BackendMaxConcurrentRequests => "backend.max_concurrent_requests",
+            ConfigKey::BackendMaxQueuedRequests => "backend.max_queued_requests",
+            ConfigKey::EditorCommand => "editor.command",
+            ConfigKey::ScreenshotsTempMaxAgeMinutes => "screenshots.temp_max_age_minutes",
+            ConfigKey::LogLevel => "log.level",
+            ConfigKey::BackendCommand => "backend.command",
+            ConfigKey::BackendExtraArgs => "backend.extra_args",
+            ConfigKey::UiHighContrast => "ui.high_contrast",
+            ConfigKey::Themes => "themes",
+            ConfigKey::MemoryMaxBytes => "memory.max_bytes",
+            ConfigKey::MemoryEvictOverBudget => "memory.evict_over_budget",
+            ConfigKey::ConfigVersion => "config_version",
+            ConfigKey::ProjectsAllowedRoots => "projects.allowed_roots",
+        }
+    }
+
+    fn kind(&self) -> ValueKind {
+        match self {
+            ConfigKey::Model => ValueKind::String,
+            ConfigKey::Temperature => ValueKind::Number { min: 0.0, max: 2.0 },
+            ConfigKey::UiTheme => ValueKind::OneOf(&["light", "dark", "system"]),
+            ConfigKey::UiFontSize => ValueKind::Integer { min: 8, max: 48 },
+            ConfigKey::BackendRestartMaxAttempts => ValueKind::Integer { min: 0, max: 100 },
+            ConfigKey::BackendRestartInitialDelayMs | ConfigKey::BackendRestartMaxDelayMs => {
+                ValueKind::Integer {
+                    min: 0,
+                    max: 3_600_000,
+                }
+            }
+            ConfigKey::ConfigStrict => ValueKind::Bool,
+            ConfigKey::AttachmentsMaxTotalBytes => ValueKind::Integer {
+                min: 0,
+                max: i64::MAX,
+            },
+            ConfigKey::ConversationMaxTurns => ValueKind::Integer { min: 1, max: 100_000 },
+            ConfigKey::MinimizeToTray => ValueKind::Bool,
+            ConfigKey::ShortcutsCaptureRegion => ValueKind::String,
+            ConfigKey::WindowX | ConfigKey::WindowY => ValueKind::Integer {
+                min: i32::MIN as i64,
+                max: i32::MAX as i64,
+            },
+            ConfigKey::WindowWidth | ConfigKey::WindowHeight => ValueKind::Integer {
+                min: 1,
+                max: u32::MAX as i64,
+            },
+            ConfigKey::WindowMaximized | ConfigKey::WindowFullscreen => ValueKind::Bool,
+            ConfigKey::DragdropAllowedExtensions => ValueKind::StringList,
+            ConfigKey::DragdropMaxFiles => ValueKind::Integer { min: 1, max: 10_000 },
+            ConfigKey::BackendPythonPath => ValueKind::String,
+            ConfigKey::BackendHttpRetries => ValueKind::Integer { min: 0, max: 10 },
+            ConfigKey::BackendHttpRetryBaseDelayMs => ValueKind::Integer { min: 0, max: 60_000 },
+            ConfigKey::NotifyOnComplete => ValueKind::Bool,
+            ConfigKey::BackendIdleShutdownMinutes => ValueKind::Integer { min: 0, max: 10_080 },
+            ConfigKey::MemoryContextMaxNotes => ValueKind::Integer { min: 0, max: 1_000 },
+            ConfigKey::MemoryContextMaxChars => ValueKind::Integer { min: 0, max: 1_000_000 },
+            ConfigKey::BackendExternal => ValueKind::Bool,
+            ConfigKey::BackendPort => ValueKind::Integer { min: 1, max: 65535 },
+            ConfigKey::BackendMaxConcurrentRequests => ValueKind::Integer { min: 1, max: 64 },
+            ConfigKey::BackendMaxQueuedRequests => ValueKind::Integer { min: 0, max: 1000 },
+            ConfigKey::EditorCommand => ValueKind::Command,
+            ConfigKey::ScreenshotsTempMaxAgeMinutes => ValueKind::Integer { min: 1, max: 525_600 },
+            ConfigKey::LogLevel => {
+                ValueKind::OneOf(&["trace", "debug", "info", "warn", "error", "off"])
+            }
+            ConfigKey::BackendCommand => ValueKind::StringList,
+            ConfigKey::BackendExtraArgs => ValueKind::StringList,
+            ConfigKey::UiHighContrast => ValueKind::Bool,
+            ConfigKey::Themes => ValueKind::Object,
+            ConfigKey::MemoryMaxBytes => ValueKind::Integer {
+                min: 1,
+                max: i64::MAX,
+            },
+            ConfigKey::MemoryEvictOverBudget => ValueKind::Bool,
+            ConfigKey::ConfigVersion => ValueKind::Integer { min: 0, max: 1_000 },
+            ConfigKey::ProjectsAllowedRoots => ValueKind::StringList,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| k.name() == name)
+    }
+
+    /// Check a value against this key's expected type and range
+    fn check(&self, value: &Value) -> Result<(), String> {
+        let key = self.name();
+
+        match self.kind() {
+            ValueKind::Bool => {
+                if !value.is_boolean() {
+                    return Err(format!("{} must be a boolean", key));
+                }
+            }
+            ValueKind::Integer { min, max } => {
+                let n = value
+                    .as_i64()
+                    .ok_or_else(|| format!("{} must be an integer", key))?;
+                if n < min || n > max {
+                    return Err(format!("{} must be between {} and {}", key, min, max));
+                }
+            }
+            ValueKind::Number { min, max } => {
+                let n = value
+                    .as_f64()
+                    .ok_or_else(|| format!("{} must be a number", key))?;
+                if n < min || n > max {
+                    return Err(format!("{} must be between {} and {}", key, min, max));
+                }
+            }
+            ValueKind::String => {
+                if !value.is_string() {
+                    return Err(format!("{} must be a string", key));
+                }
+            }
+            ValueKind::StringList => {
+                let ok = value
+                    .as_array()
+                    .is_some_and(|items| items.iter().all(|v| v.is_string()));
+                if !ok {
+                    return Err(format!("{} must be a list of strings", key));
+                }
+            }
+            ValueKind::Command => {
+                let ok = value.is_string()
+                    || value
+                        .as_array()
+                        .is_some_and(|items| items.iter().all(|v| v.is_string()));
+                if !ok {
+                    return Err(format!("{} must be a string or a list of strings", key));
+                }
+            }
+            ValueKind::OneOf(allowed) => {
+                let s = value
+                    .as_str()
+                    .ok_or_else(|| format!("{} must be a string", key))?;
+                if !allowed.contains(&s) {
+                    return Err(format!("{} must be one of: {}", key, allowed.join(", ")));
+                }
+            }
+            ValueKind::Object => {
+                if !value.is_object() {
+                    return Err(format!("{} must be an object", key));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `path` is a parent object of some known key
+fn is_known_parent(path: &str) -> bool {
+    ConfigKey::ALL.iter().any(|k| {
+        k.name()
+            .strip_prefix(path)
+            .map(|rest| rest.starts_with('.'))
+            .unwrap_or(false)
+    })
+}
+
+/// Whether `path` is inside a known key that holds free-form objects
+fn is_within_object(path: &str) -> bool {
+    ConfigKey::ALL.iter().any(|k| {
+        matches!(k.kind(), ValueKind::Object)
+            && path
+                .strip_prefix(k.name())
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Validate a value about to be stored under `key`.
+///
+/// Known keys are always type-checked. Unknown keys are rejected only in strict mode.
+pub fn validate(key: &str, value: &Value, strict: bool) -> Result<(), String> {
+    let mut issues = Vec::new();
+    collect_issues(key, value, strict, &mut issues);
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues.join("; "))
+    }
+}
+
+/// Validate every value in a stored config map, returning one message per problem
+pub fn validate_all(config: &HashMap<String, Value>, strict: bool) -> Vec<String> {
+    let mut keys: Vec<&String> = config.keys().collect();
+    keys.sort();
+
+    let mut issues = Vec::new();
+    for key in keys {
+        collect_issues(key, &config[key], strict, &mut issues);
+    }
+
+    issues
+}
+
+fn collect_issues(path: &str, value: &Value, strict: bool, issues: &mut Vec<String>) {
+    if let Some(key) = ConfigKey::from_name(path) {
+        if let Err(e) = key.check(value) {
+            issues.push(e);
+        }
+        return;
+    }
+    if is_within_object(path) {
+        return;
+    }
+
+    // Objects holding known keys are checked leaf by leaf
+    if is_known_parent(path) {
+        match value.as_object() {
+            Some(map) => {
+                for (child, v) in map {
+                    collect_issues(&format!("{}.{}", path, child), v, strict, issues);
+                }
+            }
+            None => issues.push(format!("{} must be an object", path)),
+        }
+        return;
+    }
+
+    if strict {
+        issues.push(format!("Unknown config key: {}", path));
+    }
+}
@@ -0,0 +1,566 @@
+// Roura Agent Desktop - Conversation History
+// © Roura.io
+
+use crate::commands::{
+    config_value, write_atomic, write_json_atomic, AgentMessage, AgentResponse,
+};
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One side of an exchange with the agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "lowercase")]
+pub enum ConversationTurn {
+    User {
+        message: AgentMessage,
+        timestamp: String,
+    },
+    Agent {
+        response: AgentResponse,
+        timestamp: String,
+    },
+}
+
+/// Turns kept per project when `conversation.max_turns` is unset
+const DEFAULT_MAX_TURNS: usize = 500;
+
+/// Trimming drops this fraction of `max_turns` beyond the limit, so the next
+/// few appends don't each have to trim again
+const TRIM_SLACK_DIVISOR: usize = 10;
+
+/// Extension of the append-only files new turns are written to, one turn per line
+const JSONL_EXTENSION: &str = "jsonl";
+/// Extension of files from before history was append-only, each a JSON array
+const LEGACY_EXTENSION: &str = "json";
+
+/// Turns stored per conversations directory, counted on first use and then kept
+/// up to date so appending doesn't re-read the whole history
+static TURN_COUNTS: Mutex<BTreeMap<PathBuf, usize>> = Mutex::new(BTreeMap::new());
+
+/// Directory holding a project's conversation files, one per UTC day
+fn conversations_dir(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path)
+        .join(".roura")
+        .join("conversations")
+}
+
+/// Conversation files in a directory, oldest first
+fn conversation_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            matches!(
+                p.extension().and_then(|e| e.to_str()),
+                Some(JSONL_EXTENSION | LEGACY_EXTENSION)
+            )
+        })
+        .collect();
+
+    // Date-stamped names sort chronologically, and a day's legacy file sorts
+    // before the file that replaced it
+    files.sort();
+    files
+}
+
+fn is_jsonl(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(JSONL_EXTENSION)
+}
+
+/// Parse a conversation file a line at a time.
+///
+/// Lines that aren't a valid turn, such as a partial line left by a crash,
+/// are skipped with a warning so one bad write can't hide the whole history.
+fn read_turns(path: &Path) -> Result<Vec<ConversationTurn>, CommandError> {
+    use std::io::BufRead;
+
+    let file = std::fs::File::open(path)
+        .map_err(|e| CommandError::io("Failed to read conversation").with_details(e))?;
+    let reader = std::io::BufReader::new(file);
+
+    if !is_jsonl(path) {
+        return serde_json::from_reader(reader)
+            .map_err(|e| CommandError::parse("Failed to parse conversation").with_details(e));
+    }
+
+    let mut turns = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line =
+            line.map_err(|e| CommandError::io("Failed to read conversation").with_details(e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(turn) => turns.push(turn),
+            Err(e) => tracing::warn!(
+                path = %path.display(),
+                line = i + 1,
+                error = %e,
+                "Skipping malformed conversation line"
+            ),
+        }
+    }
+
+    Ok(turns)
+}
+
+/// Rewrite a conversation file with exactly `turns`, in the file's own format
+fn write_turns(path: &Path, turns: &[ConversationTurn]) -> Result<(), CommandError> {
+    if !is_jsonl(path) {
+        return write_json_atomic(path, turns);
+    }
+
+    let mut content = String::new();
+    for turn in turns {
+        content.push_str(&turn_line(turn)?);
+    }
+    write_atomic(path, content.as_bytes())
+}
+
+/// A turn as one JSONL line, newline included
+fn turn_line(turn: &ConversationTurn) -> Result<String, CommandError> {
+    let mut line = serde_json::to_string(turn)
+        .map_err(|e| CommandError::internal("Failed to serialize conversation").with_details(e))?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Append lines to `path` in a single write, first cutting off any partial
+/// line a crash left behind so the new turns start on a line of their own
+fn append_lines(path: &Path, lines: &str) -> std::io::Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+
+    let len = file.seek(SeekFrom::End(0))?;
+    let end = complete_lines_len(&mut file, len)?;
+    if end < len {
+        tracing::warn!(
+            path = %path.display(),
+            bytes = len - end,
+            "Dropping partial conversation line"
+        );
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+    }
+
+    file.write_all(lines.as_bytes())
+}
+
+/// Length of `file` up to and including its last newline, reading backwards from `len`
+fn complete_lines_len(file: &mut std::fs::File, len: u64) -> std::io::Result<u64> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut end = len;
+    let mut chunk = [0u8; 4096];
+    while end > 0 {
+        let size = chunk.len().min(end as usize);
+        let start = end - size as u64;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk[..size])?;
+        if let Some(i) = chunk[..size].iter().rposition(|&b| b == b'\n') {
+            return Ok(start + i as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Record a user message and the agent's reply in the project's history
+pub(crate) fn append_exchange(
+    project_path: &str,
+    message: AgentMessage,
+    response: AgentResponse,
+) -> Result<(), CommandError> {
+    let dir = conversations_dir(project_path);
+    std::fs::create_dir_all(&dir).map_err(|e| {
+        CommandError::io("Failed to create conversations directory").with_details(e)
+    })?;
+
+    let now = chrono::Utc::now();
+    let path = dir.join(format!("{}.{}", now.format("%Y-%m-%d"), JSONL_EXTENSION));
+
+    let user = ConversationTurn::User {
+        message,
+        timestamp: now.to_rfc3339(),
+    };
+    let agent = ConversationTurn::Agent {
+        response,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    let lines = turn_line(&user)? + &turn_line(&agent)?;
+
+    append_lines(&path, &lines)
+        .map_err(|e| CommandError::io("Failed to save conversation").with_details(e))?;
+
+    let max_turns = config_value("conversation.max_turns")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+        .unwrap_or(DEFAULT_MAX_TURNS);
+
+    let total = {
+        let mut counts = TURN_COUNTS.lock().map_err(CommandError::poisoned)?;
+        match counts.get_mut(&dir) {
+            Some(count) => {
+                *count += 2;
+                *count
+            }
+            None => {
+                let count = count_turns(&dir);
+                counts.insert(dir.clone(), count);
+                count
+            }
+        }
+    };
+    if total <= max_turns {
+        return Ok(());
+    }
+
+    let keep = max_turns - max_turns / TRIM_SLACK_DIVISOR;
+    match trim_history(&dir, keep) {
+        Ok(kept) => {
+            if let Ok(mut counts) = TURN_COUNTS.lock() {
+                counts.insert(dir, kept);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            forget_turn_count(&dir);
+            Err(e)
+        }
+    }
+}
+
+fn count_turns(dir: &Path) -> usize {
+    conversation_files(dir)
+        .iter()
+        .map(|f| read_turns(f).map(|t| t.len()).unwrap_or(0))
+        .sum()
+}
+
+/// Drop a cached count after the files changed underneath it
+fn forget_turn_count(dir: &Path) {
+    if let Ok(mut counts) = TURN_COUNTS.lock() {
+        counts.remove(dir);
+    }
+}
+
+/// Drop the oldest turns until at most `max_turns` remain, returning how many do
+fn trim_history(dir: &Path, max_turns: usize) -> Result<usize, CommandError> {
+    let files = conversation_files(dir);
+    let counts: Vec<usize> = files
+        .iter()
+        .map(|f| read_turns(f).map(|t| t.len()).unwrap_or(0))
+        .collect();
+
+    let total = counts.iter().sum::<usize>();
+    let mut excess = total.saturating_sub(max_turns);
+
+    for (file, count) in files.iter().zip(counts) {
+        if excess == 0 {
+            break;
+        }
+
+        if count <= excess {
+            std::fs::remove_file(file)
+                .map_err(|e| {
+                    CommandError::io("Failed to remove old conversation").with_details(e)
+                })?;
+            excess -= count;
+        } else {
+            let turns = read_turns(file)?;
+            write_turns(file, &turns[excess..])?;
+            excess = 0;
+        }
+    }
+
+    Ok(total.min(max_turns))
+}
+
+/// Get the stored conversation for a project, oldest turn first
+#[tauri::command]
+pub async fn get_conversation(
+    project_path: String,
+) -> Result<Vec<ConversationTurn>, CommandError> {
+    let mut turns = Vec::new();
+
+    for file in conversation_files(&conversations_dir(&project_path)) {
+        turns.extend(read_turns(&file)?);
+    }
+
+    Ok(turns)
+}
+
+/// Rewrite a project's history as one clean JSONL file per day.
+///
+/// Legacy array files are folded into their day's JSONL file and lines that
+/// don't parse, like partial ones left by a crash, are dropped. Returns the number of turns kept.
+#[tauri::command]
+pub async fn compact_conversation(project_path: String) -> Result<usize, CommandError> {
+    let dir = conversations_dir(&project_path);
+
+    let mut days: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for file in conversation_files(&dir) {
+        let day = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        days.entry(day).or_default().push(file);
+    }
+
+    let mut kept = 0;
+    for (day, files) in days {
+        let mut turns = Vec::new();
+        for file in &files {
+            turns.extend(read_turns(file)?);
+        }
+
+        let target = dir.join(format!("{}.{}", day, JSONL_EXTENSION));
+        write_turns(&target, &turns)?;
+        for file in files.iter().filter(|f| **f != target) {
+            std::fs::remove_file(file).map_err(|e| {
+                CommandError::io("Failed to remove old conversation").with_details(e)
+            })?;
+        }
+        kept += turns.len();
+    }
+
+    forget_turn_count(&dir);
+    Ok(kept)
+}
+
+/// Delete a project's stored conversation
+#[tauri::command]
+pub async fn clear_conversation(project_path: String) -> Result<(), CommandError> {
+    let dir = conversations_dir(&project_path);
+    for file in conversation_files(&dir) {
+        std::fs::remove_file(&file)
+            .map_err(|e| CommandError::io("Failed to remove conversation").with_details(e))?;
+    }
+    forget_turn_count(&dir);
+
+    Ok(())
+}
+
+/// A turn matching a `search_conversations` query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationHit {
+    /// Conversation file the turn is in, e.g. "2024-05-01"
+    pub conversation_id: String,
+    /// "user" or "agent"
+    pub role: String,
+    pub timestamp: String,
+    /// Text around the first match
+    pub snippet: String,
+}
+
+/// Hits returned when `search_conversations` isn't given a limit
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+/// Characters of context kept on each side of a match
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Find turns containing `query`, ignoring case, newest first.
+///
+/// Conversation files that can't be read are skipped.
+#[tauri::command]
+pub async fn search_conversations(
+    project_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ConversationHit>, CommandError> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Err(CommandError::invalid_input("Search query must not be empty"));
+    }
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    let mut hits = Vec::new();
+    for file in conversation_files(&conversations_dir(&project_path)).iter().rev() {
+        let Ok(turns) = read_turns(file) else {
+            continue;
+        };
+        let conversation_id = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        for turn in turns.iter().rev() {
+            if hits.len() >= limit {
+                return Ok(hits);
+            }
+
+            let (role, content, timestamp) = match turn {
+                ConversationTurn::User { message, timestamp } => {
+                    ("user", &message.content, timestamp)
+                }
+                ConversationTurn::Agent { response, timestamp } => {
+                    ("agent", &response.content, timestamp)
+                }
+            };
+            if let Some(snippet) = match_snippet(content, &needle) {
+                hits.push(ConversationHit {
+                    conversation_id: conversation_id.clone(),
+                    role: role.to_string(),
+                    timestamp: timestamp.clone(),
+                    snippet,
+                });
+            }
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Text around the first case-insensitive occurrence of `needle`, if any
+fn match_snippet(content: &str, needle: &str) -> Option<String> {
+    let lower = content.to_lowercase();
+    let start = lower.find(needle)?;
+
+    // Lowercasing can change byte lengths; then offsets don't map back, so show the start
+    let (start, end) = if lower.len() == content.len() {
+        (start, start + needle.len())
+    } else {
+        (0, 0)
+    };
+
+    let before: String = content[..start]
+        .chars()
+        .rev()
+        .take(SNIPPET_CONTEXT_CHARS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = content[end..]
+        .chars()
+        .take(SNIPPET_CONTEXT_CHARS)
+        .collect();
+
+    let mut snippet = String::new();
+    if before.len() < start {
+        snippet.push('…');
+    }
+    snippet.push_str(&before);
+    snippet.push_str(&content[start..end]);
+    snippet.push_str(&after);
+    if end + after.len() < content.len() {
+        snippet.push('…');
+    }
+
+    Some(snippet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::temp_config_root;
+    use serde_json::json;
+
+    fn exchange(project: &str, text: &str) {
+        let message = serde_json::from_value(json!({ "content": text })).unwrap();
+        let response =
+            serde_json::from_value(json!({ "content": text, "finished": true })).unwrap();
+        append_exchange(project, message, response).unwrap();
+    }
+
+    fn contents(turns: &[ConversationTurn]) -> Vec<&str> {
+        turns
+            .iter()
+            .map(|turn| match turn {
+                ConversationTurn::User { message, .. } => message.content.as_str(),
+                ConversationTurn::Agent { response, .. } => response.content.as_str(),
+            })
+            .collect()
+    }
+
+    fn only_file(project: &str) -> PathBuf {
+        let files = conversation_files(&conversations_dir(project));
+        assert_eq!(files.len(), 1);
+        files[0].clone()
+    }
+
+    #[tokio::test]
+    async fn appends_after_a_crash_keep_history_readable() {
+        let _root = temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().into_owned();
+
+        exchange(&project, "before");
+        let file = only_file(&project);
+        let mut content = std::fs::read_to_string(&file).unwrap();
+        content.push_str(r#"{"role":"user","message":{"cont"#);
+        std::fs::write(&file, content).unwrap();
+
+        exchange(&project, "after");
+
+        let turns = get_conversation(project.clone()).await.unwrap();
+        assert_eq!(contents(&turns), ["before", "before", "after", "after"]);
+        let content = std::fs::read_to_string(&file).unwrap();
+        assert!(content.ends_with('\n'));
+        assert_eq!(content.lines().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_skipped() {
+        let _root = temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().into_owned();
+
+        exchange(&project, "one");
+        let file = only_file(&project);
+        let mut content = std::fs::read_to_string(&file).unwrap();
+        content.push_str("not json\n\n");
+        std::fs::write(&file, content).unwrap();
+        exchange(&project, "two");
+
+        let turns = get_conversation(project.clone()).await.unwrap();
+        assert_eq!(contents(&turns), ["one", "one", "two", "two"]);
+        assert_eq!(compact_conversation(project).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn trimming_leaves_slack_and_keeps_the_count() {
+        let _root = temp_config_root();
+        crate::commands::set_config("conversation.max_turns".to_string(), json!(20))
+            .await
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().into_owned();
+        let cached = || {
+            TURN_COUNTS
+                .lock()
+                .unwrap()
+                .get(&conversations_dir(&project))
+                .copied()
+        };
+
+        for i in 0..10 {
+            exchange(&project, &i.to_string());
+        }
+        assert_eq!(get_conversation(project.clone()).await.unwrap().len(), 20);
+
+        // 22 turns is over the limit, so the oldest go down to 20 - 20 / 10
+        exchange(&project, "10");
+        let turns = get_conversation(project.clone()).await.unwrap();
+        assert_eq!(turns.len(), 18);
+        assert_eq!(contents(&turns)[0], "2");
+        assert_eq!(cached(), Some(18));
+
+        // The next exchange fits in the slack without another trim
+        exchange(&project, "11");
+        assert_eq!(get_conversation(project.clone()).await.unwrap().len(), 20);
+        assert_eq!(cached(), Some(20));
+    }
+}
@@ -0,0 +1,242 @@
+// Roura Agent Desktop - Diagnostics Report
+// © Roura.io
+
+use crate::error::CommandError;
+use crate::{backend, commands};
+use serde::Serialize;
+use std::io::Write;
+
+/// Config keys whose last segment contains one of these are redacted
+const SECRET_MARKERS: &[&str] = &["token", "key", "secret"];
+
+/// Placeholder written in place of a redacted value
+const REDACTED: &str = "[redacted]";
+
+/// Prefixes of well-known API key formats
+const TOKEN_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "gho_",
+    "github_pat_",
+    "hf_",
+    "xoxb-",
+    "xoxp-",
+];
+
+/// Write a zip of everything needed to triage an issue to `dest`.
+///
+/// Holds the config with secrets redacted, recent projects, backend status,
+/// health and buffered output, the resolved Python, and the platform and app
+/// version. A part that can't be gathered is recorded as an error instead of
+/// failing the report.
+#[tauri::command]
+pub async fn export_diagnostics(dest: String) -> Result<(), CommandError> {
+    let config = commands::load_config().map(|config| {
+        config
+            .into_iter()
+            .map(|(key, value)| {
+                let value = redact(&key, value);
+                (key, value)
+            })
+            .collect::<serde_json::Map<_, _>>()
+    });
+
+    let logs: String = backend::buffered_logs()
+        .into_iter()
+        .map(|l| format!("[{}] {}\n", l.stream, redact_line(&l.line)))
+        .collect();
+
+    let files = vec![
+        (
+            "system.json",
+            part(Ok::<_, CommandError>(serde_json::json!({
+                "app_version": env!("CARGO_PKG_VERSION"),
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "generated_at": chrono::Utc::now().to_rfc3339(),
+            }))),
+        ),
+        ("config.json", part(config)),
+        ("recent_projects.json", part(commands::load_projects())),
+        ("backend_status.json", part(backend::backend_status().await)),
+        ("backend_health.json", part(backend::backend_health().await)),
+        ("python.json", part(backend::detect_python(None).await)),
+    ];
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let entries = files
+        .into_iter()
+        .map(|(name, value)| (name, value.into_bytes()))
+        .chain(std::iter::once(("backend.log", logs.into_bytes())));
+    for (name, data) in entries {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(&data).map_err(Into::into))
+            .map_err(|e| {
+                CommandError::internal("Failed to write diagnostics report").with_details(e)
+            })?;
+    }
+
+    let archive = zip
+        .finish()
+        .map_err(|e| CommandError::internal("Failed to write diagnostics report").with_details(e))?
+        .into_inner();
+    std::fs::write(&dest, archive)
+        .map_err(|e| CommandError::io("Failed to save diagnostics report").with_details(e))
+}
+
+/// Pretty JSON for one part of the report, or the error that prevented gathering it
+fn part<T: Serialize>(result: Result<T, CommandError>) -> String {
+    let value = match result {
+        Ok(value) => serde_json::to_value(value).unwrap_or_default(),
+        Err(e) => serde_json::json!({ "error": e.to_string() }),
+    };
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+/// Replace values of secret-looking keys, looking into nested objects and arrays.
+///
+/// Array items are checked under the array's own key.
+fn redact(key: &str, value: serde_json::Value) -> serde_json::Value {
+    let segment = key.rsplit('.').next().unwrap_or(key).to_ascii_lowercase();
+    if SECRET_MARKERS.iter().any(|m| segment.contains(m)) {
+        return serde_json::json!(REDACTED);
+    }
+
+    match value {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = redact(&k, v);
+                    (k, v)
+                })
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(|v| redact(key, v)).collect())
+        }
+        other => other,
+    }
+}
+
+/// Best-effort redaction of a backend log line.
+///
+/// Catches `key=value` pairs with a secret-looking key, the word after
+/// `Bearer` or a secret-looking `key:`, and words shaped like API keys.
+fn redact_line(line: &str) -> String {
+    let mut previous = "";
+    let mut words = Vec::new();
+    for word in line.split(' ') {
+        let after_label = previous.eq_ignore_ascii_case("bearer")
+            || previous.strip_suffix(':').is_some_and(is_secret_name);
+        let redacted = if after_label || is_token_shaped(word) {
+            REDACTED.to_string()
+        } else {
+            match word.split_once('=') {
+                Some((name, _)) if is_secret_name(name) => format!("{}={}", name, REDACTED),
+                _ => word.to_string(),
+            }
+        };
+        previous = word.trim_matches(|c: char| c == '"' || c == '\'');
+        words.push(redacted);
+    }
+    words.join(" ")
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name
+        .trim_matches(|c: char| !c.is_ascii_alphanumeric())
+        .to_ascii_lowercase();
+    SECRET_MARKERS.iter().any(|m| name.contains(m))
+}
+
+/// A known key prefix, or a long run mixing upper and lower case letters and digits
+fn is_token_shaped(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-');
+    if !word
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return false;
+    }
+    if TOKEN_PREFIXES.iter().any(|p| word.starts_with(p)) && word.len() >= 20 {
+        return true;
+    }
+
+    word.len() >= 32
+        && word.chars().any(|c| c.is_ascii_uppercase())
+        && word.chars().any(|c| c.is_ascii_lowercase())
+        && word.chars().any(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn secrets_are_redacted_in_nested_objects_and_arrays() {
+        let config = json!({
+            "name": "default",
+            "auth": { "api_key": "abc", "user": "sam" },
+            "providers": [
+                { "name": "openai", "api_key": "sk-1" },
+                { "name": "local", "headers": [{ "token": "t" }] },
+            ],
+        });
+
+        assert_eq!(
+            redact("backend", config),
+            json!({
+                "name": "default",
+                "auth": { "api_key": REDACTED, "user": "sam" },
+                "providers": [
+                    { "name": "openai", "api_key": REDACTED },
+                    { "name": "local", "headers": [{ "token": REDACTED }] },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn secret_keys_redact_whole_arrays() {
+        assert_eq!(redact("tokens", json!(["a", "b"])), json!(REDACTED));
+        assert_eq!(redact("backend.api_key", json!("x")), json!(REDACTED));
+        assert_eq!(redact("backend.port", json!(8765)), json!(8765));
+    }
+
+    #[test]
+    fn log_lines_lose_token_shaped_words() {
+        let cases = [
+            (
+                "GET /v1 Authorization: Bearer abc.def",
+                "GET /v1 Authorization: Bearer [redacted]",
+            ),
+            ("api_key=hunter2 port=8765", "api_key=[redacted] port=8765"),
+            ("Using token: s3cret", "Using token: [redacted]"),
+            (
+                "key sk-abcdefghijklmnopqrstuv loaded",
+                "key [redacted] loaded",
+            ),
+            ("got \"Ab3dEf6hIj9kLm2nOp5qRs8tUv1wXy4z\"", "got [redacted]"),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(redact_line(line), expected);
+        }
+    }
+
+    #[test]
+    fn ordinary_log_lines_are_kept() {
+        let lines = [
+            "Generated 128 tokens in 2.1s",
+            "request 3f2b6c1e-8a4d-4c1f-9b2e-7d6a5c4b3a21 done",
+            "sha256 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+            "Loading model key-value cache",
+        ];
+        for line in lines {
+            assert_eq!(redact_line(line), line);
+        }
+    }
+}
@@ -0,0 +1,575 @@
+// Roura Agent Desktop - Drag and Drop Filtering
+// © Roura.io
+
+use crate::commands;
+use crate::error::CommandError;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Extensions accepted when `dragdrop.allowed_extensions` isn't set
+const DEFAULT_ALLOWED_EXTENSIONS: &[&str] = &[
+    // Text and docs
+    "txt", "md", "markdown", "rst", "log", "csv", "tsv", "json", "jsonl", "yaml", "yml", "toml",
+    "xml", "ini", "cfg", "conf", "env", "pdf",
+    // Code
+    "rs", "py", "js", "jsx", "ts", "tsx", "mjs", "cjs", "html", "css", "scss", "sass", "vue",
+    "svelte", "go", "java", "kt", "kts", "swift", "c", "h", "cc", "cpp", "hpp", "cs", "rb", "php",
+    "lua", "sh", "bash", "zsh", "fish", "ps1", "sql", "graphql", "proto", "dart", "scala", "ex",
+    "exs", "hs", "ml", "r", "jl", "zig", "gradle", "dockerfile",
+    // Images
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "svg",
+];
+
+/// Files accepted from a single drop when `dragdrop.max_files` isn't set
+const DEFAULT_MAX_FILES: usize = 20;
+
+/// Text returned by `file_preview` when `max_bytes` isn't given
+const DEFAULT_PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// Most text `file_preview` returns, whatever is asked for
+const MAX_PREVIEW_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Longest side of an image preview
+const PREVIEW_MAX_DIM: u32 = 512;
+
+/// Images larger than this on disk are described rather than decoded
+const MAX_PREVIEW_IMAGE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Widest or tallest image decoded for a preview; small files can hold huge images
+const MAX_PREVIEW_DECODE_DIM: u32 = 16_384;
+
+/// Most memory the decoder may allocate for a preview
+const MAX_PREVIEW_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// A dropped path that won't be forwarded, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedDrop {
+    pub path: PathBuf,
+    /// One of "extension", "directory", "unreadable" or "too_many"
+    pub reason: &'static str,
+}
+
+/// Dropped paths split by the allowlist
+#[derive(Debug, Default, Serialize)]
+pub struct FilteredDrop {
+    pub accepted: Vec<PathBuf>,
+    pub rejected: Vec<RejectedDrop>,
+}
+
+/// Split dropped paths into accepted files and rejected entries.
+///
+/// Directories are expanded one level to their immediate files; nested
+/// directories are rejected. At most `dragdrop.max_files` files are accepted.
+pub fn filter_drop(paths: &[PathBuf]) -> FilteredDrop {
+    let allowed = allowed_extensions();
+    let max_files = commands::config_value("dragdrop.max_files")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_FILES);
+
+    let mut result = FilteredDrop::default();
+    let accept = |path: PathBuf, result: &mut FilteredDrop| {
+        let reason = if path.is_dir() {
+            Some("directory")
+        } else if !is_allowed(&path, &allowed) {
+            Some("extension")
+        } else if result.accepted.len() >= max_files {
+            Some("too_many")
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => result.rejected.push(RejectedDrop { path, reason }),
+            None => result.accepted.push(path),
+        }
+    };
+
+    for path in paths {
+        if !path.is_dir() {
+            accept(path.clone(), &mut result);
+            continue;
+        }
+
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => {
+                result.rejected.push(RejectedDrop {
+                    path: path.clone(),
+                    reason: "unreadable",
+                });
+                continue;
+            }
+        };
+
+        let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        children.sort();
+        for child in children {
+            accept(child, &mut result);
+        }
+    }
+
+    result
+}
+
+/// Lowercased extensions from config, without leading dots
+fn allowed_extensions() -> Vec<String> {
+    let configured = commands::config_value("dragdrop.allowed_extensions").and_then(|v| {
+        v.as_array().map(|items| {
+            items
+                .iter()
+                .filter_map(|e| e.as_str())
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    configured.unwrap_or_else(|| {
+        DEFAULT_ALLOWED_EXTENSIONS
+            .iter()
+            .map(|e| e.to_string())
+            .collect()
+    })
+}
+
+fn is_allowed(path: &Path, allowed: &[String]) -> bool {
+    // Extensionless files like "Dockerfile" match on their name
+    let key = path
+        .extension()
+        .or_else(|| path.file_name())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    key.is_some_and(|key| allowed.contains(&key))
+}
+
+/// What a path is on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathKind {
+    File,
+    Directory,
+    Symlink,
+    Missing,
+}
+
+/// A dropped path as the frontend needs to present it
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntry {
+    pub path: String,
+    pub kind: PathKind,
+    /// For symlinks, what the link resolves to; `Missing` when it is dangling
+    pub target_kind: Option<PathKind>,
+    /// Size in bytes, for files and links to files
+    pub size: Option<u64>,
+    /// Guessed from the extension, for files
+    pub mime: Option<String>,
+    pub readable: bool,
+    /// Immediate entries, for directories
+    pub child_count: Option<usize>,
+}
+
+/// Describe each path: its kind, size, type and whether it can be read.
+///
+/// Symlinks are reported as such, with size and readability taken from their
+/// target. Missing paths are listed rather than failing the call.
+#[tauri::command]
+pub async fn classify_paths(paths: Vec<String>) -> Result<Vec<PathEntry>, CommandError> {
+    Ok(paths.iter().map(|p| classify_path(Path::new(p))).collect())
+}
+
+fn classify_path(path: &Path) -> PathEntry {
+    let kind_of = |metadata: &std::fs::Metadata| {
+        if metadata.is_dir() {
+            PathKind::Directory
+        } else if metadata.file_type().is_symlink() {
+            PathKind::Symlink
+        } else {
+            PathKind::File
+        }
+    };
+
+    let kind = std::fs::symlink_metadata(path)
+        .map(|m| kind_of(&m))
+        .unwrap_or(PathKind::Missing);
+    // Follows links, so this describes what a symlink points at
+    let target = std::fs::metadata(path).ok();
+    let target_kind = (kind == PathKind::Symlink)
+        .then(|| target.as_ref().map(kind_of).unwrap_or(PathKind::Missing));
+    let resolved = target_kind.unwrap_or(kind);
+
+    let (readable, child_count) = match resolved {
+        PathKind::Directory => match std::fs::read_dir(path) {
+            Ok(entries) => (true, Some(entries.count())),
+            Err(_) => (false, None),
+        },
+        PathKind::File => (std::fs::File::open(path).is_ok(), None),
+        _ => (false, None),
+    };
+    let is_file = resolved == PathKind::File;
+
+    PathEntry {
+        path: path.to_string_lossy().into_owned(),
+        kind,
+        target_kind,
+        size: target.filter(|_| is_file).map(|m| m.len()),
+        mime: is_file.then(|| guess_mime(path).to_string()),
+        readable,
+        child_count,
+    }
+}
+
+/// MIME type for a file from its extension, falling back to a generic binary type
+fn guess_mime(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "jsonl" => "application/jsonl",
+        "xml" => "application/xml",
+        "zip" => "application/zip",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "md" | "markdown" => "text/markdown",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        _ if is_allowed(path, &text_extensions()) => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Allowlisted extensions that aren't images or PDFs, all of which are text
+fn text_extensions() -> Vec<String> {
+    DEFAULT_ALLOWED_EXTENSIONS
+        .iter()
+        .filter(|e| !matches!(**e, "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "pdf"))
+        .map(|e| e.to_string())
+        .collect()
+}
+
+/// How `file_preview` rendered a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewKind {
+    Image,
+    Text,
+    /// Metadata only
+    Other,
+}
+
+/// A file as the frontend needs to preview it
+#[derive(Debug, Clone, Serialize)]
+pub struct FilePreview {
+    /// Canonical path
+    pub path: String,
+    pub kind: PreviewKind,
+    /// Guessed from the extension
+    pub mime: String,
+    pub size: u64,
+    /// RFC3339, when the filesystem records it
+    pub modified: Option<String>,
+    /// PNG `data:` URL no larger than `PREVIEW_MAX_DIM`, for images
+    pub data_url: Option<String>,
+    /// Original dimensions, for images
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Leading text, for text files
+    pub text: Option<String>,
+    /// Whether `text` stops before the end of the file
+    pub truncated: bool,
+}
+
+/// Preview any attachable file: images as a downscaled data URL, text as its
+/// first `max_bytes`, anything else as metadata.
+///
+/// Paths outside `projects.allowed_roots` are refused when that's set. An
+/// image that fails to decode, or exceeds the decode limits, falls back to
+/// metadata.
+#[tauri::command]
+pub async fn file_preview(
+    path: String,
+    max_bytes: Option<u64>,
+) -> Result<FilePreview, CommandError> {
+    // The same form the allowed roots are compared in
+    let canonical_str = commands::canonical_project_path(&path);
+    let canonical = PathBuf::from(&canonical_str);
+    commands::ensure_allowed_project(&canonical_str)?;
+
+    let metadata = std::fs::metadata(&canonical).map_err(|e| {
+        CommandError::not_found(format!("File not found: {}", path)).with_details(e)
+    })?;
+    if !metadata.is_file() {
+        return Err(CommandError::invalid_input(format!("Not a file: {}", path)));
+    }
+
+    let mime = guess_mime(&canonical);
+    let mut preview = FilePreview {
+        path: canonical_str,
+        kind: PreviewKind::Other,
+        mime: mime.to_string(),
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+        data_url: None,
+        width: None,
+        height: None,
+        text: None,
+        truncated: false,
+    };
+
+    // SVG goes through the text path; the image crate only decodes raster formats
+    let raster = image::ImageFormat::from_path(&canonical).is_ok();
+    if raster && metadata.len() <= MAX_PREVIEW_IMAGE_BYTES {
+        let path = canonical.clone();
+        let image = tokio::task::spawn_blocking(move || image_preview(&path))
+            .await
+            .map_err(|e| CommandError::io("Preview task failed").with_details(e))??;
+        if let Some((png, width, height)) = image {
+            preview.kind = PreviewKind::Image;
+            preview.data_url = Some(format!("data:image/png;base64,{}", STANDARD.encode(&png)));
+            preview.width = Some(width);
+            preview.height = Some(height);
+        }
+    } else if is_text_mime(mime) {
+        let limit = max_bytes.unwrap_or(DEFAULT_PREVIEW_BYTES).min(MAX_PREVIEW_BYTES);
+        let mut bytes = Vec::new();
+        std::fs::File::open(&canonical)
+            .and_then(|file| file.take(limit).read_to_end(&mut bytes))
+            .map_err(|e| CommandError::io("Failed to read file").with_details(e))?;
+
+        preview.truncated = metadata.len() > bytes.len() as u64;
+        // Don't end on half a character cut off by the limit
+        if preview.truncated {
+            if let Err(e) = std::str::from_utf8(&bytes) {
+                if e.error_len().is_none() {
+                    bytes.truncate(e.valid_up_to());
+                }
+            }
+        }
+        preview.kind = PreviewKind::Text;
+        preview.text = Some(String::from_utf8_lossy(&bytes).into_owned());
+    }
+
+    Ok(preview)
+}
+
+/// Decode an image within the preview limits and downscale it, as PNG bytes
+/// and the original size; `None` when it can't be decoded
+fn image_preview(path: &Path) -> Result<Option<(Vec<u8>, u32, u32)>, CommandError> {
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_PREVIEW_DECODE_DIM);
+    limits.max_image_height = Some(MAX_PREVIEW_DECODE_DIM);
+    limits.max_alloc = Some(MAX_PREVIEW_DECODE_ALLOC);
+
+    let Ok(mut reader) = image::io::Reader::open(path) else {
+        return Ok(None);
+    };
+    reader.limits(limits);
+    let Ok(img) = reader.decode() else {
+        return Ok(None);
+    };
+
+    let mut png = Vec::new();
+    crate::screenshot::downscale(&img, PREVIEW_MAX_DIM)
+        .write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| CommandError::internal("Failed to encode preview").with_details(e))?;
+    Ok(Some((png, img.width(), img.height())))
+}
+
+/// Whether a MIME type from `guess_mime` is readable as text
+fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/jsonl"
+                | "application/xml"
+                | "application/yaml"
+                | "application/toml"
+                | "image/svg+xml"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_report_size_and_mime() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "# hello").unwrap();
+
+        let entry = classify_path(&path);
+        assert_eq!(entry.kind, PathKind::File);
+        assert_eq!(entry.target_kind, None);
+        assert_eq!(entry.size, Some(7));
+        assert_eq!(entry.mime.as_deref(), Some("text/markdown"));
+        assert!(entry.readable);
+        assert_eq!(entry.child_count, None);
+    }
+
+    #[test]
+    fn directories_report_their_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let entry = classify_path(dir.path());
+        assert_eq!(entry.kind, PathKind::Directory);
+        assert_eq!(entry.child_count, Some(2));
+        assert!(entry.readable);
+        assert_eq!((entry.size, entry.mime), (None, None));
+    }
+
+    #[test]
+    fn missing_paths_are_listed_unreadable() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let entry = classify_path(&dir.path().join("gone.png"));
+        assert_eq!(entry.kind, PathKind::Missing);
+        assert_eq!(entry.target_kind, None);
+        assert!(!entry.readable);
+        assert_eq!((entry.size, entry.mime), (None, None));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_describe_their_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("image.png");
+        std::fs::write(&file, [0u8; 10]).unwrap();
+        let link = dir.path().join("link.png");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        let entry = classify_path(&link);
+        assert_eq!(entry.kind, PathKind::Symlink);
+        assert_eq!(entry.target_kind, Some(PathKind::File));
+        assert_eq!(entry.size, Some(10));
+        assert_eq!(entry.mime.as_deref(), Some("image/png"));
+        assert!(entry.readable);
+
+        let dir_link = dir.path().join("dir-link");
+        std::os::unix::fs::symlink(dir.path(), &dir_link).unwrap();
+        let entry = classify_path(&dir_link);
+        assert_eq!(entry.target_kind, Some(PathKind::Directory));
+        assert!(entry.child_count.is_some());
+
+        std::fs::remove_file(&file).unwrap();
+        let entry = classify_path(&link);
+        assert_eq!(entry.kind, PathKind::Symlink);
+        assert_eq!(entry.target_kind, Some(PathKind::Missing));
+        assert!(!entry.readable);
+        assert_eq!(entry.size, None);
+    }
+
+    fn save_png(path: &Path, width: u32, height: u32) {
+        image::RgbaImage::from_pixel(width, height, image::Rgba([10, 20, 30, 255]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn images_preview_downscaled_with_their_size() {
+        let _root = crate::commands::test_support::temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wide.png");
+        save_png(&path, 1024, 256);
+
+        let preview = file_preview(path.to_string_lossy().into_owned(), None)
+            .await
+            .unwrap();
+        assert_eq!(preview.kind, PreviewKind::Image);
+        assert_eq!((preview.width, preview.height), (Some(1024), Some(256)));
+        let data = preview.data_url.unwrap();
+        let png = STANDARD
+            .decode(data.strip_prefix("data:image/png;base64,").unwrap())
+            .unwrap();
+        let thumbnail = image::load_from_memory(&png).unwrap();
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (PREVIEW_MAX_DIM, 128)
+        );
+    }
+
+    #[tokio::test]
+    async fn images_over_the_decode_limits_fall_back_to_metadata() {
+        let _root = crate::commands::test_support::temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tall.png");
+        // A few KB on disk, but taller than any preview decodes
+        save_png(&path, 1, MAX_PREVIEW_DECODE_DIM + 1);
+
+        let preview = file_preview(path.to_string_lossy().into_owned(), None)
+            .await
+            .unwrap();
+        assert_eq!(preview.kind, PreviewKind::Other);
+        assert_eq!(preview.data_url, None);
+    }
+
+    #[tokio::test]
+    async fn previews_compare_canonical_paths_with_the_allowed_roots() {
+        let _root = crate::commands::test_support::temp_config_root();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("project")).unwrap();
+        std::fs::create_dir(dir.path().join("other")).unwrap();
+        let inside = dir.path().join("project/notes.txt");
+        let outside = dir.path().join("other/notes.txt");
+        std::fs::write(&inside, "hello").unwrap();
+        std::fs::write(&outside, "hello").unwrap();
+        // A root written with `..` still names the project
+        let root = dir.path().join("other/../project");
+        crate::commands::set_config(
+            "projects.allowed_roots".to_string(),
+            serde_json::json!([root.to_string_lossy()]),
+        )
+        .await
+        .unwrap();
+
+        let via_dots = dir.path().join("other/../project/./notes.txt");
+        let preview = file_preview(via_dots.to_string_lossy().into_owned(), None)
+            .await
+            .unwrap();
+        assert_eq!(preview.text.as_deref(), Some("hello"));
+        assert_eq!(
+            PathBuf::from(&preview.path),
+            std::fs::canonicalize(&inside).unwrap()
+        );
+
+        let err = file_preview(outside.to_string_lossy().into_owned(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "forbidden");
+        let err = file_preview(
+            dir.path()
+                .join("project/gone.txt")
+                .to_string_lossy()
+                .into_owned(),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), "not_found");
+    }
+}
@@ -0,0 +1,209 @@
+// Roura Agent Desktop - Command Errors
+// © Roura.io
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Error returned by commands.
+///
+/// Serializes as `{ "code", "message", "details" }`, where `code` is stable per
+/// variant so the frontend can branch on it instead of on message text.
+#[derive(Debug, Clone)]
+pub enum CommandError {
+    /// A file, project, note or request that doesn't exist
+    NotFound { message: String, details: Option<String> },
+    /// Filesystem or process failure
+    Io { message: String, details: Option<String> },
+    /// The Python backend is unavailable or answered with an error
+    Backend { message: String, details: Option<String> },
+    /// Stored or received data couldn't be decoded
+    Parse { message: String, details: Option<String> },
+    /// The caller passed an argument that can't be used
+    InvalidInput { message: String, details: Option<String> },
+    /// Valid, but not permitted by policy
+    Forbidden { message: String, details: Option<String> },
+    /// The operation isn't available on this platform or setup
+    Unsupported { message: String, details: Option<String> },
+    /// Too much work is already queued; retry later
+    Busy { message: String, details: Option<String> },
+    /// Anything else, including state the app can't recover from
+    Internal { message: String, details: Option<String> },
+}
+
+impl CommandError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::Io {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn backend(message: impl Into<String>) -> Self {
+        Self::Backend {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::Parse {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::Forbidden {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self::Unsupported {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn busy(message: impl Into<String>) -> Self {
+        Self::Busy {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// For `lock().map_err(CommandError::poisoned)`: a thread panicked holding the lock
+    pub fn poisoned<T>(e: std::sync::PoisonError<T>) -> Self {
+        Self::internal("Internal state lock poisoned").with_details(e)
+    }
+
+    /// Attach the underlying cause, e.g. the OS error behind a failed read
+    pub fn with_details(mut self, cause: impl fmt::Display) -> Self {
+        let (_, details) = self.parts_mut();
+        *details = Some(cause.to_string());
+        self
+    }
+
+    /// Stable machine-readable identifier for the variant
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "not_found",
+            Self::Io { .. } => "io",
+            Self::Backend { .. } => "backend",
+            Self::Parse { .. } => "parse",
+            Self::InvalidInput { .. } => "invalid_input",
+            Self::Forbidden { .. } => "forbidden",
+            Self::Unsupported { .. } => "unsupported",
+            Self::Busy { .. } => "busy",
+            Self::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        self.parts().0
+    }
+
+    pub fn details(&self) -> Option<&str> {
+        self.parts().1.as_deref()
+    }
+
+    fn parts(&self) -> (&String, &Option<String>) {
+        match self {
+            Self::NotFound { message, details }
+            | Self::Io { message, details }
+            | Self::Backend { message, details }
+            | Self::Parse { message, details }
+            | Self::InvalidInput { message, details }
+            | Self::Forbidden { message, details }
+            | Self::Unsupported { message, details }
+            | Self::Busy { message, details }
+            | Self::Internal { message, details } => (message, details),
+        }
+    }
+
+    fn parts_mut(&mut self) -> (&mut String, &mut Option<String>) {
+        match self {
+            Self::NotFound { message, details }
+            | Self::Io { message, details }
+            | Self::Backend { message, details }
+            | Self::Parse { message, details }
+            | Self::InvalidInput { message, details }
+            | Self::Forbidden { message, details }
+            | Self::Unsupported { message, details }
+            | Self::Busy { message, details }
+            | Self::Internal { message, details } => (message, details),
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(details) => write!(f, "{}: {}", self.message(), details),
+            None => f.write_str(self.message()),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CommandError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(e: std::io::Error) -> Self {
+        let error = match e.kind() {
+            std::io::ErrorKind::NotFound => Self::not_found("File not found"),
+            _ => Self::io("I/O error"),
+        };
+        error.with_details(e)
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::parse("Invalid JSON").with_details(e)
+    }
+}
+
+impl From<reqwest::Error> for CommandError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::backend("Backend request failed").with_details(e)
+    }
+}
+
+/// Lets non-command code keep plain string errors while calling command helpers
+impl From<CommandError> for String {
+    fn from(e: CommandError) -> Self {
+        e.to_string()
+    }
+}
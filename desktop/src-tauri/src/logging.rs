@@ -0,0 +1,86 @@
+// Roura Agent Desktop - Diagnostics Logging
+// © Roura.io
+
+use crate::commands;
+use crate::error::CommandError;
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+/// Level used when `log.level` is unset or invalid
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+
+/// Daily log files kept in `<config root>/logs`
+const LOG_FILES_KEPT: usize = 7;
+
+/// Swaps the level filter at runtime
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Flushes the file writer when dropped, so it lives for the whole process
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Log to stderr and a daily rotated file, at the level from `log.level`
+pub fn init() {
+    let level = commands::config_value("log.level")
+        .and_then(|v| v.as_str().and_then(parse_level))
+        .unwrap_or(DEFAULT_LEVEL);
+    let (filter, handle) = reload::Layer::new(level);
+
+    let file = commands::config_root().map(|root| root.join("logs")).and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("desktop")
+            .filename_suffix("log")
+            .max_log_files(LOG_FILES_KEPT)
+            .build(&dir)
+            .map_err(|e| CommandError::io("Failed to open log file").with_details(e))
+    });
+    let (file_layer, file_error) = match file {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = FILE_GUARD.set(guard);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false);
+            (Some(layer), None)
+        }
+        Err(e) => (None, Some(e)),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .init();
+    let _ = LEVEL_HANDLE.set(handle);
+
+    // Still logged, just to stderr only
+    if let Some(e) = file_error {
+        tracing::warn!(error = %e, "File logging disabled");
+    }
+}
+
+fn parse_level(level: &str) -> Option<LevelFilter> {
+    level.trim().parse().ok()
+}
+
+/// Change the log level until the app restarts; `log.level` persists it
+#[tauri::command]
+pub async fn set_log_level(level: String) -> Result<(), CommandError> {
+    let filter = parse_level(&level)
+        .ok_or_else(|| CommandError::invalid_input(format!("Unknown log level: {}", level)))?;
+    let handle = LEVEL_HANDLE
+        .get()
+        .ok_or_else(|| CommandError::internal("Logging is not initialized"))?;
+
+    handle
+        .reload(filter)
+        .map_err(|e| CommandError::internal("Failed to change log level").with_details(e))?;
+    tracing::info!(level = %filter, "Log level changed");
+
+    Ok(())
+}
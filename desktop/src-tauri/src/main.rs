@@ -7,13 +7,47 @@
 )]
 
 mod commands;
+mod config_schema;
+mod conversation;
+mod diagnostics;
+mod dragdrop;
+mod error;
+mod logging;
 mod screenshot;
 mod backend;
+mod rpc;
+mod schema;
+mod shortcut;
+mod tray;
+mod watcher;
+mod window_state;
 
-use tauri::Manager;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Identical drops within this window are treated as one gesture
+const DROP_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Paths and time of the last forwarded drop
+static LAST_DROP: Mutex<Option<(Vec<PathBuf>, Instant)>> = Mutex::new(None);
+/// Last drag position forwarded to the frontend
+static LAST_DRAG_POSITION: Mutex<Option<(f64, f64)>> = Mutex::new(None);
 
 fn main() {
+    logging::init();
+
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before doing any work
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            request_open_project(app, &args, &cwd);
+        }))
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
@@ -23,57 +57,243 @@ fn main() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .invoke_handler(tauri::generate_handler![
             commands::send_message,
+            commands::cancel_message,
+            commands::list_active_requests,
+            commands::cancel_all_requests,
             commands::get_config,
             commands::set_config,
+            commands::remove_config,
+            commands::list_themes,
+            commands::apply_theme,
+            commands::list_config_keys,
+            commands::validate_config,
+            commands::set_config_root,
+            commands::get_effective_config,
+            commands::set_project_config,
+            commands::export_config,
+            commands::import_config,
+            commands::diff_config,
             commands::list_projects,
+            commands::set_active_project,
+            commands::get_active_project,
             commands::open_project,
+            commands::inspect_project,
+            commands::reveal_project,
+            commands::remove_project,
+            commands::clear_recent_projects,
+            commands::set_project_pinned,
+            commands::refresh_project_git,
             commands::get_memory,
+            commands::get_active_memory,
+            commands::get_active_conversation,
+            commands::get_memory_categories,
             commands::add_memory_note,
+            commands::add_memory_notes,
+            commands::update_memory_note,
+            commands::delete_memory_note,
+            commands::set_memory_note_pinned,
+            commands::recompute_memory_relevance,
+            commands::compact_memory,
+            commands::memory_stats,
+            commands::validate_memory,
+            commands::repair_memory,
+            dragdrop::classify_paths,
+            dragdrop::file_preview,
+            conversation::get_conversation,
+            conversation::clear_conversation,
+            conversation::compact_conversation,
+            conversation::search_conversations,
             screenshot::capture_screenshot,
             screenshot::capture_region,
+            screenshot::capture_interactive,
+            screenshot::capture_window,
+            screenshot::capture_active_window,
+            screenshot::capture_self_window,
+            screenshot::capture_scrolling_window,
+            screenshot::capture_all_displays,
+            screenshot::annotate_screenshot,
+            screenshot::ocr_screenshot,
+            screenshot::list_displays,
+            screenshot::screenshot_capabilities,
+            screenshot::copy_screenshot_to_clipboard,
+            screenshot::cleanup_screenshot,
+            screenshot::save_screenshot_to_project,
+            screenshot::list_project_screenshots,
             backend::start_backend,
             backend::stop_backend,
             backend::backend_status,
+            backend::probe_backend,
+            backend::backend_health,
+            backend::ping_backend,
+            backend::warm_backend,
+            backend::get_backend_logs,
+            backend::subscribe_backend_logs,
+            backend::unsubscribe_backend_logs,
+            backend::read_backend_log,
+            backend::detect_python,
+            backend::list_models,
+            backend::set_active_model,
+            logging::set_log_level,
+            diagnostics::export_diagnostics,
+            schema::get_command_schemas,
         ])
         .setup(|app| {
+            if let Some(window) = app.get_webview_window("main") {
+                window_state::restore(&window);
+            }
+
             // Initialize backend connection
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 if let Err(e) = backend::initialize(&app_handle).await {
-                    eprintln!("Failed to initialize backend: {}", e);
+                    tracing::error!(error = %e, "Failed to initialize backend");
                 }
             });
 
+            if let Err(e) = screenshot::init_temp_dir(app.handle()) {
+                tracing::error!(error = %e, "Failed to set up screenshot directory");
+            }
+
+            if let Err(e) = tray::init(app.handle()) {
+                tracing::error!(error = %e, "Failed to set up tray");
+            }
+
+            if let Err(e) = shortcut::init(app.handle()) {
+                tracing::error!(error = %e, "Failed to set up global shortcuts");
+            }
+
+            // Live-reload config edited outside the app
+            if let Err(e) = watcher::start(app.handle().clone()) {
+                tracing::error!(error = %e, "Failed to start config watcher");
+            }
+
             Ok(())
         })
         .on_window_event(|window, event| {
+            if window.label() == "main" {
+                match event {
+                    tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
+                        window_state::track(window);
+                    }
+                    tauri::WindowEvent::CloseRequested { .. } => {
+                        if let Err(e) = window_state::save() {
+                            tracing::error!(error = %e, "Failed to save window state");
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // Keep running in the tray instead of quitting
+                let minimize_to_tray = commands::config_value("minimize_to_tray")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                if minimize_to_tray {
+                    api.prevent_close();
+                    let _ = window.hide();
+                }
+            }
+
             if let tauri::WindowEvent::DragDrop(drag_drop) = event {
                 match drag_drop {
                     tauri::DragDropEvent::Drop { paths, position } => {
-                        // Emit drag-drop event to frontend
+                        // Some platforms fire Drop twice for one gesture
+                        if is_repeated_drop(paths) {
+                            return;
+                        }
+
+                        // Only forward allowed files; the UI warns about the rest
+                        let filtered = dragdrop::filter_drop(paths);
                         let _ = window.emit("file-drop", serde_json::json!({
-                            "paths": paths,
+                            "paths": filtered.accepted,
+                            "rejected": filtered.rejected,
                             "position": { "x": position.x, "y": position.y }
                         }));
                     }
                     tauri::DragDropEvent::Enter { paths, position } => {
+                        drag_moved(position.x, position.y);
                         let _ = window.emit("file-drag-enter", serde_json::json!({
                             "paths": paths,
                             "position": { "x": position.x, "y": position.y }
                         }));
                     }
                     tauri::DragDropEvent::Over { position } => {
+                        if !drag_moved(position.x, position.y) {
+                            return;
+                        }
                         let _ = window.emit("file-drag-over", serde_json::json!({
                             "position": { "x": position.x, "y": position.y }
                         }));
                     }
                     tauri::DragDropEvent::Leave => {
+                        if let Ok(mut last) = LAST_DRAG_POSITION.lock() {
+                            *last = None;
+                        }
                         let _ = window.emit("file-drag-leave", ());
                     }
                     _ => {}
                 }
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Quitting from the tray skips CloseRequested
+                if let Err(e) = window_state::save() {
+                    tracing::error!(error = %e, "Failed to save window state");
+                }
+
+                // Release the instance lock before exit so relaunch isn't blocked
+                tauri_plugin_single_instance::destroy(app);
+            }
+        });
+}
+
+/// Emit `open-project-request` for the first directory among launch arguments.
+///
+/// Relative paths are resolved against the launching process's `cwd`.
+fn request_open_project(app: &AppHandle, args: &[String], cwd: &str) {
+    let project = args
+        .iter()
+        .skip(1) // executable path
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| PathBuf::from(cwd).join(arg))
+        .find(|path| path.is_dir());
+
+    if let Some(path) = project {
+        let _ = app.emit(
+            "open-project-request",
+            serde_json::json!({ "path": path.to_string_lossy() }),
+        );
+    }
+}
+
+/// Record a drop, returning whether it repeats the previous one within `DROP_DEBOUNCE`
+fn is_repeated_drop(paths: &[PathBuf]) -> bool {
+    let Ok(mut last) = LAST_DROP.lock() else {
+        return false;
+    };
+
+    let now = Instant::now();
+    let repeated = last.as_ref().is_some_and(|(prev, at)| {
+        prev.as_slice() == paths && now.duration_since(*at) < DROP_DEBOUNCE
+    });
+    if !repeated {
+        *last = Some((paths.to_vec(), now));
+    }
+
+    repeated
+}
+
+/// Record the drag position, returning whether it differs from the last one
+fn drag_moved(x: f64, y: f64) -> bool {
+    let Ok(mut last) = LAST_DRAG_POSITION.lock() else {
+        return true;
+    };
+
+    let moved = *last != Some((x, y));
+    *last = Some((x, y));
+    moved
 }
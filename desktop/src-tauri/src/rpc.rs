@@ -0,0 +1,464 @@
+// Roura Agent Desktop - Stdio JSON-RPC Transport
+// © Roura.io
+
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{ChildStdin, ChildStdout};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+type Reply = Result<serde_json::Value, CommandError>;
+
+/// A backend speaking newline-delimited JSON-RPC 2.0 over its stdin and stdout
+struct StdioClient {
+    /// Tells this connection apart from a later one, so a stale reader can't clear it
+    generation: u64,
+    /// Lines for the writer thread, with the id of the call each belongs to. Taken on
+    /// detach, which ends the thread and closes stdin, telling the backend to exit.
+    outgoing: Mutex<Option<mpsc::UnboundedSender<(u64, String)>>>,
+    /// Calls waiting for a reply, by request id
+    pending: Mutex<HashMap<u64, oneshot::Sender<Reply>>>,
+    /// Notification streams, by the `request_id` in their params
+    streams: Mutex<HashMap<String, mpsc::UnboundedSender<serde_json::Value>>>,
+}
+
+static CLIENT: Mutex<Option<Arc<StdioClient>>> = Mutex::new(None);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: serde_json::Value,
+}
+
+/// Anything the backend writes: a reply carries `id`, a notification `method`
+#[derive(Debug, Deserialize)]
+struct Incoming {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Option<serde_json::Value>,
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Notification events for one request, unsubscribed when dropped
+pub(crate) struct Subscription {
+    request_id: String,
+    events: mpsc::UnboundedReceiver<serde_json::Value>,
+}
+
+impl Subscription {
+    pub(crate) async fn recv(&mut self) -> Option<serde_json::Value> {
+        self.events.recv().await
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Option<serde_json::Value> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(client) = current() {
+            if let Ok(mut streams) = client.streams.lock() {
+                streams.remove(&self.request_id);
+            }
+        }
+    }
+}
+
+fn current() -> Option<Arc<StdioClient>> {
+    CLIENT.lock().ok()?.clone()
+}
+
+/// Whether a stdio backend is connected
+pub(crate) fn is_attached() -> bool {
+    current().is_some()
+}
+
+/// Talk to a freshly spawned backend over its pipes, replacing any previous connection
+pub(crate) fn attach(stdin: ChildStdin, stdout: ChildStdout) {
+    let (outgoing, lines) = mpsc::unbounded_channel();
+    let client = Arc::new(StdioClient {
+        generation: NEXT_GENERATION.fetch_add(1, Ordering::SeqCst),
+        outgoing: Mutex::new(Some(outgoing)),
+        pending: Mutex::new(HashMap::new()),
+        streams: Mutex::new(HashMap::new()),
+    });
+
+    if let Ok(mut current) = CLIENT.lock() {
+        *current = Some(client.clone());
+    }
+
+    // The writer only holds a weak reference, so a replaced connection still closes
+    let writer = Arc::downgrade(&client);
+    std::thread::spawn(move || write_loop(writer, stdin, lines));
+    std::thread::spawn(move || read_loop(client, stdout));
+}
+
+/// Drop the connection, closing the backend's stdin
+pub(crate) fn detach() {
+    let client = CLIENT.lock().ok().and_then(|mut c| c.take());
+    if let Some(client) = client {
+        if let Ok(mut outgoing) = client.outgoing.lock() {
+            outgoing.take();
+        }
+    }
+}
+
+/// Send `method` and wait for its reply, up to `timeout` if given
+pub(crate) async fn call(
+    method: &str,
+    params: serde_json::Value,
+    timeout: Option<Duration>,
+) -> Result<serde_json::Value, CommandError> {
+    let client = current().ok_or_else(|| CommandError::backend("Backend is not running"))?;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let line = encode_request(id, method, params)?;
+    let (tx, rx) = oneshot::channel();
+    client
+        .pending
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .insert(id, tx);
+
+    let forget = || {
+        if let Ok(mut pending) = client.pending.lock() {
+            pending.remove(&id);
+        }
+    };
+
+    // Queued rather than written here, so a backend that stops reading can't block the runtime
+    let queued = client
+        .outgoing
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .as_ref()
+        .is_some_and(|outgoing| outgoing.send((id, line)).is_ok());
+    if !queued {
+        forget();
+        return Err(CommandError::backend("Failed to write to backend")
+            .with_details("The backend's stdin is closed"));
+    }
+
+    let reply = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, rx).await {
+            Ok(reply) => reply,
+            Err(_) => {
+                forget();
+                return Err(CommandError::backend(format!(
+                    "Backend did not answer {} within {}s",
+                    method,
+                    timeout.as_secs()
+                )));
+            }
+        },
+        None => rx.await,
+    };
+
+    reply.map_err(|_| CommandError::backend("Backend closed the stdio connection"))?
+}
+
+/// Receive notifications whose params carry `request_id`
+pub(crate) fn subscribe(request_id: &str) -> Result<Subscription, CommandError> {
+    let client = current().ok_or_else(|| CommandError::backend("Backend is not running"))?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    client
+        .streams
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .insert(request_id.to_string(), tx);
+
+    Ok(Subscription {
+        request_id: request_id.to_string(),
+        events: rx,
+    })
+}
+
+/// One request as a single line; serde_json never writes raw newlines inside strings
+fn encode_request(
+    id: u64,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<String, CommandError> {
+    let mut line = serde_json::to_string(&Request {
+        jsonrpc: "2.0",
+        id,
+        method,
+        params,
+    })?;
+    line.push('\n');
+    Ok(line)
+}
+
+fn parse_line(line: &str) -> Option<Incoming> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    serde_json::from_str(line).ok()
+}
+
+/// Write queued requests to the backend until the connection is dropped or a write fails
+fn write_loop(
+    client: Weak<StdioClient>,
+    mut stdin: ChildStdin,
+    mut lines: mpsc::UnboundedReceiver<(u64, String)>,
+) {
+    while let Some((id, line)) = lines.blocking_recv() {
+        if let Err(e) = stdin.write_all(line.as_bytes()).and_then(|_| stdin.flush()) {
+            // The reply to this call can't come now
+            let waiter = client
+                .upgrade()
+                .and_then(|c| c.pending.lock().ok()?.remove(&id));
+            if let Some(waiter) = waiter {
+                let _ = waiter.send(Err(
+                    CommandError::backend("Failed to write to backend").with_details(e)
+                ));
+            }
+            break;
+        }
+    }
+}
+
+/// Route everything the backend writes until its stdout closes
+fn read_loop(client: Arc<StdioClient>, stdout: ChildStdout) {
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match parse_line(&line) {
+            Some(message) => dispatch(&client, message),
+            // Stray prints still belong in the backend log
+            None if !line.trim().is_empty() => crate::backend::push_stdout_line(line),
+            None => {}
+        }
+    }
+
+    // Nothing in flight will be answered now
+    if let Ok(mut pending) = client.pending.lock() {
+        for (_, reply) in pending.drain() {
+            let _ = reply.send(Err(CommandError::backend("Backend closed the stdio connection")));
+        }
+    }
+    if let Ok(mut streams) = client.streams.lock() {
+        streams.clear();
+    }
+    if let Ok(mut current) = CLIENT.lock() {
+        if current.as_ref().is_some_and(|c| c.generation == client.generation) {
+            *current = None;
+        }
+    }
+}
+
+fn dispatch(client: &StdioClient, message: Incoming) {
+    match (message.id, message.method) {
+        (Some(id), None) => {
+            let reply = match message.error {
+                Some(error) => Err(CommandError::backend(error.message)
+                    .with_details(format!("JSON-RPC error {}", error.code))),
+                None => Ok(message.result.unwrap_or(serde_json::Value::Null)),
+            };
+            let waiter = client.pending.lock().ok().and_then(|mut p| p.remove(&id));
+            if let Some(waiter) = waiter {
+                let _ = waiter.send(reply);
+            }
+        }
+        (_, Some(_)) => {
+            let params = message.params.unwrap_or_default();
+            let Some(request_id) = params.get("request_id").and_then(|v| v.as_str()) else {
+                return;
+            };
+            if let Ok(streams) = client.streams.lock() {
+                if let Some(stream) = streams.get(request_id) {
+                    let _ = stream.send(params.clone());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// The connection is process-wide, so tests with a mock child take turns
+    #[cfg(unix)]
+    static CHILD_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Attach a shell loop that answers every request by echoing it back as the
+    /// result, after a notification for the `request_id` in its params and a
+    /// stray print. The method `quit` makes it exit without answering.
+    #[cfg(unix)]
+    fn attach_echo_child() -> std::process::Child {
+        let script = r#"
+            while IFS= read -r line; do
+                case "$line" in *'"method":"quit"'*) exit 0 ;; esac
+                id=$(printf '%s' "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+                rid=$(printf '%s' "$line" | sed -n 's/.*"request_id":"\([^"]*\)".*/\1/p')
+                echo "debug: got $id"
+                printf '{"jsonrpc":"2.0","method":"event","params":{"request_id":"%s"}}\n' "$rid"
+                printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$line"
+            done
+        "#;
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", script])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        attach(child.stdin.take().unwrap(), child.stdout.take().unwrap());
+        child
+    }
+
+    #[test]
+    fn requests_are_one_line_each() {
+        let line = encode_request(7, "chat", json!({ "text": "a\nb" })).unwrap();
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        let sent: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            sent,
+            json!({ "jsonrpc": "2.0", "id": 7, "method": "chat", "params": { "text": "a\nb" } })
+        );
+    }
+
+    #[test]
+    fn replies_and_notifications_parse() {
+        let reply = parse_line(r#"{"jsonrpc":"2.0","id":3,"result":{"ok":true}}"#).unwrap();
+        assert_eq!(reply.id, Some(3));
+        assert!(reply.method.is_none());
+        assert_eq!(reply.result, Some(json!({ "ok": true })));
+
+        let error =
+            parse_line(r#"{"jsonrpc":"2.0","id":4,"error":{"code":-32601,"message":"no"}}"#)
+                .unwrap()
+                .error
+                .unwrap();
+        assert_eq!((error.code, error.message.as_str()), (-32601, "no"));
+
+        let note =
+            parse_line("  {\"method\":\"token\",\"params\":{\"request_id\":\"r\"}}\r\n").unwrap();
+        assert_eq!(note.method.as_deref(), Some("token"));
+        assert!(note.id.is_none());
+    }
+
+    #[test]
+    fn blank_and_stray_lines_are_not_messages() {
+        assert!(parse_line("").is_none());
+        assert!(parse_line("   \r").is_none());
+        assert!(parse_line("Loading model...").is_none());
+        assert!(parse_line("{\"id\":").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn calls_get_their_own_reply_and_notifications() {
+        let _lock = CHILD_LOCK.lock().await;
+        let mut child = attach_echo_child();
+        let mut events = subscribe("req-1").unwrap();
+
+        let timeout = Some(Duration::from_secs(5));
+        let params = json!({ "request_id": "req-1", "text": "hi" });
+        let echoed = call("chat", params.clone(), timeout).await.unwrap();
+        assert_eq!(echoed["method"], "chat");
+        assert_eq!(echoed["params"], params);
+        assert_eq!(events.try_recv(), Some(json!({ "request_id": "req-1" })));
+
+        // Concurrent calls are matched to replies by id
+        let (a, b) = tokio::join!(
+            call("first", json!({ "n": 1 }), timeout),
+            call("second", json!({ "n": 2 }), timeout),
+        );
+        assert_eq!(a.unwrap()["params"]["n"], 1);
+        assert_eq!(b.unwrap()["params"]["n"], 2);
+
+        detach();
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pending_calls_fail_when_the_child_exits() {
+        let _lock = CHILD_LOCK.lock().await;
+        let mut child = attach_echo_child();
+
+        let err = call("quit", json!({}), Some(Duration::from_secs(5)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Backend closed the stdio connection");
+        child.wait().unwrap();
+
+        // The reader clears the connection once stdout closes
+        for _ in 0..50 {
+            if !is_attached() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!is_attached());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pings_go_over_stdio_when_attached() {
+        let _lock = CHILD_LOCK.lock().await;
+        let mut child = attach_echo_child();
+
+        let ping = crate::backend::ping_backend(Some(2)).await.unwrap();
+        assert_eq!((ping.port, ping.status), (None, None));
+        assert_eq!(ping.latency_ms.len(), 2);
+        assert!(ping.min_ms <= ping.avg_ms && ping.avg_ms <= ping.max_ms);
+
+        detach();
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn a_backend_that_stops_reading_cannot_block_calls() {
+        let _lock = CHILD_LOCK.lock().await;
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        attach(child.stdin.take().unwrap(), child.stdout.take().unwrap());
+
+        // Far more than a pipe buffer holds
+        let params = json!({ "text": "x".repeat(4 * 1024 * 1024) });
+        let started = std::time::Instant::now();
+        let err = call("chat", params, Some(Duration::from_secs(1)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Backend did not answer chat"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        detach();
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
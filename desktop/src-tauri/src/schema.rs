@@ -0,0 +1,45 @@
+// Roura Agent Desktop - Command Payload Schemas
+// © Roura.io
+
+use crate::error::CommandError;
+
+/// JSON Schema (draft 7) definitions for the payloads commands take and return.
+///
+/// Only available in builds with the `schema` feature.
+#[tauri::command]
+pub async fn get_command_schemas() -> Result<serde_json::Value, CommandError> {
+    #[cfg(feature = "schema")]
+    {
+        Ok(command_schemas())
+    }
+
+    #[cfg(not(feature = "schema"))]
+    {
+        Err(CommandError::unsupported(
+            "Command schemas are only available in builds with the schema feature",
+        ))
+    }
+}
+
+#[cfg(feature = "schema")]
+fn command_schemas() -> serde_json::Value {
+    use crate::{backend, commands, screenshot};
+
+    let mut generator = schemars::gen::SchemaSettings::draft07().into_generator();
+    generator.subschema_for::<commands::AgentMessage>();
+    generator.subschema_for::<commands::AgentResponse>();
+    generator.subschema_for::<commands::ToolCall>();
+    generator.subschema_for::<commands::Project>();
+    generator.subschema_for::<commands::MemoryNote>();
+    generator.subschema_for::<commands::Config>();
+    generator.subschema_for::<backend::BackendStatus>();
+    generator.subschema_for::<screenshot::ScreenshotResult>();
+    generator.subschema_for::<screenshot::CaptureRegion>();
+
+    // Types reached through these, like GitInfo, are included too
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Roura Agent desktop command payloads",
+        "definitions": generator.take_definitions(),
+    })
+}
@@ -1,213 +1,3657 @@
 // Roura Agent Desktop - Screenshot Capture
 // © Roura.io
 
+use crate::error::CommandError;
 use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 
 /// Screenshot result
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ScreenshotResult {
-    /// Base64-encoded image data
-    pub data: String,
-    /// Image format (png, jpeg)
+    /// Base64-encoded image data, omitted when the capture is returned by path only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// Image format (png, jpeg, webp, bmp, avif)
     pub format: String,
+    /// Set when the requested format wasn't available and another was used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
     /// Width in pixels
     pub width: u32,
     /// Height in pixels
     pub height: u32,
+    /// Base64-encoded downscaled copy, when `thumbnail_max_dim` was given
+    #[serde(default)]
+    pub thumbnail: Option<String>,
     /// File path if saved
     pub path: Option<String>,
+    /// Size of the encoded image, which is also the saved file's length
+    #[serde(default)]
+    pub byte_size: u64,
+    /// Hex SHA-256 of the encoded image, when `hash` was requested
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Title of the captured window, for window captures
+    pub window_title: Option<String>,
+    /// Application owning the captured window, when known
+    #[serde(default)]
+    pub window_app: Option<String>,
+    /// Index of the captured display, for single-display captures
+    pub display: Option<usize>,
+    /// Bounds of the captured display, for single-display captures
+    #[serde(default)]
+    pub display_bounds: Option<DisplayInfo>,
+    /// Display scale the capture was taken at (physical pixels per logical point)
+    pub scale_factor: f64,
+    /// Width in logical points
+    pub logical_width: u32,
+    /// Height in logical points
+    pub logical_height: u32,
 }
 
-/// Region for partial screenshot
-#[derive(Debug, Serialize, Deserialize)]
+impl ScreenshotResult {
+    /// Record the display scale and derive the logical size from it
+    fn set_scale(&mut self, scale: f64) {
+        self.scale_factor = scale;
+        self.logical_width = (self.width as f64 / scale).round() as u32;
+        self.logical_height = (self.height as f64 / scale).round() as u32;
+    }
+}
+
+/// Region for partial screenshot, in logical coordinates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CaptureRegion {
     pub x: i32,
     pub y: i32,
     pub width: u32,
     pub height: u32,
+    /// Scale of the display the region is on; looked up from the display list when omitted
+    #[serde(default)]
+    pub scale_factor: Option<f64>,
 }
 
-/// Capture full screenshot
-#[tauri::command]
-pub async fn capture_screenshot(save_path: Option<String>) -> Result<ScreenshotResult, String> {
-    #[cfg(target_os = "macos")]
-    {
-        capture_macos_screenshot(save_path, None).await
+impl CaptureRegion {
+    /// Convert logical coordinates to physical pixels at the given scale
+    fn to_physical(&self, scale: f64) -> CaptureRegion {
+        CaptureRegion {
+            x: (self.x as f64 * scale).round() as i32,
+            y: (self.y as f64 * scale).round() as i32,
+            width: (self.width as f64 * scale).round() as u32,
+            height: (self.height as f64 * scale).round() as u32,
+            scale_factor: Some(1.0),
+        }
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        capture_windows_screenshot(save_path, None).await
+    /// Clamp to a `width` x `height` image, as (x, y, width, height); `None` if nothing is left
+    #[cfg(any(target_os = "linux", test))]
+    fn clamp_to(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let (width, height) = (i64::from(width), i64::from(height));
+        let x0 = i64::from(self.x).clamp(0, width);
+        let y0 = i64::from(self.y).clamp(0, height);
+        let x1 = (i64::from(self.x) + i64::from(self.width)).clamp(0, width);
+        let y1 = (i64::from(self.y) + i64::from(self.height)).clamp(0, height);
+
+        (x1 > x0 && y1 > y0).then(|| (x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+
+    /// Scale of the display this region is on
+    fn scale(&self) -> f64 {
+        self.scale_factor
+            .or_else(|| {
+                list_native_displays()
+                    .ok()?
+                    .into_iter()
+                    .find(|d| {
+                        self.x >= d.x
+                            && self.y >= d.y
+                            && (self.x as i64) < d.x as i64 + d.width as i64
+                            && (self.y as i64) < d.y as i64 + d.height as i64
+                    })
+                    .map(|d| d.scale_factor)
+            })
+            .filter(|s| *s > 0.0)
+            .unwrap_or(1.0)
     }
+}
 
+/// Whether the native region tool expects physical pixels rather than logical points.
+/// `screencapture -R` and grim take logical coordinates; scrot and GDI work in physical pixels.
+fn native_region_is_physical() -> bool {
     #[cfg(target_os = "linux")]
     {
-        capture_linux_screenshot(save_path, None).await
+        !is_wayland_session()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        cfg!(target_os = "windows")
     }
 }
 
-/// Capture screenshot of a specific region
+/// A connected display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DisplayInfo {
+    pub index: usize,
+    /// Bounds in the desktop coordinate space used by `CaptureRegion`
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+/// Encoding used for the returned image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Png,
+    Jpeg,
+    WebP,
+    Bmp,
+    /// Only encodable when built with the `avif` feature
+    Avif,
+}
+
+impl OutputFormat {
+    fn parse(format: Option<&str>) -> Result<Self, CommandError> {
+        match format {
+            None => Ok(Self::Png),
+            Some(name) => Self::from_name(name).ok_or_else(|| {
+                CommandError::invalid_input(format!("Unsupported screenshot format: {}", name))
+            }),
+        }
+    }
+
+    /// Format for a format name or file extension, case-insensitively
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "webp" => Some(Self::WebP),
+            "bmp" => Some(Self::Bmp),
+            "avif" => Some(Self::Avif),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::WebP => "webp",
+            Self::Bmp => "bmp",
+            Self::Avif => "avif",
+        }
+    }
+
+    /// This format, or PNG and a warning if its encoder isn't compiled in
+    fn or_fallback(self) -> (Self, Option<String>) {
+        if self == Self::Avif && !cfg!(feature = "avif") {
+            let warning = "AVIF support is not included in this build; encoded as PNG instead";
+            return (Self::Png, Some(warning.to_string()));
+        }
+        (self, None)
+    }
+}
+
+/// Output options shared by the capture commands
+#[derive(Debug, Clone)]
+struct OutputOptions {
+    format: OutputFormat,
+    /// JPEG, lossy WebP and AVIF quality, 1-100
+    quality: u8,
+    /// Return the encoded image as base64; otherwise only `path` is filled in
+    inline: bool,
+    /// Longest side of the preview thumbnail, none when not requested
+    thumbnail_max_dim: Option<u32>,
+    /// Fill in `sha256` on the result
+    hash: bool,
+    /// The caller chose `quality` rather than taking the default
+    quality_requested: bool,
+}
+
+impl OutputOptions {
+    fn new(format: Option<String>, quality: Option<u8>) -> Result<Self, CommandError> {
+        Ok(Self {
+            format: OutputFormat::parse(format.as_deref())?,
+            quality: quality.unwrap_or(DEFAULT_JPEG_QUALITY).clamp(1, 100),
+            inline: true,
+            thumbnail_max_dim: None,
+            hash: false,
+            quality_requested: quality.is_some(),
+        })
+    }
+
+    /// Warning for a WebP quality this build can't honour
+    fn lossless_webp_warning(&self) -> Option<String> {
+        let lossless = self.format == OutputFormat::WebP && !cfg!(feature = "webp-lossy");
+        (lossless && self.quality_requested).then(|| {
+            "Lossy WebP is not included in this build; encoded losslessly, ignoring quality"
+                .to_string()
+        })
+    }
+}
+
+/// JPEG quality used when none is requested
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Capture full screenshot, or a single display when `display` is given.
+///
+/// `delay_ms` waits before capturing, emitting `capture-countdown` each second.
+/// With `inline: false` the image is only written to `save_path`, or to a temp
+/// file loadable through the asset protocol, and `data` is left out.
 #[tauri::command]
-pub async fn capture_region(
-    region: CaptureRegion,
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_screenshot(
+    app: AppHandle,
     save_path: Option<String>,
-) -> Result<ScreenshotResult, String> {
+    format: Option<String>,
+    quality: Option<u8>,
+    display: Option<usize>,
+    include_cursor: Option<bool>,
+    delay_ms: Option<u64>,
+    inline: Option<bool>,
+    thumbnail_max_dim: Option<u32>,
+    hash: Option<bool>,
+) -> Result<ScreenshotResult, CommandError> {
+    let mut output = OutputOptions::new(format, quality)?;
+    output.inline = inline.unwrap_or(true);
+    output.thumbnail_max_dim = thumbnail_max_dim;
+    output.hash = hash.unwrap_or(false);
+    let include_cursor = include_cursor.unwrap_or(false);
+
+    // A path-only result still needs somewhere for the frontend to load it from
+    let save_path = match save_path {
+        Some(path) => Some(path),
+        None if !output.inline => Some(new_temp_screenshot_path(&output)?),
+        None => None,
+    };
+
+    countdown(&app, delay_ms.unwrap_or(0)).await;
+
+    let Some(index) = display else {
+        let img = capture_native(None, include_cursor).await?;
+        let mut result = finish_capture(img, save_path, &output)?;
+        if let Some(primary) = list_native_displays()
+            .ok()
+            .and_then(|d| d.into_iter().find(|d| d.primary))
+        {
+            result.set_scale(primary.scale_factor);
+        }
+        return Ok(result);
+    };
+
+    let target = list_native_displays()?
+        .into_iter()
+        .find(|d| d.index == index)
+        .ok_or_else(|| CommandError::not_found(format!("No display with index {}", index)))?;
+
+    let img = capture_native_display(&target, include_cursor).await?;
+    let mut result = finish_capture(img, save_path, &output)?;
+    result.display = Some(index);
+    result.set_scale(target.scale_factor);
+    result.display_bounds = Some(target);
+    Ok(result)
+}
+
+/// Capture every display separately, in one pass so they match in time.
+///
+/// With `save_dir`, each capture is also written there as `display_<index>.png`.
+#[tauri::command]
+pub async fn capture_all_displays(
+    save_dir: Option<String>,
+) -> Result<Vec<ScreenshotResult>, CommandError> {
+    let mut displays = list_native_displays()?;
+    if displays.is_empty() {
+        return Err(CommandError::not_found("No displays found"));
+    }
+    displays.sort_by_key(|d| d.index);
+
+    if let Some(dir) = &save_dir {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| CommandError::io("Failed to create screenshot directory").with_details(e))?;
+    }
+
+    let output = OutputOptions::new(None, None)?;
+    let images = capture_native_displays(&displays).await?;
+
+    displays
+        .into_iter()
+        .zip(images)
+        .map(|(display, img)| {
+            let save_path = save_dir.as_ref().map(|dir| {
+                Path::new(dir)
+                    .join(format!("display_{}.png", display.index))
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            let mut result = finish_capture(img, save_path, &output)?;
+            result.display = Some(display.index);
+            result.set_scale(display.scale_factor);
+            result.display_bounds = Some(display);
+            Ok(result)
+        })
+        .collect()
+}
+
+/// A capture tool looked for on `PATH`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenshotTool {
+    pub name: String,
+    pub installed: bool,
+}
+
+/// What capture commands can do here, from `screenshot_capabilities`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenshotCapabilities {
+    pub platform: String,
+    /// "wayland" or "x11" on Linux
+    pub session: Option<String>,
+    pub full: bool,
+    pub region: bool,
+    pub window: bool,
+    pub interactive: bool,
+    /// Linux tools checked; empty where capture is built in
+    pub tools: Vec<ScreenshotTool>,
+    /// What to install to enable an unsupported capability
+    pub hints: Vec<String>,
+}
+
+/// Capture tools the Linux paths can use
+#[cfg(target_os = "linux")]
+const LINUX_CAPTURE_TOOLS: &[&str] = &[
+    "grim",
+    "slurp",
+    "scrot",
+    "gnome-screenshot",
+    "spectacle",
+    "import",
+    "xdotool",
+];
+
+/// Report which kinds of capture work on this platform and session.
+///
+/// On Linux this depends on the installed tools: grim and slurp under Wayland
+/// (with the desktop portal as a fallback), scrot, gnome-screenshot, ImageMagick's
+/// import and xdotool under X11.
+#[tauri::command]
+pub async fn screenshot_capabilities() -> Result<ScreenshotCapabilities, CommandError> {
     #[cfg(target_os = "macos")]
     {
-        capture_macos_screenshot(save_path, Some(region)).await
+        // screencapture ships with the OS
+        Ok(ScreenshotCapabilities {
+            platform: std::env::consts::OS.to_string(),
+            session: None,
+            full: true,
+            region: true,
+            window: true,
+            interactive: true,
+            tools: Vec::new(),
+            hints: Vec::new(),
+        })
     }
 
     #[cfg(target_os = "windows")]
     {
-        capture_windows_screenshot(save_path, Some(region)).await
+        Ok(ScreenshotCapabilities {
+            platform: std::env::consts::OS.to_string(),
+            session: None,
+            full: true,
+            region: true,
+            window: false,
+            interactive: false,
+            tools: Vec::new(),
+            hints: Vec::new(),
+        })
     }
 
     #[cfg(target_os = "linux")]
     {
-        capture_linux_screenshot(save_path, Some(region)).await
+        let tools: Vec<ScreenshotTool> = LINUX_CAPTURE_TOOLS
+            .iter()
+            .map(|name| ScreenshotTool {
+                name: name.to_string(),
+                installed: crate::backend::program_exists(name),
+            })
+            .collect();
+        let has = |name: &str| tools.iter().any(|t| t.name == name && t.installed);
+        let wayland = is_wayland_session();
+
+        let mut hints = Vec::new();
+        let (full, region, window, interactive) = if wayland {
+            // The portal covers full and region captures wherever grim can't run
+            if !(has("grim") && has("slurp")) {
+                hints.push("Install grim and slurp to select a region".to_string());
+            }
+            (true, true, false, has("grim") && has("slurp"))
+        } else {
+            let full = has("gnome-screenshot") || has("scrot");
+            let window = has("xdotool") && (has("import") || has("gnome-screenshot"));
+            if !has("scrot") {
+                hints.push("Install scrot for region and interactive capture".to_string());
+            }
+            if !window {
+                hints.push("Install xdotool and ImageMagick for window capture".to_string());
+            }
+            (full, has("scrot"), window, has("scrot"))
+        };
+
+        Ok(ScreenshotCapabilities {
+            platform: std::env::consts::OS.to_string(),
+            session: Some(if wayland { "wayland" } else { "x11" }.to_string()),
+            full,
+            region,
+            window,
+            interactive,
+            tools,
+            hints,
+        })
     }
 }
 
-#[cfg(target_os = "macos")]
-async fn capture_macos_screenshot(
-    save_path: Option<String>,
-    region: Option<CaptureRegion>,
-) -> Result<ScreenshotResult, String> {
-    use std::process::Command;
+/// List connected displays
+#[tauri::command]
+pub async fn list_displays() -> Result<Vec<DisplayInfo>, CommandError> {
+    list_native_displays()
+}
 
-    // Create temp file path
-    let temp_path = std::env::temp_dir().join(format!("roura_screenshot_{}.png", uuid::Uuid::new_v4()));
+/// Capture screenshot of a specific region
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_region(
+    app: AppHandle,
+    region: CaptureRegion,
+    save_path: Option<String>,
+    format: Option<String>,
+    quality: Option<u8>,
+    include_cursor: Option<bool>,
+    delay_ms: Option<u64>,
+    thumbnail_max_dim: Option<u32>,
+    hash: Option<bool>,
+) -> Result<ScreenshotResult, CommandError> {
+    let mut output = OutputOptions::new(format, quality)?;
+    output.thumbnail_max_dim = thumbnail_max_dim;
+    output.hash = hash.unwrap_or(false);
 
-    // Build screencapture command
-    let mut cmd = Command::new("screencapture");
-    cmd.arg("-x"); // No sound
+    countdown(&app, delay_ms.unwrap_or(0)).await;
 
-    if let Some(r) = &region {
-        cmd.arg("-R").arg(format!("{},{},{},{}", r.x, r.y, r.width, r.height));
-    }
+    let (img, scale) = capture_native_region(region, include_cursor.unwrap_or(false)).await?;
+    let mut result = finish_capture(img, save_path, &output)?;
+    result.set_scale(scale);
+    Ok(result)
+}
 
-    let final_path = if let Some(ref p) = save_path {
-        PathBuf::from(p)
+/// Capture a logical region, returning the image and the scale it was taken at
+async fn capture_native_region(
+    region: CaptureRegion,
+    include_cursor: bool,
+) -> Result<(image::DynamicImage, f64), CommandError> {
+    let scale = region.scale();
+    let native_region = if native_region_is_physical() {
+        region.to_physical(scale)
     } else {
-        temp_path.clone()
+        CaptureRegion {
+            scale_factor: Some(scale),
+            ..region
+        }
     };
 
-    cmd.arg(&final_path);
+    Ok((capture_native(Some(native_region), include_cursor).await?, scale))
+}
 
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+/// Wait `delay_ms` before a capture, emitting `capture-countdown` on each whole second
+async fn countdown(app: &AppHandle, delay_ms: u64) {
+    let mut remaining = delay_ms;
 
-    if !output.status.success() {
-        return Err(format!(
-            "Screenshot failed: {}",
-            String::from_utf8_lossy(&output.stderr)
+    while remaining > 0 {
+        let _ = app.emit(
+            "capture-countdown",
+            serde_json::json!({
+                "remaining_ms": remaining,
+                "remaining_secs": remaining.div_ceil(1000),
+            }),
+        );
+
+        // First tick absorbs the fraction so later ones land on whole seconds
+        let step = match remaining % 1000 {
+            0 => 1000,
+            fraction => fraction,
+        };
+        tokio::time::sleep(Duration::from_millis(step)).await;
+        remaining -= step;
+    }
+}
+
+/// How a screenshot ended up on the clipboard
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardFormat {
+    /// Raw image data, pasteable into image editors and chat apps
+    Image,
+    /// A `data:image/png;base64,...` URL, used where images can't be written
+    Text,
+    /// A `text/uri-list` entry pointing at the saved PNG
+    #[serde(rename = "file_uri")]
+    FileUri,
+}
+
+/// What `copy_screenshot_to_clipboard` should put on the clipboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardMode {
+    /// Raw image data, for image editors, browsers and most chat apps
+    #[default]
+    Image,
+    /// The saved file's URI, for file managers (Dolphin, Thunar, Nautilus) and
+    /// apps that only take pasted files as if they were dropped. Linux only.
+    FileUri,
+}
+
+/// Capture the screen or a region and put it on the clipboard.
+///
+/// The PNG is also written to `also_save` when given. With `FileUri` that
+/// file is what the clipboard points at; otherwise a temp file is kept.
+#[tauri::command]
+pub async fn copy_screenshot_to_clipboard(
+    app: AppHandle,
+    region: Option<CaptureRegion>,
+    also_save: Option<String>,
+    clipboard_mode: Option<ClipboardMode>,
+) -> Result<ClipboardFormat, CommandError> {
+    let mode = clipboard_mode.unwrap_or_default();
+    #[cfg(not(target_os = "linux"))]
+    if mode == ClipboardMode::FileUri {
+        return Err(CommandError::unsupported(
+            "Copying screenshots as file URIs is only supported on Linux",
         ));
     }
 
-    // Read the image
-    let image_data = std::fs::read(&final_path)
-        .map_err(|e| format!("Failed to read screenshot: {}", e))?;
+    let img = match region {
+        Some(region) => capture_native_region(region, false).await?.0,
+        None => capture_native(None, false).await?,
+    };
+    let output = OutputOptions::new(None, None)?;
 
-    // Get dimensions using image crate
-    let img = image::load_from_memory(&image_data)
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    #[cfg(target_os = "linux")]
+    if mode == ClipboardMode::FileUri {
+        let path = match also_save {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let dir = temp_screenshot_dir();
+                std::fs::create_dir_all(&dir).map_err(|e| {
+                    CommandError::io("Failed to create screenshot directory").with_details(e)
+                })?;
+                dir.join(format!("clipboard_{}.png", uuid::Uuid::new_v4()))
+            }
+        };
+        std::fs::write(&path, encode_image(&img, &output)?)
+            .map_err(|e| CommandError::io("Failed to save screenshot").with_details(e))?;
 
-    let result = ScreenshotResult {
-        data: STANDARD.encode(&image_data),
-        format: "png".to_string(),
-        width: img.width(),
-        height: img.height(),
-        path: save_path,
+        copy_file_uri_linux(&path)?;
+        return Ok(ClipboardFormat::FileUri);
+    }
+
+    let encoded = match &also_save {
+        Some(path) => {
+            let data = encode_image(&img, &output)?;
+            std::fs::write(path, &data)
+                .map_err(|e| CommandError::io("Failed to save screenshot").with_details(e))?;
+            Some(data)
+        }
+        None => None,
     };
 
-    // Clean up temp file if not saving
-    if save_path.is_none() {
-        let _ = std::fs::remove_file(&temp_path);
+    let rgba = img.to_rgba8();
+    let image = tauri::image::Image::new(rgba.as_raw(), rgba.width(), rgba.height());
+    if app.clipboard().write_image(&image).is_ok() {
+        return Ok(ClipboardFormat::Image);
+    }
+
+    // Some clipboards (e.g. Wayland without data-control) only take text
+    let data = match encoded {
+        Some(data) => data,
+        None => encode_image(&img, &output)?,
+    };
+    app.clipboard()
+        .write_text(format!("data:image/png;base64,{}", STANDARD.encode(&data)))
+        .map_err(|e| CommandError::io("Failed to write to clipboard").with_details(e))?;
+
+    Ok(ClipboardFormat::Text)
+}
+
+/// Capture a single window whose title contains `title_substring`
+///
+/// When several windows match, the frontmost one is captured.
+#[tauri::command]
+pub async fn capture_window(
+    title_substring: String,
+    save_path: Option<String>,
+    thumbnail_max_dim: Option<u32>,
+) -> Result<ScreenshotResult, CommandError> {
+    if title_substring.trim().is_empty() {
+        return Err(CommandError::invalid_input("Window title must not be empty"));
     }
 
+    let mut output = OutputOptions::new(None, None)?;
+    output.thumbnail_max_dim = thumbnail_max_dim;
+    let (img, title) = capture_native_window(&title_substring).await?;
+
+    let mut result = finish_capture(img, save_path, &output)?;
+    result.window_title = Some(title);
     Ok(result)
 }
 
-#[cfg(target_os = "windows")]
-async fn capture_windows_screenshot(
+/// Capture whichever window has focus, skipping Roura Agent's own windows.
+///
+/// If this app is frontmost, the next window down is captured instead. On
+/// Wayland the portal asks the user to pick the window.
+#[tauri::command]
+pub async fn capture_active_window(
     save_path: Option<String>,
-    region: Option<CaptureRegion>,
-) -> Result<ScreenshotResult, String> {
-    // Windows implementation would use win32 API or powershell
-    // For now, return an error suggesting external tools
-    Err("Screenshot capture on Windows requires additional setup. Use Snipping Tool or Win+Shift+S.".to_string())
+) -> Result<ScreenshotResult, CommandError> {
+    let output = OutputOptions::new(None, None)?;
+    let (img, window) = capture_native_active_window().await?;
+
+    let mut result = finish_capture(img, save_path, &output)?;
+    if let Some(window) = window {
+        result.window_title = Some(window.title).filter(|t| !t.is_empty());
+        result.window_app = window.app;
+    }
+    Ok(result)
 }
 
-#[cfg(target_os = "linux")]
-async fn capture_linux_screenshot(
+/// Capture one of this app's own windows by label, `main` by default.
+///
+/// Uses the window's outer rectangle, so title bar and borders are included.
+#[tauri::command]
+pub async fn capture_self_window(
+    app: AppHandle,
+    label: Option<String>,
     save_path: Option<String>,
-    region: Option<CaptureRegion>,
-) -> Result<ScreenshotResult, String> {
-    use std::process::Command;
+) -> Result<ScreenshotResult, CommandError> {
+    let label = label.unwrap_or_else(|| "main".to_string());
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| CommandError::not_found(format!("No window labelled {}", label)))?;
 
-    // Try gnome-screenshot, scrot, or import (ImageMagick)
-    let temp_path = std::env::temp_dir().join(format!("roura_screenshot_{}.png", uuid::Uuid::new_v4()));
+    // A hidden window would capture whatever is behind it
+    if window.is_minimized().unwrap_or(false) || !window.is_visible().unwrap_or(true) {
+        return Err(CommandError::invalid_input(format!("Window {} is not visible", label)));
+    }
 
-    let final_path = if let Some(ref p) = save_path {
-        PathBuf::from(p)
+    // Wayland clients can't learn their global position
+    #[cfg(target_os = "linux")]
+    if is_wayland_session() {
+        return Err(CommandError::unsupported(
+            "Capturing a window by position isn't possible on Wayland",
+        ));
+    }
+
+    let geometry_error =
+        |e: tauri::Error| CommandError::internal("Failed to read window geometry").with_details(e);
+    let position = window.outer_position().map_err(geometry_error)?;
+    let size = window.outer_size().map_err(geometry_error)?;
+    let scale = window.scale_factor().map_err(geometry_error)?;
+
+    // Tauri reports physical pixels; convert only for tools that take points
+    let physical = CaptureRegion {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale_factor: Some(1.0),
+    };
+    let region = if native_region_is_physical() {
+        physical
     } else {
-        temp_path.clone()
+        CaptureRegion {
+            x: (position.x as f64 / scale).round() as i32,
+            y: (position.y as f64 / scale).round() as i32,
+            width: (size.width as f64 / scale).round() as u32,
+            height: (size.height as f64 / scale).round() as u32,
+            scale_factor: Some(scale),
+        }
     };
 
-    // Try different screenshot tools
-    let result = if let Some(r) = &region {
-        // Try scrot with region
-        Command::new("scrot")
-            .arg("-a")
-            .arg(format!("{},{},{},{}", r.x, r.y, r.width, r.height))
-            .arg(&final_path)
-            .output()
-    } else {
-        // Try gnome-screenshot first, then scrot
-        let gnome_result = Command::new("gnome-screenshot")
-            .arg("-f")
-            .arg(&final_path)
-            .output();
+    let output = OutputOptions::new(None, None)?;
+    let img = capture_native(Some(region), false).await?;
 
-        if gnome_result.is_ok() && gnome_result.as_ref().unwrap().status.success() {
-            gnome_result
-        } else {
-            Command::new("scrot")
-                .arg(&final_path)
-                .output()
+    let mut result = finish_capture(img, save_path, &output)?;
+    result.set_scale(scale);
+    result.window_title = window.title().ok().filter(|t| !t.is_empty());
+    Ok(result)
+}
+
+/// Most frames `capture_scrolling_window` takes, whatever is asked for
+const MAX_SCROLL_FRAMES: u32 = 50;
+
+/// Time for scrolled content to redraw before the next frame
+const SCROLL_SETTLE: Duration = Duration::from_millis(300);
+
+/// Capture a window whose title contains `title_substring` top to bottom.
+///
+/// Scrolls by `scroll_step_px` between frames until the content stops
+/// changing or `max_frames` (capped at `MAX_SCROLL_FRAMES`) are taken, then
+/// stitches the frames into one tall image. Rows that stay put between
+/// frames, like toolbars, appear once. The pointer is put back where it was.
+#[tauri::command]
+pub async fn capture_scrolling_window(
+    title_substring: String,
+    scroll_step_px: u32,
+    max_frames: u32,
+    save_path: Option<String>,
+) -> Result<ScreenshotResult, CommandError> {
+    if title_substring.trim().is_empty() {
+        return Err(CommandError::invalid_input("Window title must not be empty"));
+    }
+    if scroll_step_px == 0 {
+        return Err(CommandError::invalid_input("Scroll step must be at least 1 pixel"));
+    }
+    let max_frames = max_frames.clamp(1, MAX_SCROLL_FRAMES) as usize;
+    let output = OutputOptions::new(None, None)?;
+
+    // Every step runs a tool or sleeps, so keep the whole loop off the async runtime
+    let (title, stitched) = tokio::task::spawn_blocking(move || {
+        let target = find_scroll_target(&title_substring)?;
+        let frames = capture_scroll_frames(&target, scroll_step_px, max_frames)?;
+        Ok::<_, CommandError>((target.title, stitch_frames(&frames)))
+    })
+    .await
+    .map_err(|e| CommandError::io("Screenshot task failed").with_details(e))??;
+
+    let img = image::DynamicImage::ImageRgba8(stitched);
+    let mut result = finish_capture(img, save_path, &output)?;
+    result.window_title = Some(title).filter(|t| !t.is_empty());
+    Ok(result)
+}
+
+/// Scroll `target` and capture it after each step, with each frame's row hashes
+fn capture_scroll_frames(
+    target: &ScrollTarget,
+    step_px: u32,
+    max_frames: usize,
+) -> Result<Vec<ScrollFrame>, CommandError> {
+    // Scrolling moves the real pointer; this puts it back however the loop ends
+    let _pointer = SavedPointer::save();
+
+    let first = capture_scroll_frame(target)?;
+    let mut frames = vec![(row_hashes(&first), first)];
+    while frames.len() < max_frames {
+        scroll_target(target, step_px)?;
+        std::thread::sleep(SCROLL_SETTLE);
+
+        let frame = capture_scroll_frame(target)?;
+        let hashes = row_hashes(&frame);
+        // Reached the end once scrolling changes nothing; a resize ends it too
+        let (last_hashes, last) = &frames[frames.len() - 1];
+        if frame.dimensions() != last.dimensions() || hashes == *last_hashes {
+            break;
         }
-    };
+        frames.push((hashes, frame));
+    }
 
-    let output = result.map_err(|e| format!("Failed to run screenshot tool: {}", e))?;
+    Ok(frames)
+}
 
-    if !output.status.success() {
-        return Err(format!(
-            "Screenshot failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+/// A captured frame with the hash of each of its rows
+type ScrollFrame = (Vec<u64>, image::RgbaImage);
+
+/// Hash of each pixel row, for spotting unchanged frames and overlaps
+fn row_hashes(img: &image::RgbaImage) -> Vec<u64> {
+    use std::hash::{Hash, Hasher};
+
+    let row_len = (img.width() as usize * 4).max(1);
+    img.as_raw()
+        .chunks(row_len)
+        .map(|row| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            row.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+/// Join same-sized frames, each scrolled further than the last, into one image.
+///
+/// Rows identical in place across the first two frames are fixed chrome and
+/// bound the scrolling viewport. Each later frame adds only the viewport rows
+/// not already shown, found as the smallest shift that lines it up with the
+/// previous frame; with no overlap the whole viewport is appended.
+fn stitch_frames(frames: &[ScrollFrame]) -> image::RgbaImage {
+    let (first_rows, first) = &frames[0];
+    if frames.len() == 1 {
+        return first.clone();
     }
 
-    // Read the image
-    let image_data = std::fs::read(&final_path)
-        .map_err(|e| format!("Failed to read screenshot: {}", e))?;
+    let (width, height) = first.dimensions();
+    let second_rows = &frames[1].0;
+    let top = first_rows.iter().zip(second_rows).take_while(|(a, b)| a == b).count();
+    let bottom = first_rows[top..]
+        .iter()
+        .rev()
+        .zip(second_rows[top..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (view_start, view_end) = (top, height as usize - bottom);
+    let view_len = view_end - view_start;
 
-    let img = image::load_from_memory(&image_data)
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    // (frame, first row, end row) of each strip, top to bottom
+    let mut strips = vec![(0, 0, view_end)];
+    for i in 1..frames.len() {
+        let prev = &frames[i - 1].0[view_start..view_end];
+        let next = &frames[i].0[view_start..view_end];
+        let shift = (1..view_len)
+            .find(|&s| prev[s..] == next[..view_len - s])
+            .unwrap_or(view_len);
+        strips.push((i, view_end - shift, view_end));
+    }
+    strips.push((frames.len() - 1, view_end, height as usize));
 
-    let result = ScreenshotResult {
-        data: STANDARD.encode(&image_data),
-        format: "png".to_string(),
-        width: img.width(),
-        height: img.height(),
-        path: save_path,
+    let total: usize = strips.iter().map(|(_, from, to)| to - from).sum();
+    let mut stitched = image::RgbaImage::new(width, total as u32);
+    let mut y = 0;
+    for (frame, from, to) in strips {
+        let strip =
+            image::imageops::crop_imm(&frames[frame].1, 0, from as u32, width, (to - from) as u32)
+                .to_image();
+        image::imageops::replace(&mut stitched, &strip, 0, y as i64);
+        y += to - from;
+    }
+    stitched
+}
+
+/// Title and owning application of a captured window
+struct WindowLabel {
+    title: String,
+    app: Option<String>,
+}
+
+/// Rectangle in image pixels; parts outside the image are ignored
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnnotateRect {
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AnnotateRect {
+    /// Clamp to a `width` x `height` image, as (x, y, width, height); `None` if nothing is left
+    fn clamp(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let (width, height) = (width as i64, height as i64);
+        let x0 = self.x.clamp(0, width);
+        let y0 = self.y.clamp(0, height);
+        let x1 = self.x.saturating_add(self.width as i64).clamp(0, width);
+        let y1 = self.y.saturating_add(self.height as i64).clamp(0, height);
+
+        (x1 > x0 && y1 > y0).then(|| (x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+}
+
+/// One edit applied by `annotate_screenshot`. Colors are `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnnotateOp {
+    /// Gaussian blur, for redacting text
+    Blur {
+        rect: AnnotateRect,
+        #[serde(default)]
+        sigma: Option<f32>,
+    },
+    /// Outline, for highlighting
+    Rectangle {
+        rect: AnnotateRect,
+        color: String,
+        #[serde(default)]
+        thickness: Option<u32>,
+    },
+    /// Solid box, blended by the color's alpha
+    Fill { rect: AnnotateRect, color: String },
+}
+
+/// Blur strength when none is given; strong enough that text can't be read back
+const DEFAULT_BLUR_SIGMA: f32 = 12.0;
+/// Outline width in pixels when none is given
+const DEFAULT_OUTLINE_THICKNESS: u32 = 3;
+
+/// Apply `ops` in order to an image and return the result as PNG.
+///
+/// `source` is a file path, base64 data, or a `data:` URL.
+#[tauri::command]
+pub async fn annotate_screenshot(
+    source: String,
+    ops: Vec<AnnotateOp>,
+) -> Result<ScreenshotResult, CommandError> {
+    let mut img = load_source_image(&source)?.to_rgba8();
+
+    for op in &ops {
+        apply_annotation(&mut img, op)?;
+    }
+
+    let output = OutputOptions::new(None, None)?;
+    finish_capture(image::DynamicImage::ImageRgba8(img), None, &output)
+}
+
+/// Decode an image given as a file path, base64 data, or a `data:` URL
+fn load_source_image(source: &str) -> Result<image::DynamicImage, CommandError> {
+    let bytes = match source.strip_prefix("data:") {
+        Some(url) => {
+            let (_, data) = url
+                .split_once(";base64,")
+                .ok_or_else(|| CommandError::invalid_input("Only base64 data URLs are supported"))?;
+            decode_base64(data)?
+        }
+        None if Path::new(source).is_file() => std::fs::read(source)
+            .map_err(|e| CommandError::io("Failed to read screenshot").with_details(e))?,
+        None => decode_base64(source)?,
     };
 
-    // Clean up temp file if not saving
-    if save_path.is_none() {
-        let _ = std::fs::remove_file(&temp_path);
+    image::load_from_memory(&bytes)
+        .map_err(|e| CommandError::parse("Failed to decode image").with_details(e))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, CommandError> {
+    STANDARD
+        .decode(data.trim())
+        .map_err(|e| {
+            CommandError::invalid_input("Source is neither a file nor base64 data").with_details(e)
+        })
+}
+
+fn apply_annotation(img: &mut image::RgbaImage, op: &AnnotateOp) -> Result<(), CommandError> {
+    use image::GenericImageView;
+
+    let (width, height) = img.dimensions();
+
+    match op {
+        AnnotateOp::Blur { rect, sigma } => {
+            let Some((x, y, w, h)) = rect.clamp(width, height) else {
+                return Ok(());
+            };
+            let region = img.view(x, y, w, h).to_image();
+            let blurred = image::imageops::blur(&region, sigma.unwrap_or(DEFAULT_BLUR_SIGMA));
+            image::imageops::replace(img, &blurred, x as i64, y as i64);
+        }
+        AnnotateOp::Fill { rect, color } => {
+            let color = parse_color(color)?;
+            if let Some(bounds) = rect.clamp(width, height) {
+                blend_rect(img, bounds, color);
+            }
+        }
+        AnnotateOp::Rectangle {
+            rect,
+            color,
+            thickness,
+        } => {
+            let color = parse_color(color)?;
+            let Some((x, y, w, h)) = rect.clamp(width, height) else {
+                return Ok(());
+            };
+            let t = thickness.unwrap_or(DEFAULT_OUTLINE_THICKNESS).max(1);
+            let (tw, th) = (t.min(w), t.min(h));
+
+            // Top and bottom span the full width; the sides fill in between
+            blend_rect(img, (x, y, w, th), color);
+            blend_rect(img, (x, y + h - th, w, th), color);
+            if h > 2 * th {
+                blend_rect(img, (x, y + th, tw, h - 2 * th), color);
+                blend_rect(img, (x + w - tw, y + th, tw, h - 2 * th), color);
+            }
+        }
     }
 
-    Ok(result)
+    Ok(())
+}
+
+/// Alpha-blend `color` over an already clamped rectangle
+fn blend_rect(
+    img: &mut image::RgbaImage,
+    (x, y, w, h): (u32, u32, u32, u32),
+    color: image::Rgba<u8>,
+) {
+    use image::Pixel;
+
+    for py in y..y + h {
+        for px in x..x + w {
+            img.get_pixel_mut(px, py).blend(&color);
+        }
+    }
+}
+
+/// Parse `#rrggbb` or `#rrggbbaa`
+fn parse_color(color: &str) -> Result<image::Rgba<u8>, CommandError> {
+    let invalid = || CommandError::invalid_input(format!("Invalid color: {}", color));
+
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Ok(image::Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+/// A line of text found by `ocr_screenshot`, with its box in image pixels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Mean word confidence, 0 to 100
+    pub confidence: f32,
+}
+
+/// Text extracted from an image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrResult {
+    /// All lines, joined with newlines
+    pub text: String,
+    pub lines: Vec<OcrLine>,
+    /// Tesseract language codes used, e.g. "eng" or "eng+deu"
+    pub lang: String,
+}
+
+/// Language used when `ocr_screenshot` isn't given one
+const DEFAULT_OCR_LANG: &str = "eng";
+
+/// Extract text from an image with the `tesseract` binary.
+///
+/// `source` is a file path, base64 data, or a `data:` URL. `lang` takes
+/// Tesseract codes, joined with `+` for several languages.
+#[tauri::command]
+pub async fn ocr_screenshot(
+    source: String,
+    lang: Option<String>,
+) -> Result<OcrResult, CommandError> {
+    let lang = lang
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| DEFAULT_OCR_LANG.to_string());
+    if !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+') {
+        return Err(CommandError::invalid_input(format!("Invalid OCR language: {}", lang)));
+    }
+
+    // Re-encoded as PNG, since tesseract can't read every format we accept
+    let img = load_source_image(&source)?;
+    let temp_path = temp_capture_path();
+    img.save_with_format(&temp_path, image::ImageFormat::Png)
+        .map_err(|e| CommandError::io("Failed to write OCR input").with_details(e))?;
+
+    let output = tokio::task::spawn_blocking({
+        let temp_path = temp_path.clone();
+        let lang = lang.clone();
+        move || {
+            std::process::Command::new("tesseract")
+                .arg(&temp_path)
+                .args(["stdout", "-l", &lang, "tsv"])
+                .output()
+        }
+    })
+    .await
+    .map_err(|e| CommandError::internal("OCR task failed").with_details(e))?;
+    let _ = std::fs::remove_file(&temp_path);
+
+    let output = output.map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => CommandError::unsupported(
+            "Tesseract is not installed; install it (e.g. `brew install tesseract` or \
+             `apt install tesseract-ocr`) and make sure `tesseract` is on PATH",
+        ),
+        _ => CommandError::io("Failed to run tesseract").with_details(e),
+    })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("Failed loading language") {
+            return Err(CommandError::not_found(format!(
+                "Tesseract has no data for language {}; install its traineddata package",
+                lang
+            ))
+            .with_details(stderr));
+        }
+        return Err(CommandError::io("Tesseract failed").with_details(stderr));
+    }
+
+    let lines = parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout));
+    let text = lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(OcrResult { text, lines, lang })
+}
+
+/// Tesseract's page, block, paragraph and line numbers, which together identify a line
+type TsvLineKey = (u32, u32, u32, u32);
+
+/// Group the words in tesseract's TSV output into lines, in reading order
+fn parse_tesseract_tsv(tsv: &str) -> Vec<OcrLine> {
+    // Columns: level page block par line word left top width height conf text
+    // Each line is built up with its key and word count, for the mean confidence
+    let mut lines: Vec<(TsvLineKey, OcrLine, usize)> = Vec::new();
+
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.splitn(12, '\t').collect();
+        if cols.len() < 12 || cols[0] != "5" {
+            continue;
+        }
+        let word = cols[11].trim();
+        if word.is_empty() {
+            continue;
+        }
+        let num = |i: usize| cols[i].parse::<u32>().unwrap_or(0);
+        let key = (num(1), num(2), num(3), num(4));
+        let (left, top, width, height) = (num(6), num(7), num(8), num(9));
+        let confidence = cols[10].parse::<f32>().unwrap_or(0.0).max(0.0);
+
+        match lines.last_mut().filter(|(k, _, _)| *k == key) {
+            Some((_, line, words)) => {
+                let right = (line.x + line.width).max(left + width);
+                let bottom = (line.y + line.height).max(top + height);
+                line.x = line.x.min(left);
+                line.y = line.y.min(top);
+                line.width = right - line.x;
+                line.height = bottom - line.y;
+                line.text.push(' ');
+                line.text.push_str(word);
+                line.confidence += confidence;
+                *words += 1;
+            }
+            None => lines.push((
+                key,
+                OcrLine {
+                    text: word.to_string(),
+                    x: left,
+                    y: top,
+                    width,
+                    height,
+                    confidence,
+                },
+                1,
+            )),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(_, mut line, words)| {
+            line.confidence /= words as f32;
+            line
+        })
+        .collect()
+}
+
+/// Command wrapper around [`capture_interactive_native`].
+#[tauri::command]
+pub async fn capture_interactive(
+    save_path: Option<String>,
+) -> Result<Option<ScreenshotResult>, CommandError> {
+    capture_interactive_native(save_path).await
+}
+
+/// Let the user draw a selection with the platform's native picker.
+///
+/// Returns `Ok(None)` when the user cancels with Esc.
+pub(crate) async fn capture_interactive_native(
+    save_path: Option<String>,
+) -> Result<Option<ScreenshotResult>, CommandError> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = save_path;
+        Err(CommandError::unsupported("Interactive capture is not supported on Windows yet"))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let output = OutputOptions::new(None, None)?;
+        let temp_path = temp_capture_path();
+
+        // The picker waits on the user, so keep it off the async runtime
+        let img = tokio::task::spawn_blocking(move || pick_native_region(&temp_path))
+            .await
+            .map_err(|e| CommandError::io("Screenshot task failed").with_details(e))??;
+
+        match img {
+            Some(img) => finish_capture(img, save_path, &output).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Run the native picker, capturing through `temp_path`; `Ok(None)` if cancelled
+#[cfg(not(target_os = "windows"))]
+fn pick_native_region(temp_path: &Path) -> Result<Option<image::DynamicImage>, CommandError> {
+    use std::process::Command;
+
+    // scrot can't grab the screen under Wayland
+    #[cfg(target_os = "linux")]
+    if is_wayland_session() {
+        return select_wayland_region(temp_path);
+    }
+
+    #[cfg(target_os = "macos")]
+    let mut cmd = Command::new("screencapture");
+    #[cfg(target_os = "macos")]
+    cmd.arg("-x").arg("-i");
+
+    #[cfg(target_os = "linux")]
+    let mut cmd = Command::new("scrot");
+    #[cfg(target_os = "linux")]
+    cmd.arg("-s");
+
+    cmd.arg(temp_path)
+        .status()
+        .map_err(|e| CommandError::io("Failed to run screenshot tool").with_details(e))?;
+
+    // Cancelling with Esc leaves no file behind
+    if !temp_path.exists() {
+        return Ok(None);
+    }
+
+    let img = load_captured_file(temp_path);
+    let _ = std::fs::remove_file(temp_path);
+    img.map(Some)
+}
+
+/// Capture the screen (or a region of it) with the platform's native tooling
+async fn capture_native(
+    region: Option<CaptureRegion>,
+    include_cursor: bool,
+) -> Result<image::DynamicImage, CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        capture_macos_screenshot(region, include_cursor).await
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // BitBlt never draws the cursor
+        let _ = include_cursor;
+        capture_windows_screenshot(region).await
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        capture_linux_screenshot(region, include_cursor).await
+    }
+}
+
+// Flags for each native capture tool, up to but not including the output path.
+// Compiled into tests on every platform so the flag sets can be checked anywhere.
+
+/// `screencapture` flags for the whole screen, a `region` in points, or a 1-based `display`
+#[cfg(any(target_os = "macos", test))]
+fn screencapture_args(
+    include_cursor: bool,
+    region: Option<&CaptureRegion>,
+    display: Option<usize>,
+) -> Vec<String> {
+    // No shutter sound
+    let mut args = vec!["-x".to_string()];
+    if include_cursor {
+        args.push("-C".to_string());
+    }
+    if let Some(r) = region {
+        args.push("-R".to_string());
+        args.push(format!("{},{},{},{}", r.x, r.y, r.width, r.height));
+    }
+    if let Some(display) = display {
+        args.push("-D".to_string());
+        args.push(display.to_string());
+    }
+    args
+}
+
+/// `scrot` flags for the whole screen or a `region` in physical pixels
+#[cfg(any(target_os = "linux", test))]
+fn scrot_args(include_cursor: bool, region: Option<&CaptureRegion>) -> Vec<String> {
+    let mut args = Vec::new();
+    if include_cursor {
+        args.push("--pointer".to_string());
+    }
+    if let Some(r) = region {
+        args.push("-a".to_string());
+        args.push(format!("{},{},{},{}", r.x, r.y, r.width, r.height));
+    }
+    args
+}
+
+/// `gnome-screenshot` flags for the whole screen; the path follows `-f`
+#[cfg(any(target_os = "linux", test))]
+fn gnome_screenshot_args(include_cursor: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if include_cursor {
+        args.push("--include-pointer".to_string());
+    }
+    args.push("-f".to_string());
+    args
+}
+
+/// `grim` flags for the whole screen or a `region` in logical pixels
+#[cfg(any(target_os = "linux", test))]
+fn grim_args(include_cursor: bool, region: Option<&CaptureRegion>) -> Vec<String> {
+    let mut args = Vec::new();
+    if include_cursor {
+        args.push("-c".to_string());
+    }
+    if let Some(r) = region {
+        args.push("-g".to_string());
+        args.push(format!("{},{} {}x{}", r.x, r.y, r.width, r.height));
+    }
+    args
+}
+
+/// Capture a single display with the platform's native tooling
+async fn capture_native_display(
+    display: &DisplayInfo,
+    include_cursor: bool,
+) -> Result<image::DynamicImage, CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        use std::process::Command;
+
+        // screencapture numbers displays from 1, in NSScreen order
+        let temp_path = temp_capture_path();
+        let mut cmd = Command::new("screencapture");
+        cmd.args(screencapture_args(
+            include_cursor,
+            None,
+            Some(display.index + 1),
+        ))
+        .arg(&temp_path);
+        run_capture_tool(&mut cmd, &temp_path)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let logical = CaptureRegion {
+            x: display.x,
+            y: display.y,
+            width: display.width,
+            height: display.height,
+            scale_factor: Some(display.scale_factor),
+        };
+        let region = if native_region_is_physical() {
+            logical.to_physical(display.scale_factor)
+        } else {
+            logical
+        };
+        capture_native(Some(region), include_cursor).await
+    }
+}
+
+/// Capture each of `displays` (sorted by index) with a single native capture
+async fn capture_native_displays(
+    displays: &[DisplayInfo],
+) -> Result<Vec<image::DynamicImage>, CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        // Given one file per display, screencapture grabs them all at once
+        let paths: Vec<PathBuf> = displays.iter().map(|_| temp_capture_path()).collect();
+        let output = std::process::Command::new("screencapture")
+            .arg("-x")
+            .args(&paths)
+            .output()
+            .map_err(|e| CommandError::io("Failed to run screenshot tool").with_details(e))?;
+
+        let images = if output.status.success() {
+            paths.iter().map(|p| load_captured_file(p)).collect()
+        } else {
+            Err(CommandError::io("Screenshot failed")
+                .with_details(String::from_utf8_lossy(&output.stderr)))
+        };
+        for path in &paths {
+            let _ = std::fs::remove_file(path);
+        }
+        images
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        // The full capture spans the whole desktop, so each display is a crop of it
+        let desktop = capture_native(None, false).await?;
+
+        Ok(display_crops(displays)
+            .iter()
+            .map(|c| desktop.crop_imm(c.x as u32, c.y as u32, c.width, c.height))
+            .collect())
+    }
+}
+
+/// Where each display sits in a full desktop capture, in the capture's physical pixels.
+///
+/// Display bounds are logical, so they're offset from the desktop origin and
+/// scaled by the display's own scale factor.
+#[cfg(any(not(target_os = "macos"), test))]
+fn display_crops(displays: &[DisplayInfo]) -> Vec<CaptureRegion> {
+    let origin_x = displays.iter().map(|d| d.x).min().unwrap_or(0);
+    let origin_y = displays.iter().map(|d| d.y).min().unwrap_or(0);
+
+    displays
+        .iter()
+        .map(|d| {
+            let logical = CaptureRegion {
+                x: d.x - origin_x,
+                y: d.y - origin_y,
+                width: d.width,
+                height: d.height,
+                scale_factor: Some(d.scale_factor),
+            };
+            logical.to_physical(logical.scale())
+        })
+        .collect()
+}
+
+/// Enumerate displays with the platform's native tooling
+fn list_native_displays() -> Result<Vec<DisplayInfo>, CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        list_macos_displays()
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        list_windows_displays()
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        list_linux_displays()
+    }
+}
+
+/// Capture a window by title with the platform's native tooling
+async fn capture_native_window(
+    title_substring: &str,
+) -> Result<(image::DynamicImage, String), CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        capture_macos_window(title_substring).await
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = title_substring;
+        Err(CommandError::unsupported("Window capture is not supported on Windows yet"))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        capture_linux_window(title_substring).await
+    }
+}
+
+/// Capture the focused window with the platform's native tooling
+async fn capture_native_active_window(
+) -> Result<(image::DynamicImage, Option<WindowLabel>), CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        capture_macos_active_window().await
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Err(CommandError::unsupported("Window capture is not supported on Windows yet"))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        capture_linux_active_window().await
+    }
+}
+
+#[cfg(target_os = "windows")]
+struct ScrollTarget {
+    title: String,
+}
+
+#[cfg(target_os = "windows")]
+fn find_scroll_target(_title_substring: &str) -> Result<ScrollTarget, CommandError> {
+    Err(CommandError::unsupported("Window capture is not supported on Windows yet"))
+}
+
+#[cfg(target_os = "windows")]
+fn capture_scroll_frame(_target: &ScrollTarget) -> Result<image::RgbaImage, CommandError> {
+    Err(CommandError::unsupported("Window capture is not supported on Windows yet"))
+}
+
+#[cfg(target_os = "windows")]
+fn scroll_target(_target: &ScrollTarget, _step_px: u32) -> Result<(), CommandError> {
+    Err(CommandError::unsupported("Window capture is not supported on Windows yet"))
+}
+
+/// Nothing scrolls on Windows yet, so there's no pointer to put back
+#[cfg(target_os = "windows")]
+struct SavedPointer;
+
+#[cfg(target_os = "windows")]
+impl SavedPointer {
+    fn save() -> Option<Self> {
+        Some(Self)
+    }
+}
+
+/// Encode a captured image, save it if requested, and build the result.
+///
+/// A known extension on `save_path` overrides the requested format so the
+/// file's bytes match its name; any other path gets the format's extension appended.
+fn finish_capture(
+    img: image::DynamicImage,
+    save_path: Option<String>,
+    output: &OutputOptions,
+) -> Result<ScreenshotResult, CommandError> {
+    let mut output = output.clone();
+    let extension_format = save_path
+        .as_deref()
+        .and_then(|path| Path::new(path).extension()?.to_str())
+        .and_then(OutputFormat::from_name);
+    let (format, warning) = extension_format.unwrap_or(output.format).or_fallback();
+    output.format = format;
+    let warning = warning.or_else(|| output.lossless_webp_warning());
+    let save_path = save_path.map(|path| {
+        if extension_format == Some(format) {
+            path
+        } else {
+            format!("{}.{}", path, format.as_str())
+        }
+    });
+    let output = &output;
+
+    let image_data = encode_image(&img, output)?;
+
+    if let Some(ref p) = save_path {
+        std::fs::write(PathBuf::from(p), &image_data)
+            .map_err(|e| CommandError::io("Failed to save screenshot").with_details(e))?;
+    }
+
+    // These are the exact bytes written, so they describe the saved file too
+    let sha256 = output.hash.then(|| {
+        use sha2::Digest;
+        format!("{:x}", sha2::Sha256::digest(&image_data))
+    });
+
+    let thumbnail = match output.thumbnail_max_dim {
+        Some(max_dim) => Some(STANDARD.encode(encode_image(&downscale(&img, max_dim), output)?)),
+        None => None,
+    };
+
+    Ok(ScreenshotResult {
+        data: output.inline.then(|| STANDARD.encode(&image_data)),
+        thumbnail,
+        format: output.format.as_str().to_string(),
+        warning,
+        width: img.width(),
+        height: img.height(),
+        path: save_path,
+        byte_size: image_data.len() as u64,
+        sha256,
+        window_title: None,
+        window_app: None,
+        display: None,
+        display_bounds: None,
+        scale_factor: 1.0,
+        logical_width: img.width(),
+        logical_height: img.height(),
+    })
+}
+
+/// Downscale `img` so its longest side is at most `max_dim`, keeping the aspect ratio
+pub(crate) fn downscale(img: &image::DynamicImage, max_dim: u32) -> image::DynamicImage {
+    let max_dim = max_dim.max(1);
+    if img.width() <= max_dim && img.height() <= max_dim {
+        return img.clone();
+    }
+
+    // Triangle is much faster than Lanczos and looks the same at preview sizes
+    img.resize(max_dim, max_dim, image::imageops::FilterType::Triangle)
+}
+
+/// Encode an image in the requested output format
+fn encode_image(
+    img: &image::DynamicImage,
+    output: &OutputOptions,
+) -> Result<Vec<u8>, CommandError> {
+    let mut image_data = Vec::new();
+
+    match output.format {
+        OutputFormat::Png => img
+            .write_to(
+                &mut std::io::Cursor::new(&mut image_data),
+                image::ImageOutputFormat::Png,
+            )
+            .map_err(|e| CommandError::internal("Failed to encode image").with_details(e))?,
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let mut encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut image_data, output.quality);
+            encoder
+                .encode_image(&img.to_rgb8())
+                .map_err(|e| CommandError::internal("Failed to encode image").with_details(e))?;
+        }
+        OutputFormat::WebP => {
+            // Lossy WebP needs libwebp; the pure-Rust encoder is lossless only
+            let rgba = img.to_rgba8();
+            #[cfg(feature = "webp-lossy")]
+            let encoder = image::codecs::webp::WebPEncoder::new_with_quality(
+                &mut image_data,
+                image::codecs::webp::WebPQuality::lossy(output.quality),
+            );
+            #[cfg(not(feature = "webp-lossy"))]
+            let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut image_data);
+            encoder
+                .encode(rgba.as_raw(), rgba.width(), rgba.height(), image::ColorType::Rgba8)
+                .map_err(|e| CommandError::internal("Failed to encode image").with_details(e))?;
+        }
+        #[cfg(feature = "avif")]
+        OutputFormat::Avif => {
+            use image::ImageEncoder;
+
+            // Speed 8 of 10 keeps capture latency low at a small size cost
+            let rgba = img.to_rgba8();
+            image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                &mut image_data,
+                8,
+                output.quality,
+            )
+            .write_image(rgba.as_raw(), rgba.width(), rgba.height(), image::ColorType::Rgba8)
+            .map_err(|e| CommandError::internal("Failed to encode image").with_details(e))?;
+        }
+        #[cfg(not(feature = "avif"))]
+        OutputFormat::Avif => {
+            return Err(CommandError::unsupported("AVIF support is not included in this build"));
+        }
+        OutputFormat::Bmp => img
+            .write_to(
+                &mut std::io::Cursor::new(&mut image_data),
+                image::ImageOutputFormat::Bmp,
+            )
+            .map_err(|e| CommandError::internal("Failed to encode image").with_details(e))?,
+    }
+
+    Ok(image_data)
+}
+
+/// Directory holding captures returned by path instead of inline data
+fn temp_screenshot_dir() -> PathBuf {
+    std::env::temp_dir().join("roura-agent-screenshots")
+}
+
+/// Temp captures older than this are removed by the startup sweep
+const TEMP_SCREENSHOT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Native tool output older than this is assumed orphaned, unless
+/// `screenshots.temp_max_age_minutes` says otherwise
+const DEFAULT_CAPTURE_LEFTOVER_MINUTES: u64 = 60;
+/// Prefix of the files native capture tools write into the system temp dir
+const CAPTURE_TEMP_PREFIX: &str = "roura_screenshot_";
+
+/// Fresh path in the temp screenshot directory for a path-only capture
+fn new_temp_screenshot_path(output: &OutputOptions) -> Result<String, CommandError> {
+    let dir = temp_screenshot_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| CommandError::io("Failed to create screenshot directory").with_details(e))?;
+
+    let extension = output.format.or_fallback().0.as_str();
+    let path = dir.join(format!("{}.{}", uuid::Uuid::new_v4(), extension));
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Expose the temp screenshot directory to the asset protocol and sweep old captures
+pub(crate) fn init_temp_dir(app: &AppHandle) -> Result<(), CommandError> {
+    use tauri::Manager;
+
+    // Captures interrupted by a crash leave their tool output behind
+    let leftover_age = crate::commands::config_value("screenshots.temp_max_age_minutes")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_CAPTURE_LEFTOVER_MINUTES);
+    tauri::async_runtime::spawn_blocking(move || {
+        let removed = sweep_old_files(
+            &std::env::temp_dir(),
+            Some(CAPTURE_TEMP_PREFIX),
+            Duration::from_secs(leftover_age * 60),
+        );
+        if removed > 0 {
+            tracing::info!(removed, "Removed leftover screenshot temp files");
+        }
+    });
+
+    let dir = temp_screenshot_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| CommandError::io("Failed to create screenshot directory").with_details(e))?;
+    app.asset_protocol_scope()
+        .allow_directory(&dir, false)
+        .map_err(|e| CommandError::internal("Failed to allow screenshot directory").with_details(e))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        sweep_old_files(&dir, None, TEMP_SCREENSHOT_MAX_AGE);
+    });
+
+    Ok(())
+}
+
+/// Delete files in `dir` older than `max_age`, optionally only those named with `prefix`.
+///
+/// Returns how many were removed; an unreadable directory removes nothing.
+fn sweep_old_files(dir: &Path, prefix: Option<&str>, max_age: Duration) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let matches = prefix.is_none_or(|p| entry.file_name().to_string_lossy().starts_with(p));
+        if !matches || !entry.file_type().is_ok_and(|t| t.is_file()) {
+            continue;
+        }
+
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if expired && std::fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+
+    removed
+}
+
+/// Delete a temp capture returned with `inline: false`, returning whether it existed.
+///
+/// Only files inside the temp screenshot directory can be removed.
+#[tauri::command]
+pub async fn cleanup_screenshot(path: String) -> Result<bool, CommandError> {
+    let path = match std::fs::canonicalize(&path) {
+        Ok(path) => path,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(CommandError::io("Failed to resolve screenshot path").with_details(e)),
+    };
+
+    let in_temp_dir = std::fs::canonicalize(temp_screenshot_dir())
+        .is_ok_and(|dir| path.parent() == Some(dir.as_path()));
+    if !in_temp_dir {
+        return Err(CommandError::invalid_input(format!(
+            "Not a temporary screenshot: {}",
+            path.display()
+        )));
+    }
+
+    std::fs::remove_file(&path)
+        .map_err(|e| CommandError::io("Failed to remove screenshot").with_details(e))?;
+
+    Ok(true)
+}
+
+/// A screenshot kept in a project's gallery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GalleryEntry {
+    pub id: String,
+    /// Absolute path of the stored PNG
+    pub path: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Layout of `screenshots.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GalleryIndex {
+    #[serde(default)]
+    screenshots: Vec<GalleryEntry>,
+}
+
+/// Directory holding a project's gallery images and index
+fn gallery_dir(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path).join(".roura").join("screenshots")
+}
+
+fn load_gallery(dir: &Path) -> Result<GalleryIndex, CommandError> {
+    let path = dir.join("screenshots.json");
+    if !path.exists() {
+        return Ok(GalleryIndex::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| CommandError::io("Failed to read screenshot index").with_details(e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| CommandError::parse("Failed to parse screenshot index").with_details(e))
+}
+
+/// One lock per gallery directory, held while its index is read, updated and written
+static GALLERY_LOCKS: std::sync::Mutex<BTreeMap<PathBuf, Arc<std::sync::Mutex<()>>>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+/// Store a screenshot in `<project>/.roura/screenshots` and record it in the index.
+///
+/// `source` is a file path, base64 data, or a `data:` URL; it is always kept as PNG.
+#[tauri::command]
+pub async fn save_screenshot_to_project(
+    project_path: String,
+    source: String,
+    tags: Vec<String>,
+    note: Option<String>,
+) -> Result<GalleryEntry, CommandError> {
+    if !Path::new(&project_path).is_dir() {
+        return Err(CommandError::not_found(format!(
+            "Project does not exist: {}",
+            project_path
+        )));
+    }
+
+    let img = load_source_image(&source)?;
+    let dir = gallery_dir(&project_path);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| CommandError::io("Failed to create screenshot gallery").with_details(e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let image_path = dir.join(format!("{}.png", id));
+    img.save_with_format(&image_path, image::ImageFormat::Png)
+        .map_err(|e| CommandError::io("Failed to save screenshot").with_details(e))?;
+
+    let mut unique_tags: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !unique_tags.iter().any(|t| t == tag) {
+            unique_tags.push(tag.to_string());
+        }
+    }
+
+    let entry = GalleryEntry {
+        id,
+        path: image_path.to_string_lossy().into_owned(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        tags: unique_tags,
+        note: note.filter(|n| !n.trim().is_empty()),
+        width: img.width(),
+        height: img.height(),
+    };
+
+    let lock = GALLERY_LOCKS
+        .lock()
+        .map_err(CommandError::poisoned)?
+        .entry(dir.clone())
+        .or_default()
+        .clone();
+    let _guard = lock.lock().map_err(CommandError::poisoned)?;
+
+    let mut index = load_gallery(&dir)?;
+    index.screenshots.push(entry.clone());
+    if let Err(e) = crate::commands::write_json_atomic(&dir.join("screenshots.json"), &index) {
+        // Don't leave an image the index doesn't know about
+        let _ = std::fs::remove_file(&image_path);
+        return Err(e);
+    }
+
+    Ok(entry)
+}
+
+/// A project's gallery screenshots, newest first, optionally only those with `tag`
+#[tauri::command]
+pub async fn list_project_screenshots(
+    project_path: String,
+    tag: Option<String>,
+) -> Result<Vec<GalleryEntry>, CommandError> {
+    let mut entries = load_gallery(&gallery_dir(&project_path))?.screenshots;
+
+    if let Some(tag) = tag.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        entries.retain(|e| e.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+    }
+    entries.reverse();
+
+    Ok(entries)
+}
+
+/// Unique temp file path for a native screenshot tool to write into
+fn temp_capture_path() -> PathBuf {
+    std::env::temp_dir().join(format!("roura_screenshot_{}.png", uuid::Uuid::new_v4()))
+}
+
+/// Run a native capture command and load the image it wrote to `path`
+#[cfg(not(target_os = "windows"))]
+fn run_capture_tool(
+    cmd: &mut std::process::Command,
+    path: &Path,
+) -> Result<image::DynamicImage, CommandError> {
+    let output = cmd
+        .output()
+        .map_err(|e| CommandError::io("Failed to run screenshot tool").with_details(e))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(path);
+        return Err(
+            CommandError::io("Screenshot failed")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+
+    let img = load_captured_file(path);
+    let _ = std::fs::remove_file(path);
+    img
+}
+
+/// Read and decode an image file written by a native screenshot tool
+#[cfg(not(target_os = "windows"))]
+fn load_captured_file(path: &Path) -> Result<image::DynamicImage, CommandError> {
+    let image_data = std::fs::read(path)
+        .map_err(|e| CommandError::io("Failed to read screenshot").with_details(e))?;
+
+    image::load_from_memory(&image_data)
+        .map_err(|e| CommandError::parse("Failed to decode image").with_details(e))
+}
+
+#[cfg(target_os = "macos")]
+async fn capture_macos_screenshot(
+    region: Option<CaptureRegion>,
+    include_cursor: bool,
+) -> Result<image::DynamicImage, CommandError> {
+    use std::process::Command;
+
+    // Create temp file path
+    let temp_path = std::env::temp_dir().join(format!("roura_screenshot_{}.png", uuid::Uuid::new_v4()));
+
+    // Build screencapture command
+    let mut cmd = Command::new("screencapture");
+    cmd.args(screencapture_args(include_cursor, region.as_ref(), None))
+        .arg(&temp_path);
+
+    let output = cmd.output()
+        .map_err(|e| CommandError::io("Failed to run screencapture").with_details(e))?;
+
+    if !output.status.success() {
+        return Err(
+            CommandError::io("Screenshot failed")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+
+    // Read the image
+    let img = load_captured_file(&temp_path);
+
+    // Clean up temp file
+    let _ = std::fs::remove_file(&temp_path);
+
+    img
+}
+
+/// An on-screen window as reported by the CoreGraphics window list
+#[cfg(target_os = "macos")]
+#[derive(Debug, Deserialize)]
+struct MacWindow {
+    id: u64,
+    title: String,
+    owner: String,
+    pid: u32,
+    #[serde(default)]
+    bounds: Option<MacBounds>,
+}
+
+/// A window's frame in global points, top-left origin
+#[cfg(target_os = "macos")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MacBounds {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// List normal on-screen windows, frontmost first
+#[cfg(target_os = "macos")]
+fn list_macos_windows() -> Result<Vec<MacWindow>, CommandError> {
+    use std::process::Command;
+
+    // CGWindowListCopyWindowInfo via the JavaScript for Automation ObjC bridge
+    const SCRIPT: &str = r#"
+ObjC.import('CoreGraphics');
+const info = ObjC.deepUnwrap(ObjC.castRefToObject($.CGWindowListCopyWindowInfo(
+    $.kCGWindowListOptionOnScreenOnly | $.kCGWindowListExcludeDesktopElements,
+    $.kCGNullWindowID)));
+JSON.stringify(info
+    .filter(w => w.kCGWindowLayer === 0)
+    .map(w => ({ id: w.kCGWindowNumber, title: w.kCGWindowName || '',
+        owner: w.kCGWindowOwnerName || '', pid: w.kCGWindowOwnerPID,
+        bounds: w.kCGWindowBounds })));
+"#;
+
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", SCRIPT])
+        .output()
+        .map_err(|e| CommandError::io("Failed to run osascript").with_details(e))?;
+
+    if !output.status.success() {
+        return Err(
+            CommandError::io("Failed to list windows")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| CommandError::parse("Failed to parse window list").with_details(e))
+}
+
+#[cfg(target_os = "macos")]
+async fn capture_macos_window(
+    title_substring: &str,
+) -> Result<(image::DynamicImage, String), CommandError> {
+    use std::process::Command;
+
+    let needle = title_substring.to_lowercase();
+
+    // Window titles need Screen Recording permission, so also match the app name
+    let window = list_macos_windows()?
+        .into_iter()
+        .find(|w| {
+            w.title.to_lowercase().contains(&needle) || w.owner.to_lowercase().contains(&needle)
+        })
+        .ok_or_else(|| {
+            CommandError::not_found(format!("No window matches \"{}\"", title_substring))
+        })?;
+
+    let temp_path = temp_capture_path();
+    let mut cmd = Command::new("screencapture");
+    cmd.args(["-x", "-o", "-l"]) // No sound, no shadow
+        .arg(window.id.to_string())
+        .arg(&temp_path);
+
+    let img = run_capture_tool(&mut cmd, &temp_path)?;
+    let title = if window.title.is_empty() {
+        window.owner
+    } else {
+        window.title
+    };
+
+    Ok((img, title))
+}
+
+/// A window `capture_scrolling_window` captures and scrolls
+#[cfg(target_os = "macos")]
+struct ScrollTarget {
+    id: u64,
+    title: String,
+    /// Window centre in global points, where scroll events are aimed
+    center: (f64, f64),
+}
+
+#[cfg(target_os = "macos")]
+fn find_scroll_target(title_substring: &str) -> Result<ScrollTarget, CommandError> {
+    let needle = title_substring.to_lowercase();
+    let window = list_macos_windows()?
+        .into_iter()
+        .find(|w| {
+            w.title.to_lowercase().contains(&needle) || w.owner.to_lowercase().contains(&needle)
+        })
+        .ok_or_else(|| {
+            CommandError::not_found(format!("No window matches \"{}\"", title_substring))
+        })?;
+
+    let bounds = window
+        .bounds
+        .ok_or_else(|| CommandError::internal("Window list did not include window bounds"))?;
+    Ok(ScrollTarget {
+        id: window.id,
+        title: if window.title.is_empty() { window.owner } else { window.title },
+        center: (bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0),
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn capture_scroll_frame(target: &ScrollTarget) -> Result<image::RgbaImage, CommandError> {
+    use std::process::Command;
+
+    let temp_path = temp_capture_path();
+    let mut cmd = Command::new("screencapture");
+    cmd.args(["-x", "-o", "-l"]) // No sound, no shadow
+        .arg(target.id.to_string())
+        .arg(&temp_path);
+
+    Ok(run_capture_tool(&mut cmd, &temp_path)?.to_rgba8())
+}
+
+/// CoreGraphics calls used to scroll a window and move the pointer
+#[cfg(target_os = "macos")]
+mod cg {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct CGPoint {
+        pub x: f64,
+        pub y: f64,
+    }
+
+    /// kCGScrollEventUnitPixel
+    pub const PIXEL_UNITS: u32 = 0;
+    /// kCGHIDEventTap
+    pub const HID_EVENT_TAP: u32 = 0;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        pub fn CGWarpMouseCursorPosition(point: CGPoint) -> i32;
+        pub fn CGEventCreate(source: *const c_void) -> *mut c_void;
+        pub fn CGEventGetLocation(event: *mut c_void) -> CGPoint;
+        pub fn CGEventCreateScrollWheelEvent(
+            source: *const c_void,
+            units: u32,
+            wheel_count: u32,
+            wheel1: i32, ...
+        ) -> *mut c_void;
+        pub fn CGEventPost(tap: u32, event: *mut c_void);
+    }
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        pub fn CFRelease(cf: *const c_void);
+    }
+}
+
+/// Where the pointer was before scrolling; put back when dropped
+#[cfg(target_os = "macos")]
+struct SavedPointer(cg::CGPoint);
+
+#[cfg(target_os = "macos")]
+impl SavedPointer {
+    fn save() -> Option<Self> {
+        // SAFETY: an event created without a source just reports the pointer location
+        unsafe {
+            let event = cg::CGEventCreate(std::ptr::null());
+            if event.is_null() {
+                return None;
+            }
+            let location = cg::CGEventGetLocation(event);
+            cg::CFRelease(event);
+            Some(Self(location))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for SavedPointer {
+    fn drop(&mut self) {
+        // SAFETY: plain CoreGraphics call with a point by value
+        unsafe {
+            cg::CGWarpMouseCursorPosition(self.0);
+        }
+    }
+}
+
+/// Scroll the window's content down by posting a CoreGraphics wheel event over it
+#[cfg(target_os = "macos")]
+fn scroll_target(target: &ScrollTarget, step_px: u32) -> Result<(), CommandError> {
+    use cg::*;
+
+    // Negative deltas scroll content up, revealing what's below
+    let delta = -(step_px.min(i32::MAX as u32) as i32);
+    // SAFETY: plain CoreGraphics calls; the event is released after posting
+    unsafe {
+        let (x, y) = target.center;
+        if CGWarpMouseCursorPosition(CGPoint { x, y }) != 0 {
+            return Err(CommandError::internal("Failed to move the pointer over the window"));
+        }
+        let event = CGEventCreateScrollWheelEvent(std::ptr::null(), PIXEL_UNITS, 1, delta);
+        if event.is_null() {
+            return Err(CommandError::internal("Failed to create scroll event"));
+        }
+        CGEventPost(HID_EVENT_TAP, event);
+        CFRelease(event);
+    }
+    Ok(())
+}
+
+/// Capture the frontmost window that doesn't belong to this process
+#[cfg(target_os = "macos")]
+async fn capture_macos_active_window(
+) -> Result<(image::DynamicImage, Option<WindowLabel>), CommandError> {
+    use std::process::Command;
+
+    let own_pid = std::process::id();
+    let window = list_macos_windows()?
+        .into_iter()
+        .find(|w| w.pid != own_pid)
+        .ok_or_else(|| CommandError::not_found("No other window is on screen"))?;
+
+    let temp_path = temp_capture_path();
+    let mut cmd = Command::new("screencapture");
+    cmd.args(["-x", "-o", "-l"]) // No sound, no shadow
+        .arg(window.id.to_string())
+        .arg(&temp_path);
+
+    let img = run_capture_tool(&mut cmd, &temp_path)?;
+    Ok((
+        img,
+        Some(WindowLabel {
+            title: window.title,
+            app: Some(window.owner).filter(|o| !o.is_empty()),
+        }),
+    ))
+}
+
+/// Raw NSScreen geometry, in points
+#[cfg(target_os = "macos")]
+#[derive(Debug, Deserialize)]
+struct MacScreen {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    scale: f64,
+}
+
+#[cfg(target_os = "macos")]
+fn list_macos_displays() -> Result<Vec<DisplayInfo>, CommandError> {
+    use std::process::Command;
+
+    // NSScreen.screens lists the main display first, matching `screencapture -D`
+    const SCRIPT: &str = r#"
+ObjC.import('AppKit');
+const screens = $.NSScreen.screens.js;
+JSON.stringify(screens.map(s => ({
+    x: s.frame.origin.x, y: s.frame.origin.y,
+    width: s.frame.size.width, height: s.frame.size.height,
+    scale: s.backingScaleFactor })));
+"#;
+
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", SCRIPT])
+        .output()
+        .map_err(|e| CommandError::io("Failed to run osascript").with_details(e))?;
+
+    if !output.status.success() {
+        return Err(
+            CommandError::io("Failed to list displays")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+
+    let screens: Vec<MacScreen> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| CommandError::parse("Failed to parse display list").with_details(e))?;
+
+    // Cocoa puts the origin at the bottom-left of the main display; flip to top-left
+    let main_height = screens.first().map(|s| s.height).unwrap_or(0.0);
+
+    Ok(screens
+        .iter()
+        .enumerate()
+        .map(|(index, s)| DisplayInfo {
+            index,
+            x: s.x as i32,
+            y: (main_height - s.y - s.height) as i32,
+            width: s.width as u32,
+            height: s.height as u32,
+            scale_factor: s.scale,
+            primary: index == 0,
+        })
+        .collect())
+}
+
+#[cfg(target_os = "windows")]
+fn list_windows_displays() -> Result<Vec<DisplayInfo>, CommandError> {
+    use windows::core::BOOL;
+    use windows::Win32::Foundation::{LPARAM, RECT};
+    use windows::Win32::Graphics::Gdi::{
+        EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO,
+    };
+    use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+    use windows::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+
+    unsafe extern "system" fn collect(
+        monitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let displays = &mut *(data.0 as *mut Vec<DisplayInfo>);
+
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if GetMonitorInfoW(monitor, &mut info).as_bool() {
+            let (mut dpi_x, mut dpi_y) = (96u32, 96u32);
+            let _ = GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y);
+
+            let r = info.rcMonitor;
+            displays.push(DisplayInfo {
+                index: displays.len(),
+                x: r.left,
+                y: r.top,
+                width: (r.right - r.left) as u32,
+                height: (r.bottom - r.top) as u32,
+                scale_factor: dpi_x as f64 / 96.0,
+                primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+            });
+        }
+
+        BOOL(1)
+    }
+
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+    let ok = unsafe {
+        EnumDisplayMonitors(
+            None,
+            None,
+            Some(collect),
+            LPARAM(&mut displays as *mut Vec<DisplayInfo> as isize),
+        )
+    };
+
+    if !ok.as_bool() {
+        return Err(CommandError::io("Failed to enumerate displays"));
+    }
+
+    Ok(displays)
+}
+
+#[cfg(target_os = "windows")]
+async fn capture_windows_screenshot(
+    region: Option<CaptureRegion>,
+) -> Result<image::DynamicImage, CommandError> {
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+        SM_YVIRTUALSCREEN,
+    };
+
+    // Full captures cover the whole virtual desktop, which spans every monitor
+    let (x, y, width, height) = match &region {
+        Some(r) => (r.x, r.y, r.width as i32, r.height as i32),
+        None => unsafe {
+            (
+                GetSystemMetrics(SM_XVIRTUALSCREEN),
+                GetSystemMetrics(SM_YVIRTUALSCREEN),
+                GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                GetSystemMetrics(SM_CYVIRTUALSCREEN),
+            )
+        },
+    };
+
+    if width <= 0 || height <= 0 {
+        return Err(CommandError::invalid_input("Capture region must have a non-zero size"));
+    }
+
+    let pixels = tokio::task::spawn_blocking(move || unsafe { bitblt_screen(x, y, width, height) })
+        .await
+        .map_err(|e| CommandError::io("Screenshot task failed").with_details(e))??;
+
+    let img = image::RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .ok_or_else(|| CommandError::internal("Captured pixel buffer has unexpected size"))?;
+
+    Ok(image::DynamicImage::ImageRgba8(img))
+}
+
+/// Copy a rectangle of the virtual desktop into an RGBA pixel buffer
+#[cfg(target_os = "windows")]
+unsafe fn bitblt_screen(x: i32, y: i32, width: i32, height: i32) -> Result<Vec<u8>, CommandError> {
+    use windows::Win32::Graphics::Gdi::{
+        BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC,
+        GetDIBits, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CAPTUREBLT,
+        DIB_RGB_COLORS, HGDIOBJ, SRCCOPY,
+    };
+
+    let screen_dc = GetDC(None);
+    if screen_dc.is_invalid() {
+        return Err(CommandError::io("Failed to get screen device context"));
+    }
+
+    let mem_dc = CreateCompatibleDC(Some(screen_dc));
+    let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+    let previous = SelectObject(mem_dc, HGDIOBJ::from(bitmap));
+
+    let blit = BitBlt(
+        mem_dc,
+        0,
+        0,
+        width,
+        height,
+        Some(screen_dc),
+        x,
+        y,
+        SRCCOPY | CAPTUREBLT,
+    );
+
+    // Top-down 32-bit BGRA rows
+    let mut info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            biHeight: -height,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    let lines = if blit.is_ok() {
+        GetDIBits(
+            mem_dc,
+            bitmap,
+            0,
+            height as u32,
+            Some(pixels.as_mut_ptr() as *mut _),
+            &mut info,
+            DIB_RGB_COLORS,
+        )
+    } else {
+        0
+    };
+
+    SelectObject(mem_dc, previous);
+    let _ = DeleteObject(HGDIOBJ::from(bitmap));
+    let _ = DeleteDC(mem_dc);
+    ReleaseDC(None, screen_dc);
+
+    if let Err(e) = blit {
+        return Err(CommandError::io("BitBlt failed").with_details(e));
+    }
+    if lines == 0 {
+        return Err(CommandError::io("GetDIBits returned no data"));
+    }
+
+    // BGRA -> RGBA, and force opaque alpha since GDI leaves it undefined
+    for px in pixels.chunks_exact_mut(4) {
+        px.swap(0, 2);
+        px[3] = 255;
+    }
+
+    Ok(pixels)
+}
+
+#[cfg(target_os = "linux")]
+async fn capture_linux_screenshot(
+    region: Option<CaptureRegion>,
+    include_cursor: bool,
+) -> Result<image::DynamicImage, CommandError> {
+    use std::process::Command;
+
+    // X11 tools can't read a Wayland compositor and return black or failed images
+    if is_wayland_session() {
+        return capture_wayland_screenshot(region, include_cursor).await;
+    }
+
+    // Try gnome-screenshot, scrot, or import (ImageMagick)
+    let temp_path = std::env::temp_dir().join(format!("roura_screenshot_{}.png", uuid::Uuid::new_v4()));
+
+    let scrot = |region: Option<&CaptureRegion>| {
+        Command::new("scrot")
+            .args(scrot_args(include_cursor, region))
+            .arg(&temp_path)
+            .output()
+    };
+
+    // Try different screenshot tools
+    let result = if let Some(r) = &region {
+        // Try scrot with region
+        scrot(Some(r))
+    } else {
+        // Try gnome-screenshot first, then scrot
+        let gnome_result = Command::new("gnome-screenshot")
+            .args(gnome_screenshot_args(include_cursor))
+            .arg(&temp_path)
+            .output();
+
+        if gnome_result.is_ok() && gnome_result.as_ref().unwrap().status.success() {
+            gnome_result
+        } else {
+            scrot(None)
+        }
+    };
+
+    let output =
+        result.map_err(|e| CommandError::io("Failed to run screenshot tool").with_details(e))?;
+
+    if !output.status.success() {
+        return Err(
+            CommandError::io("Screenshot failed using X11 tools")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+
+    // Read the image
+    let img = load_captured_file(&temp_path);
+
+    // Clean up temp file
+    let _ = std::fs::remove_file(&temp_path);
+
+    img
+}
+
+/// Offer `path` as a `text/uri-list` clipboard target, with wl-copy or xclip
+#[cfg(target_os = "linux")]
+fn copy_file_uri_linux(path: &Path) -> Result<(), CommandError> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let path = std::fs::canonicalize(path)
+        .map_err(|e| CommandError::io("Failed to resolve screenshot path").with_details(e))?;
+    let uri = reqwest::Url::from_file_path(&path)
+        .map_err(|_| CommandError::internal("Failed to build a file URI for the screenshot"))?;
+
+    let mut cmd = if is_wayland_session() {
+        let mut cmd = Command::new("wl-copy");
+        cmd.args(["--type", "text/uri-list"]);
+        cmd
+    } else {
+        let mut cmd = Command::new("xclip");
+        cmd.args(["-selection", "clipboard", "-t", "text/uri-list"]);
+        cmd
+    };
+    let tool = cmd.get_program().to_string_lossy().into_owned();
+
+    // Both tools fork to keep serving the selection, so their output can't be
+    // piped without waiting on the background copy forever
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            CommandError::unsupported(format!("{} is required to copy file URIs", tool))
+                .with_details(e)
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // uri-list lines end in CRLF
+        stdin
+            .write_all(format!("{}\r\n", uri).as_bytes())
+            .map_err(|e| CommandError::io("Failed to write to clipboard").with_details(e))?;
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| CommandError::io("Failed to write to clipboard").with_details(e))?;
+    if !status.success() {
+        return Err(CommandError::io(format!("{} failed to set the clipboard", tool)));
+    }
+
+    Ok(())
+}
+
+/// Whether the session runs under a Wayland compositor
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t.eq_ignore_ascii_case("wayland"))
+}
+
+/// Capture on Wayland with grim, falling back to the desktop portal
+#[cfg(target_os = "linux")]
+async fn capture_wayland_screenshot(
+    region: Option<CaptureRegion>,
+    include_cursor: bool,
+) -> Result<image::DynamicImage, CommandError> {
+    let temp_path = temp_capture_path();
+    let mut grim = std::process::Command::new("grim");
+    grim.args(grim_args(include_cursor, region.as_ref()))
+        .arg(&temp_path);
+
+    let grim_error = match run_capture_tool(&mut grim, &temp_path) {
+        Ok(img) => return Ok(img),
+        Err(e) => e,
+    };
+
+    // GNOME and KDE don't implement wlr-screencopy, so grim fails there
+    let portal_error = match capture_portal_screenshot(false).await {
+        // The portal image is in physical pixels, unlike the region
+        Ok(img) => match region {
+            Some(r) => {
+                let (x, y, width, height) = r
+                    .to_physical(r.scale())
+                    .clamp_to(img.width(), img.height())
+                    .ok_or_else(|| CommandError::invalid_input("Region is outside the screen"))?;
+                return Ok(img.crop_imm(x, y, width, height));
+            }
+            None => return Ok(img),
+        },
+        Err(e) => e,
+    };
+
+    Err(CommandError::io("Screenshot failed on Wayland").with_details(format!(
+        "grim: {}; xdg-desktop-portal: {}",
+        grim_error, portal_error
+    )))
+}
+
+/// Let the user pick a region with slurp and capture it with grim
+#[cfg(target_os = "linux")]
+fn select_wayland_region(temp_path: &Path) -> Result<Option<image::DynamicImage>, CommandError> {
+    let selection = std::process::Command::new("slurp")
+        .output()
+        .map_err(|e| CommandError::io("Failed to run slurp (is it installed?)").with_details(e))?;
+
+    // slurp exits with an error when the selection is cancelled
+    if !selection.status.success() {
+        return Ok(None);
+    }
+
+    let geometry = String::from_utf8_lossy(&selection.stdout).trim().to_string();
+    let mut grim = std::process::Command::new("grim");
+    grim.arg("-g").arg(&geometry).arg(temp_path);
+    run_capture_tool(&mut grim, temp_path).map(Some)
+}
+
+/// Take a capture through `org.freedesktop.portal.Screenshot`.
+///
+/// Non-interactive captures are full-screen; interactive ones let the user
+/// pick what to capture.
+#[cfg(target_os = "linux")]
+async fn capture_portal_screenshot(interactive: bool) -> Result<image::DynamicImage, CommandError> {
+    use futures_util::StreamExt;
+    use std::collections::HashMap;
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+    let dbus_error = |e: zbus::Error| CommandError::io("Portal request failed").with_details(e);
+
+    let conn = zbus::Connection::session().await.map_err(dbus_error)?;
+
+    // Subscribe to the request's Response before calling, or a fast reply is missed
+    let token = format!("roura{}", uuid::Uuid::new_v4().simple());
+    let sender = conn
+        .unique_name()
+        .ok_or_else(|| CommandError::internal("D-Bus connection has no unique name"))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let request_path = format!(
+        "/org/freedesktop/portal/desktop/request/{}/{}",
+        sender, token
+    );
+    let request = zbus::Proxy::new(
+        &conn,
+        "org.freedesktop.portal.Desktop",
+        request_path.as_str(),
+        "org.freedesktop.portal.Request",
+    )
+    .await
+    .map_err(dbus_error)?;
+    let mut responses = request.receive_signal("Response").await.map_err(dbus_error)?;
+
+    let portal = zbus::Proxy::new(
+        &conn,
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Screenshot",
+    )
+    .await
+    .map_err(dbus_error)?;
+    let options: HashMap<&str, Value> = HashMap::from([
+        ("handle_token", Value::from(token.as_str())),
+        ("interactive", Value::from(interactive)),
+    ]);
+    let _: OwnedObjectPath = portal
+        .call("Screenshot", &("", options))
+        .await
+        .map_err(dbus_error)?;
+
+    let message = responses
+        .next()
+        .await
+        .ok_or_else(|| CommandError::io("Portal closed without a response"))?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) =
+        message.body().deserialize().map_err(dbus_error)?;
+    if code != 0 {
+        return Err(CommandError::io(format!(
+            "Portal screenshot was denied or cancelled (code {})",
+            code
+        )));
+    }
+
+    let path = results
+        .get("uri")
+        .and_then(|uri| uri.downcast_ref::<&str>().ok())
+        .and_then(|uri| tauri::Url::parse(uri).ok())
+        .and_then(|uri| uri.to_file_path().ok())
+        .ok_or_else(|| CommandError::parse("Portal response has no file URI"))?;
+
+    // The portal saves into the user's pictures folder; this capture wasn't asked to be kept
+    let img = load_captured_file(&path);
+    let _ = std::fs::remove_file(&path);
+    img
+}
+
+/// Find the frontmost X11 window whose title contains `title_substring`
+#[cfg(target_os = "linux")]
+fn find_x11_window(title_substring: &str) -> Result<(u64, String), CommandError> {
+    use std::process::Command;
+
+    let output = Command::new("xdotool")
+        .args(["search", "--onlyvisible", "--name"])
+        .arg(regex_escape(title_substring))
+        .output()
+        .map_err(|e| CommandError::io("Failed to run xdotool (is it installed?)").with_details(e))?;
+
+    let matches: Vec<u64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.trim().parse().ok())
+        .collect();
+
+    if matches.is_empty() {
+        return Err(CommandError::not_found(format!("No window matches \"{}\"", title_substring)));
+    }
+
+    // _NET_CLIENT_LIST_STACKING is ordered bottom to top
+    let stacking: Vec<u64> = Command::new("xprop")
+        .args(["-root", "_NET_CLIENT_LIST_STACKING"])
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split(['#', ','])
+                .skip(1)
+                .filter_map(|id| u64::from_str_radix(id.trim().trim_start_matches("0x"), 16).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let id = stacking
+        .iter()
+        .rev()
+        .find(|id| matches.contains(id))
+        .copied()
+        .unwrap_or(matches[matches.len() - 1]);
+
+    let title = Command::new("xdotool")
+        .arg("getwindowname")
+        .arg(id.to_string())
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default();
+
+    Ok((id, title))
+}
+
+/// Escape regex metacharacters, since `xdotool search` treats its pattern as a regex
+#[cfg(target_os = "linux")]
+fn regex_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(target_os = "linux")]
+async fn capture_linux_window(
+    title_substring: &str,
+) -> Result<(image::DynamicImage, String), CommandError> {
+    use std::process::Command;
+
+    let (id, title) = find_x11_window(title_substring)?;
+    let temp_path = temp_capture_path();
+
+    // Prefer ImageMagick's import, which can grab a window by id
+    let mut import = Command::new("import");
+    import.arg("-window").arg(id.to_string()).arg(&temp_path);
+    let img = match run_capture_tool(&mut import, &temp_path) {
+        Ok(img) => img,
+        Err(_) => {
+            // gnome-screenshot can only capture the focused window, so raise it first
+            let _ = Command::new("xdotool")
+                .args(["windowactivate", "--sync"])
+                .arg(id.to_string())
+                .output();
+
+            let mut gnome = Command::new("gnome-screenshot");
+            gnome.arg("-w").arg("-f").arg(&temp_path);
+            run_capture_tool(&mut gnome, &temp_path)?
+        }
+    };
+
+    Ok((img, title))
+}
+
+/// Pixels one X11 wheel click scrolls in most toolkits
+#[cfg(target_os = "linux")]
+const X11_WHEEL_STEP_PX: u32 = 50;
+
+/// A window `capture_scrolling_window` captures and scrolls
+#[cfg(target_os = "linux")]
+struct ScrollTarget {
+    id: u64,
+    title: String,
+}
+
+#[cfg(target_os = "linux")]
+fn find_scroll_target(title_substring: &str) -> Result<ScrollTarget, CommandError> {
+    // Wayland has neither window ids nor synthetic input for other clients
+    if is_wayland_session() {
+        return Err(CommandError::unsupported("Scrolling capture isn't possible on Wayland"));
+    }
+
+    let (id, title) = find_x11_window(title_substring)?;
+    Ok(ScrollTarget { id, title })
+}
+
+#[cfg(target_os = "linux")]
+fn capture_scroll_frame(target: &ScrollTarget) -> Result<image::RgbaImage, CommandError> {
+    let temp_path = temp_capture_path();
+    let mut import = std::process::Command::new("import");
+    import.arg("-window").arg(target.id.to_string()).arg(&temp_path);
+
+    Ok(run_capture_tool(&mut import, &temp_path)?.to_rgba8())
+}
+
+/// Where the X11 pointer was before scrolling, as (x, y, screen); put back when dropped
+#[cfg(target_os = "linux")]
+struct SavedPointer(String, String, String);
+
+#[cfg(target_os = "linux")]
+impl SavedPointer {
+    fn save() -> Option<Self> {
+        let output = std::process::Command::new("xdotool")
+            .args(["getmouselocation", "--shell"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let location = String::from_utf8_lossy(&output.stdout);
+        let value = |key: &str| {
+            location
+                .lines()
+                .find_map(|l| l.strip_prefix(key))
+                .map(|v| v.trim().to_string())
+        };
+        Some(Self(value("X=")?, value("Y=")?, value("SCREEN=")?))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for SavedPointer {
+    fn drop(&mut self) {
+        let Self(x, y, screen) = self;
+        let _ = std::process::Command::new("xdotool")
+            .args([
+                "mousemove",
+                "--screen",
+                screen.as_str(),
+                x.as_str(),
+                y.as_str(),
+            ])
+            .output();
+    }
+}
+
+/// Scroll the window's content down with wheel clicks over its centre
+#[cfg(target_os = "linux")]
+fn scroll_target(target: &ScrollTarget, step_px: u32) -> Result<(), CommandError> {
+    use std::process::Command;
+
+    let id = target.id.to_string();
+    let geometry = Command::new("xdotool")
+        .args(["getwindowgeometry", "--shell", &id])
+        .output()
+        .map_err(|e| CommandError::io("Failed to run xdotool (is it installed?)").with_details(e))?;
+    let geometry = String::from_utf8_lossy(&geometry.stdout);
+    let dimension = |key: &str| {
+        geometry
+            .lines()
+            .find_map(|l| l.strip_prefix(key)?.trim().parse::<u32>().ok())
+            .unwrap_or(0)
+    };
+    let (x, y) = (dimension("WIDTH=") / 2, dimension("HEIGHT=") / 2);
+
+    // X11 scrolls in wheel clicks, so round the step up to whole clicks
+    let clicks = step_px.div_ceil(X11_WHEEL_STEP_PX).max(1);
+    let output = Command::new("xdotool")
+        .args(["mousemove", "--window", &id, &x.to_string(), &y.to_string()])
+        .args(["click", "--repeat", &clicks.to_string(), "--delay", "20", "5"])
+        .output()
+        .map_err(|e| CommandError::io("Failed to run xdotool (is it installed?)").with_details(e))?;
+
+    if !output.status.success() {
+        return Err(
+            CommandError::io("Failed to scroll window")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+    Ok(())
+}
+
+/// Capture the focused X11 window, or let the portal pick one on Wayland
+#[cfg(target_os = "linux")]
+async fn capture_linux_active_window(
+) -> Result<(image::DynamicImage, Option<WindowLabel>), CommandError> {
+    use std::process::Command;
+
+    // Wayland doesn't expose the focused window to clients
+    if is_wayland_session() {
+        return Ok((capture_portal_screenshot(true).await?, None));
+    }
+
+    let id = find_x11_active_window()?;
+    let temp_path = temp_capture_path();
+
+    let mut import = Command::new("import");
+    import.arg("-window").arg(id.to_string()).arg(&temp_path);
+    let img = run_capture_tool(&mut import, &temp_path)?;
+
+    let xdotool = |subcommand: &str| {
+        Command::new("xdotool")
+            .arg(subcommand)
+            .arg(id.to_string())
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    Ok((
+        img,
+        Some(WindowLabel {
+            title: xdotool("getwindowname").unwrap_or_default(),
+            app: xdotool("getwindowclassname"),
+        }),
+    ))
+}
+
+/// The focused X11 window, or the topmost other one if this app has focus
+#[cfg(target_os = "linux")]
+fn find_x11_active_window() -> Result<u64, CommandError> {
+    use std::process::Command;
+
+    let output = Command::new("xdotool")
+        .arg("getactivewindow")
+        .output()
+        .map_err(|e| CommandError::io("Failed to run xdotool (is it installed?)").with_details(e))?;
+    let active: Option<u64> = String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+
+    let own_pid = std::process::id().to_string();
+    let is_own = |id: u64| {
+        Command::new("xdotool")
+            .arg("getwindowpid")
+            .arg(id.to_string())
+            .output()
+            .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == own_pid)
+    };
+
+    if let Some(id) = active.filter(|&id| !is_own(id)) {
+        return Ok(id);
+    }
+
+    // _NET_CLIENT_LIST_STACKING is ordered bottom to top
+    Command::new("xprop")
+        .args(["-root", "_NET_CLIENT_LIST_STACKING"])
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split(['#', ','])
+                .skip(1)
+                .filter_map(|id| u64::from_str_radix(id.trim().trim_start_matches("0x"), 16).ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .find(|&id| !is_own(id))
+        .ok_or_else(|| CommandError::not_found("No focused window found"))
+}
+
+#[cfg(target_os = "linux")]
+fn list_linux_displays() -> Result<Vec<DisplayInfo>, CommandError> {
+    use std::process::Command;
+
+    // wlr-randr reports native Wayland outputs, including their scale
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        if let Ok(output) = Command::new("wlr-randr").arg("--json").output() {
+            if output.status.success() {
+                if let Some(displays) = parse_wlr_randr(&output.stdout) {
+                    return Ok(displays);
+                }
+            }
+        }
+    }
+
+    let output = Command::new("xrandr")
+        .arg("--query")
+        .output()
+        .map_err(|e| CommandError::io("Failed to run xrandr").with_details(e))?;
+
+    if !output.status.success() {
+        return Err(
+            CommandError::io("Failed to list displays")
+                .with_details(String::from_utf8_lossy(&output.stderr)),
+        );
+    }
+
+    Ok(parse_xrandr(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse connected outputs from `xrandr --query`, e.g.
+/// `HDMI-1 connected primary 1920x1080+0+0 (normal left inverted ...) 527mm x 296mm`
+#[cfg(target_os = "linux")]
+fn parse_xrandr(output: &str) -> Vec<DisplayInfo> {
+    let mut displays = Vec::new();
+
+    for line in output.lines() {
+        if line.starts_with(' ') || !line.contains(" connected") {
+            continue;
+        }
+
+        let primary = line.contains(" primary ");
+        let Some(geometry) = line.split_whitespace().find_map(parse_x_geometry) else {
+            // Connected but disabled
+            continue;
+        };
+        let (width, height, x, y) = geometry;
+
+        displays.push(DisplayInfo {
+            index: displays.len(),
+            x,
+            y,
+            width,
+            height,
+            scale_factor: 1.0,
+            primary,
+        });
+    }
+
+    displays
+}
+
+/// Parse an X geometry string like `1920x1080+0+0`
+#[cfg(target_os = "linux")]
+fn parse_x_geometry(token: &str) -> Option<(u32, u32, i32, i32)> {
+    let (size, offsets) = token.split_at(token.find(['+', '-'])?);
+    let (width, height) = size.split_once('x')?;
+    let (x, y) = offsets.split_at(offsets[1..].find(['+', '-'])? + 1);
+
+    Some((
+        width.parse().ok()?,
+        height.parse().ok()?,
+        x.parse().ok()?,
+        y.parse().ok()?,
+    ))
+}
+
+/// Parse the output of `wlr-randr --json`
+#[cfg(target_os = "linux")]
+fn parse_wlr_randr(stdout: &[u8]) -> Option<Vec<DisplayInfo>> {
+    let outputs: Vec<serde_json::Value> = serde_json::from_slice(stdout).ok()?;
+
+    let displays = outputs
+        .iter()
+        .filter(|o| o.get("enabled").and_then(|v| v.as_bool()).unwrap_or(false))
+        .enumerate()
+        .filter_map(|(index, o)| {
+            let mode = o
+                .get("modes")?
+                .as_array()?
+                .iter()
+                .find(|m| m.get("current").and_then(|v| v.as_bool()).unwrap_or(false))?;
+            let position = o.get("position")?;
+            let scale = o
+                .get("scale")
+                .and_then(|v| v.as_f64())
+                .filter(|s| *s > 0.0)
+                .unwrap_or(1.0);
+            // Modes are in device pixels, positions in logical ones like `DisplayInfo`
+            let logical =
+                |key: &str| Some((mode.get(key)?.as_u64()? as f64 / scale).round() as u32);
+
+            Some(DisplayInfo {
+                index,
+                x: position.get("x")?.as_i64()? as i32,
+                y: position.get("y")?.as_i64()? as i32,
+                width: logical("width")?,
+                height: logical("height")?,
+                scale_factor: scale,
+                primary: index == 0,
+            })
+        })
+        .collect();
+
+    Some(displays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(x: i32, y: i32, width: u32, height: u32) -> CaptureRegion {
+        CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+            scale_factor: None,
+        }
+    }
+
+    #[test]
+    fn region_scales_to_physical_pixels() {
+        let logical = region(100, 50, 640, 480);
+        for (scale, expected) in [
+            (1.0, (100, 50, 640, 480)),
+            (2.0, (200, 100, 1280, 960)),
+            (1.5, (150, 75, 960, 720)),
+        ] {
+            let physical = logical.to_physical(scale);
+            assert_eq!(
+                (physical.x, physical.y, physical.width, physical.height),
+                expected,
+                "scale {}",
+                scale
+            );
+            assert_eq!(physical.scale_factor, Some(1.0));
+        }
+    }
+
+    #[test]
+    fn fractional_scale_rounds_to_nearest_pixel() {
+        let physical = region(-3, 7, 101, 33).to_physical(1.5);
+        assert_eq!(
+            (physical.x, physical.y, physical.width, physical.height),
+            (-5, 11, 152, 50)
+        );
+    }
+
+    #[test]
+    fn screencapture_flags_for_each_combination() {
+        let r = region(10, 20, 300, 200);
+
+        assert_eq!(screencapture_args(false, None, None), ["-x"]);
+        assert_eq!(screencapture_args(true, None, None), ["-x", "-C"]);
+        assert_eq!(
+            screencapture_args(false, Some(&r), None),
+            ["-x", "-R", "10,20,300,200"]
+        );
+        assert_eq!(
+            screencapture_args(true, Some(&r), None),
+            ["-x", "-C", "-R", "10,20,300,200"]
+        );
+        assert_eq!(
+            screencapture_args(true, None, Some(2)),
+            ["-x", "-C", "-D", "2"]
+        );
+    }
+
+    #[test]
+    fn linux_tool_flags_for_each_combination() {
+        let r = region(10, 20, 300, 200);
+
+        assert!(scrot_args(false, None).is_empty());
+        assert_eq!(scrot_args(true, None), ["--pointer"]);
+        assert_eq!(scrot_args(false, Some(&r)), ["-a", "10,20,300,200"]);
+        assert_eq!(
+            scrot_args(true, Some(&r)),
+            ["--pointer", "-a", "10,20,300,200"]
+        );
+
+        assert_eq!(gnome_screenshot_args(false), ["-f"]);
+        assert_eq!(gnome_screenshot_args(true), ["--include-pointer", "-f"]);
+
+        assert!(grim_args(false, None).is_empty());
+        assert_eq!(grim_args(true, None), ["-c"]);
+        assert_eq!(grim_args(false, Some(&r)), ["-g", "10,20 300x200"]);
+        assert_eq!(grim_args(true, Some(&r)), ["-c", "-g", "10,20 300x200"]);
+    }
+
+    #[test]
+    fn wayland_regions_stay_logical_for_grim_and_scale_for_the_portal() {
+        let mut r = region(10, 20, 300, 200);
+        r.scale_factor = Some(2.0);
+        assert_eq!(grim_args(false, Some(&r)), ["-g", "10,20 300x200"]);
+
+        // The portal's image is physical, so its crop is scaled and kept in bounds
+        let physical = r.to_physical(r.scale());
+        assert_eq!(physical.clamp_to(1000, 1000), Some((20, 40, 600, 400)));
+        assert_eq!(physical.clamp_to(500, 300), Some((20, 40, 480, 260)));
+        assert_eq!(
+            region(-5, -5, 10, 10).clamp_to(100, 100),
+            Some((0, 0, 5, 5))
+        );
+        assert_eq!(region(200, 0, 10, 10).clamp_to(100, 100), None);
+    }
+
+    fn decode_base64_image(data: &str) -> image::DynamicImage {
+        image::load_from_memory(&STANDARD.decode(data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn thumbnail_fits_the_longest_side() {
+        let mut output = OutputOptions::new(None, None).unwrap();
+
+        for (width, height, max_dim, expected) in [
+            (400, 100, 64, (64, 16)),
+            (100, 400, 64, (16, 64)),
+            (300, 300, 50, (50, 50)),
+            // Never upscaled
+            (40, 20, 64, (40, 20)),
+        ] {
+            let img = image::DynamicImage::new_rgba8(width, height);
+            output.thumbnail_max_dim = Some(max_dim);
+            let result = finish_capture(img, None, &output).unwrap();
+
+            let thumbnail = decode_base64_image(result.thumbnail.as_deref().unwrap());
+            assert_eq!(
+                (thumbnail.width(), thumbnail.height()),
+                expected,
+                "{}x{} at {}",
+                width,
+                height,
+                max_dim
+            );
+            assert_eq!((result.width, result.height), (width, height));
+        }
+    }
+
+    #[test]
+    fn no_thumbnail_unless_requested() {
+        let output = OutputOptions::new(None, None).unwrap();
+        let result = finish_capture(image::DynamicImage::new_rgba8(8, 8), None, &output).unwrap();
+        assert!(result.thumbnail.is_none());
+    }
+
+    #[test]
+    fn explicit_scale_factor_is_used() {
+        let mut logical = region(0, 0, 10, 10);
+        logical.scale_factor = Some(2.0);
+        assert_eq!(logical.scale(), 2.0);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn wlr_randr_sizes_are_logical() {
+        let stdout = br#"[
+            {"name": "eDP-1", "enabled": true, "scale": 2.0, "position": {"x": 0, "y": 0},
+             "modes": [{"width": 2560, "height": 1600, "current": true}]},
+            {"name": "HDMI-A-1", "enabled": true, "scale": 1.0, "position": {"x": 1280, "y": 0},
+             "modes": [{"width": 1920, "height": 1080, "current": true}]}
+        ]"#;
+
+        let displays = parse_wlr_randr(stdout).unwrap();
+        assert_eq!((displays[0].width, displays[0].height), (1280, 800));
+        assert_eq!(displays[0].scale_factor, 2.0);
+        assert_eq!((displays[1].width, displays[1].height), (1920, 1080));
+    }
+
+    #[test]
+    fn save_path_extension_picks_the_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = OutputOptions::new(None, None).unwrap();
+
+        for (name, format, expected) in [
+            ("shot.png", "png", image::ImageFormat::Png),
+            ("shot.jpg", "jpeg", image::ImageFormat::Jpeg),
+            ("shot.JPEG", "jpeg", image::ImageFormat::Jpeg),
+            ("shot.webp", "webp", image::ImageFormat::WebP),
+            ("shot.bmp", "bmp", image::ImageFormat::Bmp),
+        ] {
+            let path = dir.path().join(name).to_string_lossy().into_owned();
+            let img = image::DynamicImage::new_rgba8(4, 3);
+            let result = finish_capture(img, Some(path.clone()), &output).unwrap();
+
+            assert_eq!(result.format, format, "{}", name);
+            assert_eq!(result.path.as_deref(), Some(path.as_str()));
+            let saved = std::fs::read(&path).unwrap();
+            assert_eq!(image::guess_format(&saved).unwrap(), expected, "{}", name);
+            let data = STANDARD.decode(result.data.unwrap()).unwrap();
+            assert_eq!(data, saved, "{}", name);
+        }
+    }
+
+    #[test]
+    fn unknown_extensions_get_png_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = OutputOptions::new(None, None).unwrap();
+        let path = dir.path().join("shot.tiff").to_string_lossy().into_owned();
+
+        let result = finish_capture(
+            image::DynamicImage::new_rgba8(4, 3),
+            Some(path.clone()),
+            &output,
+        )
+        .unwrap();
+
+        let expected = format!("{}.png", path);
+        assert_eq!(result.format, "png");
+        assert_eq!(result.path.as_deref(), Some(expected.as_str()));
+        let saved = std::fs::read(&expected).unwrap();
+        assert_eq!(
+            image::guess_format(&saved).unwrap(),
+            image::ImageFormat::Png
+        );
+        assert!(!Path::new(&path).exists());
+    }
+
+    fn display(index: usize, x: i32, y: i32, width: u32, height: u32, scale: f64) -> DisplayInfo {
+        DisplayInfo {
+            index,
+            x,
+            y,
+            width,
+            height,
+            scale_factor: scale,
+            primary: index == 0,
+        }
+    }
+
+    #[test]
+    fn display_crops_are_in_physical_pixels() {
+        let crop = |c: &CaptureRegion| (c.x, c.y, c.width, c.height);
+
+        let side_by_side = [
+            display(0, 0, 0, 1280, 800, 2.0),
+            display(1, 1280, 0, 1280, 800, 2.0),
+        ];
+        let crops: Vec<_> = display_crops(&side_by_side).iter().map(crop).collect();
+        assert_eq!(crops, [(0, 0, 2560, 1600), (2560, 0, 2560, 1600)]);
+
+        // Offsets are taken from the leftmost, topmost display
+        let negative = [
+            display(0, 0, 0, 1920, 1080, 1.0),
+            display(1, -1920, -200, 1920, 1080, 1.0),
+        ];
+        let crops: Vec<_> = display_crops(&negative).iter().map(crop).collect();
+        assert_eq!(crops, [(1920, 200, 1920, 1080), (0, 0, 1920, 1080)]);
+    }
+
+    fn checkerboard(size: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(size, size, |x, y| {
+            if (x + y) % 2 == 0 {
+                image::Rgba([0, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        })
+    }
+
+    fn rect(x: i64, y: i64, width: u32, height: u32) -> AnnotateRect {
+        AnnotateRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn inside(x: u32, y: u32, (rx, ry, rw, rh): (u32, u32, u32, u32)) -> bool {
+        x >= rx && x < rx + rw && y >= ry && y < ry + rh
+    }
+
+    #[test]
+    fn blur_changes_only_the_rect() {
+        let original = checkerboard(40);
+        let mut img = original.clone();
+        let op = AnnotateOp::Blur {
+            rect: rect(10, 10, 20, 20),
+            sigma: None,
+        };
+        apply_annotation(&mut img, &op).unwrap();
+
+        for (x, y, pixel) in img.enumerate_pixels() {
+            if inside(x, y, (10, 10, 20, 20)) {
+                assert_ne!(
+                    pixel,
+                    original.get_pixel(x, y),
+                    "({}, {}) not blurred",
+                    x,
+                    y
+                );
+            } else {
+                assert_eq!(pixel, original.get_pixel(x, y), "({}, {}) changed", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn rects_are_clamped_to_the_image() {
+        let original = checkerboard(20);
+        let mut img = original.clone();
+        let fill = AnnotateOp::Fill {
+            rect: rect(-5, 15, 10, 100),
+            color: "#ff0000".to_string(),
+        };
+        apply_annotation(&mut img, &fill).unwrap();
+
+        for (x, y, pixel) in img.enumerate_pixels() {
+            if inside(x, y, (0, 15, 5, 5)) {
+                assert_eq!(*pixel, image::Rgba([255, 0, 0, 255]));
+            } else {
+                assert_eq!(pixel, original.get_pixel(x, y));
+            }
+        }
+
+        // Entirely outside is a no-op
+        let op = AnnotateOp::Blur {
+            rect: rect(30, 30, 5, 5),
+            sigma: None,
+        };
+        apply_annotation(&mut img, &op).unwrap();
+        assert_eq!(img.get_pixel(19, 19), original.get_pixel(19, 19));
+
+        // Far edges saturate instead of overflowing
+        assert_eq!(rect(i64::MAX, 0, u32::MAX, 5).clamp(20, 20), None);
+        assert_eq!(rect(0, i64::MAX, 5, u32::MAX).clamp(20, 20), None);
+        assert_eq!(rect(i64::MIN, 0, u32::MAX, 5).clamp(20, 20), None);
+    }
+
+    #[test]
+    fn rectangle_outlines_leave_the_inside_alone() {
+        let original = checkerboard(20);
+        let mut img = original.clone();
+        let op = AnnotateOp::Rectangle {
+            rect: rect(2, 2, 10, 10),
+            color: "#00ff00".to_string(),
+            thickness: Some(2),
+        };
+        apply_annotation(&mut img, &op).unwrap();
+
+        let green = image::Rgba([0, 255, 0, 255]);
+        assert_eq!(*img.get_pixel(2, 2), green);
+        assert_eq!(*img.get_pixel(11, 6), green);
+        assert_eq!(*img.get_pixel(6, 11), green);
+        assert_eq!(img.get_pixel(6, 6), original.get_pixel(6, 6));
+        assert_eq!(img.get_pixel(12, 12), original.get_pixel(12, 12));
+    }
+
+    #[test]
+    fn invalid_colors_are_rejected() {
+        let mut img = checkerboard(4);
+        for color in ["ff0000", "#ff00", "#gg0000", "#ff0000ff00"] {
+            let op = AnnotateOp::Fill {
+                rect: rect(0, 0, 1, 1),
+                color: color.to_string(),
+            };
+            let err = apply_annotation(&mut img, &op).unwrap_err();
+            assert_eq!(err.code(), "invalid_input", "{}", color);
+        }
+        assert_eq!(
+            parse_color("#11223380").unwrap(),
+            image::Rgba([0x11, 0x22, 0x33, 0x80])
+        );
+    }
+
+    /// Create `name` in `dir`, last modified `age` ago
+    fn file_aged(dir: &Path, name: &str, age: Duration) -> PathBuf {
+        let path = dir.join(name);
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - age)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn sweep_removes_only_old_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let hours = |h: u64| Duration::from_secs(h * 60 * 60);
+        let old = file_aged(dir.path(), "roura_screenshot_old.png", hours(2));
+        let new = file_aged(dir.path(), "roura_screenshot_new.png", Duration::ZERO);
+        let unrelated = file_aged(dir.path(), "other_old.png", hours(2));
+        std::fs::create_dir(dir.path().join("roura_screenshot_dir")).unwrap();
+
+        let removed = sweep_old_files(dir.path(), Some(CAPTURE_TEMP_PREFIX), hours(1));
+
+        assert_eq!(removed, 1);
+        assert!(!old.exists());
+        assert!(new.exists());
+        assert!(unrelated.exists());
+        assert!(dir.path().join("roura_screenshot_dir").is_dir());
+
+        // Without a prefix, everything old goes
+        assert_eq!(sweep_old_files(dir.path(), None, hours(1)), 1);
+        assert!(!unrelated.exists());
+    }
+
+    #[test]
+    fn sweeping_a_missing_dir_removes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        assert_eq!(sweep_old_files(&missing, None, Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn encoded_formats_decode_to_the_captured_size() {
+        for format in ["png", "jpeg", "webp", "bmp"] {
+            let output = OutputOptions::new(Some(format.to_string()), Some(80)).unwrap();
+            let img = image::DynamicImage::ImageRgba8(checkerboard(37));
+            let cropped = img.crop_imm(0, 0, 37, 21);
+            let result = finish_capture(cropped, None, &output).unwrap();
+
+            assert_eq!(result.format, format);
+            let decoded = decode_base64_image(result.data.as_deref().unwrap());
+            assert_eq!((decoded.width(), decoded.height()), (37, 21), "{}", format);
+        }
+    }
+
+    #[test]
+    fn webp_quality_warns_when_only_lossless_is_built() {
+        let img = image::DynamicImage::new_rgba8(4, 4);
+
+        let requested = OutputOptions::new(Some("webp".to_string()), Some(80)).unwrap();
+        let result = finish_capture(img.clone(), None, &requested).unwrap();
+        assert_eq!(result.warning.is_some(), !cfg!(feature = "webp-lossy"));
+
+        let default = OutputOptions::new(Some("webp".to_string()), None).unwrap();
+        assert!(finish_capture(img, None, &default)
+            .unwrap()
+            .warning
+            .is_none());
+    }
+
+    #[cfg(not(feature = "avif"))]
+    #[test]
+    fn avif_falls_back_to_png_with_a_warning() {
+        let output = OutputOptions::new(Some("avif".to_string()), None).unwrap();
+        let result = finish_capture(image::DynamicImage::new_rgba8(5, 3), None, &output).unwrap();
+
+        assert_eq!(result.format, "png");
+        assert!(result.warning.unwrap().contains("AVIF"));
+        let decoded = decode_base64_image(result.data.as_deref().unwrap());
+        assert_eq!((decoded.width(), decoded.height()), (5, 3));
+    }
+
+    #[test]
+    fn reported_size_and_hash_match_the_saved_file() {
+        use sha2::Digest;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shot.png").to_string_lossy().into_owned();
+        let mut output = OutputOptions::new(None, None).unwrap();
+        output.hash = true;
+
+        let img = image::DynamicImage::ImageRgba8(checkerboard(16));
+        let result = finish_capture(img, Some(path.clone()), &output).unwrap();
+
+        let saved = std::fs::read(&path).unwrap();
+        assert_eq!(result.byte_size, saved.len() as u64);
+        assert_eq!(
+            result.sha256.as_deref(),
+            Some(format!("{:x}", sha2::Sha256::digest(&saved)).as_str())
+        );
+
+        output.hash = false;
+        let result = finish_capture(image::DynamicImage::new_rgba8(2, 2), None, &output).unwrap();
+        assert!(result.sha256.is_none());
+        assert!(result.byte_size > 0);
+    }
+
+    /// Rows of a window scrolled to `offset`: two header rows, a `view`-row
+    /// viewport onto distinct rows of the page, then one footer row
+    fn window_rows(offset: u8, view: u8) -> Vec<[u8; 4]> {
+        let mut rows = vec![[255, 0, 0, 255], [255, 255, 0, 255]];
+        rows.extend((offset..offset + view).map(|r| [r, 100, 0, 255]));
+        rows.push([0, 0, 255, 255]);
+        rows
+    }
+
+    fn scrolled_frame(offset: u8, view: u8) -> ScrollFrame {
+        let rows = window_rows(offset, view);
+        let img =
+            image::RgbaImage::from_fn(3, rows.len() as u32, |_, y| image::Rgba(rows[y as usize]));
+        (row_hashes(&img), img)
+    }
+
+    fn row_colors(img: &image::RgbaImage) -> Vec<[u8; 4]> {
+        (0..img.height()).map(|y| img.get_pixel(0, y).0).collect()
+    }
+
+    #[test]
+    fn stitched_frames_show_each_page_row_once() {
+        // Scrolls of 4, 4 and then 2 rows at the end of the page
+        let frames = [0, 4, 8, 10].map(|offset| scrolled_frame(offset, 10));
+
+        let stitched = stitch_frames(&frames);
+        assert_eq!(stitched.width(), 3);
+        assert_eq!(row_colors(&stitched), window_rows(0, 20));
+    }
+
+    #[test]
+    fn frames_without_overlap_are_appended_whole() {
+        let frames = [scrolled_frame(0, 5), scrolled_frame(5, 5)];
+        assert_eq!(row_colors(&stitch_frames(&frames)), window_rows(0, 10));
+    }
+
+    #[test]
+    fn a_single_frame_is_returned_as_is() {
+        let frame = scrolled_frame(0, 4);
+        assert_eq!(stitch_frames(std::slice::from_ref(&frame)), frame.1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_gallery_saves_all_reach_the_index() {
+        let project = tempfile::tempdir().unwrap();
+        let project_path = project.path().to_string_lossy().into_owned();
+        let mut png = Vec::new();
+        checkerboard(4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let source = STANDARD.encode(&png);
+
+        let saves: Vec<_> = (0..8)
+            .map(|_| {
+                tokio::spawn(save_screenshot_to_project(
+                    project_path.clone(),
+                    source.clone(),
+                    Vec::new(),
+                    None,
+                ))
+            })
+            .collect();
+        let mut saved = Vec::new();
+        for save in saves {
+            saved.push(save.await.unwrap().unwrap().id);
+        }
+
+        let listed = list_project_screenshots(project_path, None).await.unwrap();
+        assert_eq!(listed.len(), saved.len());
+        assert!(saved.iter().all(|id| listed.iter().any(|e| &e.id == id)));
+    }
 }
@@ -0,0 +1,134 @@
+// Roura Agent Desktop - Global Shortcuts
+// © Roura.io
+
+use crate::{commands, screenshot};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Listener};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Config key holding the region capture accelerator
+const CAPTURE_REGION_KEY: &str = "shortcuts.capture_region";
+
+const DEFAULT_CAPTURE_REGION_SHORTCUT: &str = "CommandOrControl+Shift+R";
+
+/// Shortcut currently bound to region capture
+static CAPTURE_SHORTCUT: Mutex<Option<Shortcut>> = Mutex::new(None);
+
+/// Install the global shortcut plugin and bind the configured shortcuts
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    app.plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, shortcut, event| {
+                if event.state() != ShortcutState::Pressed {
+                    return;
+                }
+
+                let is_capture = CAPTURE_SHORTCUT
+                    .lock()
+                    .map(|s| s.as_ref() == Some(shortcut))
+                    .unwrap_or(false);
+                if is_capture {
+                    tauri::async_runtime::spawn(capture_and_send(app.clone()));
+                }
+            })
+            .build(),
+    )
+    .map_err(|e| format!("Failed to install global shortcut plugin: {}", e))?;
+
+    register_capture_shortcut(app);
+
+    // Rebind when the accelerator is edited
+    let handle = app.clone();
+    app.listen("config-changed", move |event| {
+        let changed: serde_json::Value =
+            serde_json::from_str(event.payload()).unwrap_or_default();
+        let affected = changed
+            .get("keys")
+            .and_then(|k| k.as_array())
+            .is_some_and(|keys| keys.iter().any(|k| k.as_str() == Some(CAPTURE_REGION_KEY)));
+        if affected {
+            register_capture_shortcut(&handle);
+        }
+    });
+
+    Ok(())
+}
+
+/// Bind region capture to the configured accelerator, replacing any previous binding.
+///
+/// Failures, such as the accelerator being taken by another app, are reported
+/// with a `shortcut-error` event.
+fn register_capture_shortcut(app: &AppHandle) {
+    let accelerator = commands::config_value(CAPTURE_REGION_KEY)
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| DEFAULT_CAPTURE_REGION_SHORTCUT.to_string());
+
+    // Not held while (un)registering, which the shortcut handler would contend with
+    let previous = CAPTURE_SHORTCUT.lock().ok().and_then(|mut s| s.take());
+    if let Some(old) = previous {
+        let _ = app.global_shortcut().unregister(old);
+    }
+
+    let result = accelerator
+        .parse::<Shortcut>()
+        .map_err(|e| format!("Invalid shortcut: {}", e))
+        .and_then(|shortcut| {
+            app.global_shortcut()
+                .register(shortcut)
+                .map(|_| shortcut)
+                .map_err(|e| format!("Failed to register shortcut: {}", e))
+        });
+
+    match result {
+        Ok(shortcut) => {
+            if let Ok(mut current) = CAPTURE_SHORTCUT.lock() {
+                *current = Some(shortcut);
+            }
+        }
+        Err(error) => {
+            let _ = app.emit(
+                "shortcut-error",
+                serde_json::json!({
+                    "key": CAPTURE_REGION_KEY,
+                    "shortcut": accelerator,
+                    "error": error,
+                }),
+            );
+        }
+    }
+}
+
+/// Let the user pick a region and send it to the agent as an attachment
+async fn capture_and_send(app: AppHandle) {
+    let path = std::env::temp_dir().join(format!("roura_shortcut_{}.png", uuid::Uuid::new_v4()));
+    let path = path.to_string_lossy().into_owned();
+
+    let screenshot = match screenshot::capture_interactive_native(Some(path.clone())).await {
+        Ok(Some(screenshot)) => screenshot,
+        // Selection was cancelled
+        Ok(None) => return,
+        Err(e) => {
+            let _ = app.emit(
+                "shortcut-error",
+                serde_json::json!({ "key": CAPTURE_REGION_KEY, "error": e.to_string() }),
+            );
+            return;
+        }
+    };
+
+    // Let the UI show the capture alongside the streamed reply
+    let _ = app.emit("shortcut-capture", &screenshot);
+
+    let message = commands::AgentMessage {
+        content: String::new(),
+        attachments: Some(vec![path.clone()]),
+        context: None,
+        request_id: None,
+        include_memory: false,
+    };
+    if let Err(e) = commands::send_message(app.clone(), message, None).await {
+        tracing::error!(error = %e, "Failed to send captured region");
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
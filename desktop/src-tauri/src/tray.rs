@@ -0,0 +1,113 @@
+// Roura Agent Desktop - System Tray
+// © Roura.io
+
+use crate::backend;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconEvent};
+use tauri::{AppHandle, Listener, Manager};
+
+/// Id of the tray icon declared in tauri.conf.json
+const TRAY_ID: &str = "main";
+
+/// Attach the quick-action menu to the configured tray icon
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or("Tray icon is not configured")?;
+
+    let item = |id: &str, text: &str, enabled: bool| {
+        MenuItem::with_id(app, id, text, enabled, None::<&str>)
+            .map_err(|e| format!("Failed to create tray menu: {}", e))
+    };
+    let toggle = item("toggle_window", "Show/Hide Window", true)?;
+    let start = item("start_backend", "Start Backend", true)?;
+    let stop = item("stop_backend", "Stop Backend", false)?;
+    let quit = item("quit", "Quit Roura Agent", true)?;
+    let separator = || {
+        PredefinedMenuItem::separator(app)
+            .map_err(|e| format!("Failed to create tray menu: {}", e))
+    };
+
+    let menu = Menu::with_items(
+        app,
+        &[&toggle, &separator()?, &start, &stop, &separator()?, &quit],
+    )
+    .map_err(|e| format!("Failed to create tray menu: {}", e))?;
+
+    tray.set_menu(Some(menu))
+        .map_err(|e| format!("Failed to set tray menu: {}", e))?;
+    // Left click toggles the window, the menu stays on right click
+    let _ = tray.set_show_menu_on_left_click(false);
+    let _ = tray.set_tooltip(Some("Roura Agent - backend stopped"));
+
+    tray.on_menu_event(|app, event| match event.id().as_ref() {
+        "toggle_window" => toggle_window(app),
+        "start_backend" => {
+            tauri::async_runtime::spawn(async {
+                let started =
+                    backend::start_backend(None, None, Some(true), None, None, None).await;
+                if let Err(e) = started {
+                    tracing::error!(error = %e, "Failed to start backend from tray");
+                }
+            });
+        }
+        "stop_backend" => {
+            tauri::async_runtime::spawn(async {
+                if let Err(e) = backend::stop_backend().await {
+                    tracing::error!(error = %e, "Failed to stop backend from tray");
+                }
+            });
+        }
+        "quit" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = backend::stop_backend().await;
+                app.exit(0);
+            });
+        }
+        _ => {}
+    });
+
+    tray.on_tray_icon_event(|tray, event| {
+        if let TrayIconEvent::Click {
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } = event
+        {
+            toggle_window(tray.app_handle());
+        }
+    });
+
+    // Keep the tooltip and menu in step with the backend
+    app.listen("backend-status", move |event| {
+        let status: serde_json::Value =
+            serde_json::from_str(event.payload()).unwrap_or_default();
+        let port = status.get("port").and_then(|p| p.as_u64());
+
+        let tooltip = match port {
+            Some(port) => format!("Roura Agent - backend running on port {}", port),
+            None => "Roura Agent - backend stopped".to_string(),
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+        let _ = start.set_enabled(port.is_none());
+        let _ = stop.set_enabled(port.is_some());
+    });
+
+    Ok(())
+}
+
+/// Show the main window if hidden, otherwise hide it
+fn toggle_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+
+    if window.is_visible().unwrap_or(false) {
+        let _ = window.hide();
+    } else {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
@@ -0,0 +1,114 @@
+// Roura Agent Desktop - Config File Watcher
+// © Roura.io
+
+use crate::commands;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Quiet period before a burst of file events is treated as one change
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watcher for the current config root; replacing it ends the old watcher's thread
+static WATCHER: Mutex<Option<RecommendedWatcher>> = Mutex::new(None);
+
+/// Config the app wrote and already announced, so the watcher stays quiet about it
+static ANNOUNCED: Mutex<Option<BTreeMap<String, serde_json::Value>>> = Mutex::new(None);
+
+/// Skip `config-changed` for the config as it is now, after the caller emitted its own
+pub fn mark_announced() {
+    if let Ok(mut announced) = ANNOUNCED.lock() {
+        *announced = Some(snapshot());
+    }
+}
+
+/// Whether `current` is the config from the last `mark_announced`, consuming the mark
+fn was_announced(current: &BTreeMap<String, serde_json::Value>) -> bool {
+    let Ok(mut announced) = ANNOUNCED.lock() else {
+        return false;
+    };
+    announced.take().as_ref() == Some(current)
+}
+
+/// Watch the config directory and emit `config-changed` when config.json is edited.
+///
+/// Calling it again, e.g. after `set_config_root`, moves the watch to the new root.
+pub fn start(app: AppHandle) -> Result<(), String> {
+    let config_dir = commands::config_root()?;
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let (tx, rx) = mpsc::channel();
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if event.paths.iter().any(|p| is_config_file(p)) {
+                let _ = tx.send(());
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to create config watcher: {}", e))?;
+
+    watcher
+        .watch(&config_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch config directory: {}", e))?;
+
+    // Dropping the previous watcher closes its channel, which ends its thread
+    *WATCHER.lock().map_err(|e| e.to_string())? = Some(watcher);
+
+    std::thread::spawn(move || {
+        let mut cached = snapshot();
+
+        while rx.recv().is_ok() {
+            // Wait for the burst to settle, e.g. temp write followed by rename
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+
+            let current = snapshot();
+            let changed = changed_keys(&cached, &current);
+            let announced = was_announced(&current);
+            cached = current;
+
+            if !changed.is_empty() && !announced {
+                let _ = app.emit("config-changed", serde_json::json!({ "keys": changed }));
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Only config.json itself matters; temp files from atomic writes are ignored
+fn is_config_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+
+    // Atomic writes land as ".config.json.<id>.tmp" first, which never matches
+    name == "config.json"
+}
+
+/// Flattened view of the current config, or empty if it can't be read
+fn snapshot() -> BTreeMap<String, serde_json::Value> {
+    commands::load_config()
+        .map(|config| commands::flatten_config(&config))
+        .unwrap_or_default()
+}
+
+/// Dotted keys added, removed, or changed between two snapshots
+fn changed_keys(
+    old: &BTreeMap<String, serde_json::Value>,
+    new: &BTreeMap<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut keys: Vec<String> = new
+        .iter()
+        .filter(|(k, v)| old.get(*k) != Some(*v))
+        .map(|(k, _)| k.clone())
+        .collect();
+
+    keys.extend(old.keys().filter(|k| !new.contains_key(*k)).cloned());
+    keys.sort();
+    keys
+}
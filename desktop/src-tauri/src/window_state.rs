@@ -0,0 +1,142 @@
+// Roura Agent Desktop - Window State
+// © Roura.io
+
+use crate::commands;
+use std::sync::Mutex;
+use tauri::{Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window};
+
+/// Last known geometry of the main window, in physical pixels
+#[derive(Debug, Clone, Copy)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+/// Updated on every move/resize and written to config on close
+static LAST_GEOMETRY: Mutex<Option<WindowGeometry>> = Mutex::new(None);
+
+/// How much of a restored window must overlap a monitor to count as on-screen
+const MIN_VISIBLE: i64 = 100;
+
+/// Apply the geometry saved under `window.*`, keeping the window on a connected monitor
+pub fn restore(window: &WebviewWindow) {
+    let int = |key: &str| commands::config_value(key).and_then(|v| v.as_i64());
+    let flag = |key: &str| {
+        commands::config_value(key)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    };
+
+    let monitors = window.available_monitors().unwrap_or_default();
+
+    if let (Some(width), Some(height)) = (int("window.width"), int("window.height")) {
+        let mut size = PhysicalSize::new(width.max(1) as u32, height.max(1) as u32);
+
+        // Never larger than the biggest monitor
+        if let Some(largest) = monitors.iter().map(|m| m.work_area().size).max_by_key(|s| {
+            s.width as u64 * s.height as u64
+        }) {
+            size.width = size.width.min(largest.width);
+            size.height = size.height.min(largest.height);
+        }
+        let _ = window.set_size(size);
+    }
+
+    if let (Some(x), Some(y)) = (int("window.x"), int("window.y")) {
+        let size = window
+            .outer_size()
+            .unwrap_or_else(|_| PhysicalSize::new(0, 0));
+        let position = PhysicalPosition::new(x as i32, y as i32);
+
+        if monitors.iter().any(|m| is_visible_on(m, position, size)) {
+            let _ = window.set_position(position);
+        } else {
+            // Saved on a monitor that's no longer connected
+            let _ = window.center();
+        }
+    }
+
+    if flag("window.maximized") {
+        let _ = window.maximize();
+    }
+    if flag("window.fullscreen") {
+        let _ = window.set_fullscreen(true);
+    }
+}
+
+/// Whether enough of a window at `position` would land on `monitor` to be grabbed
+fn is_visible_on(monitor: &Monitor, position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> bool {
+    let area = monitor.work_area();
+
+    let left = (position.x as i64).max(area.position.x as i64);
+    let right = (position.x as i64 + size.width as i64)
+        .min(area.position.x as i64 + area.size.width as i64);
+    let top = (position.y as i64).max(area.position.y as i64);
+    let bottom = (position.y as i64 + size.height as i64)
+        .min(area.position.y as i64 + area.size.height as i64);
+
+    right - left >= MIN_VISIBLE.min(size.width as i64)
+        && bottom - top >= MIN_VISIBLE.min(size.height as i64)
+}
+
+/// Record the window's current geometry after a move or resize
+pub fn track(window: &Window) {
+    let maximized = window.is_maximized().unwrap_or(false);
+    let fullscreen = window.is_fullscreen().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+
+    let Ok(mut last) = LAST_GEOMETRY.lock() else {
+        return;
+    };
+
+    // Keep the normal bounds while maximized, fullscreen or minimized so
+    // restoring out of those states lands where the user left the window
+    if maximized || fullscreen || minimized {
+        if let Some(geometry) = last.as_mut() {
+            if !minimized {
+                geometry.maximized = maximized;
+                geometry.fullscreen = fullscreen;
+            }
+        }
+        return;
+    }
+
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+
+    *last = Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: false,
+        fullscreen: false,
+    });
+}
+
+/// Write the last tracked geometry to config under `window.*`
+pub fn save() -> Result<(), String> {
+    let Some(geometry) = *LAST_GEOMETRY.lock().map_err(|e| e.to_string())? else {
+        return Ok(());
+    };
+
+    let mut config = commands::load_config()?;
+    let values = [
+        ("window.x", serde_json::json!(geometry.x)),
+        ("window.y", serde_json::json!(geometry.y)),
+        ("window.width", serde_json::json!(geometry.width)),
+        ("window.height", serde_json::json!(geometry.height)),
+        ("window.maximized", serde_json::json!(geometry.maximized)),
+        ("window.fullscreen", serde_json::json!(geometry.fullscreen)),
+    ];
+    for (key, value) in values {
+        commands::insert_config(&mut config, key, value)?;
+    }
+
+    Ok(commands::save_config(&config)?)
+}